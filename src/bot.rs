@@ -4,198 +4,1711 @@
 
 mod answer;
 mod automatize;
+mod caption;
 mod commands;
 mod config;
+mod dialogue;
+mod generator;
+mod greeting;
+mod i18n;
+mod inline;
+mod keyboard;
+mod media;
+mod mention;
 mod providers;
+mod rating;
 mod repository;
+mod watermark;
+mod weather;
 
-use std::sync::OnceLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
-use answer::{Answer, AnswerBuilder};
-use automatize::Automatizer;
-use buongiornissimo_rs::{Greeting, ScrapeError, ScrapeResult};
-use chrono::{Local, NaiveDate};
-use commands::Command;
+use answer::{Answer, AnswerBuilder, Markup};
+pub use automatize::Automatizer;
+use buongiornissimo_rs::{Greeting, ScrapeError};
+use caption::{Placeholder, Template, TemplateError};
+use commands::{Command, WatermarkSetting};
 pub use config::Config;
-use providers::Providers;
+use dialogue::{BirthdayDialogue, BirthdayState, BirthdayStorage};
+use futures::StreamExt as _;
+use futures::stream;
+use i18n::{Key, Language, UserError};
+use inline::InlineCache;
+use keyboard::CallbackData;
+use media::{MediaKind, Validation};
+use providers::{GreetingImage, ImagePicker, ImagesError, ImagesResult, Providers};
 use rand::rng;
 use rand::seq::SliceRandom;
+use repository::Repository;
+use teloxide::dispatching::UpdateHandler;
 use teloxide::prelude::*;
+use teloxide::types::{FileId, InputFile, Me, MessageEntity, User, UserId};
 use teloxide::utils::command::BotCommands;
 use tracing::{debug, error, info};
 use url::Url;
+use watermark::Mark;
 
+use crate::metrics::METRICS;
 use crate::repository::SqliteDb;
-use crate::utils::random as random_utils;
+use crate::repository::delivery::Delivery;
+use crate::utils::date::PartialDate;
+use crate::utils::shutdown as shutdown_utils;
 
-pub static AUTOMATIZER: OnceLock<Automatizer> = OnceLock::new();
+/// The result of the update handlers
+type HandlerResult = anyhow::Result<()>;
+
+/// Maximum amount of invalid or duplicate images discarded for each provider before giving up on it
+const MAX_DISCARDED_IMAGES: usize = 3;
+/// Maximum amount of images of a provider validated at the same time
+const MAX_CONCURRENT_VALIDATIONS: usize = 4;
+/// Name of the job recorded in the delivery log for the greetings sent on request
+const ON_DEMAND_JOB: &str = "on_demand";
+
+/// The services the command handler depends on
+#[derive(Clone)]
+struct Services {
+    admins: Arc<Vec<UserId>>,
+    automatizer: Arc<Automatizer>,
+    dialogue_storage: Arc<BirthdayStorage>,
+    repository: Repository,
+}
 
 /// Buongiornissimo bot application
 pub struct Buongiornissimo {
+    admins: Arc<Vec<UserId>>,
+    automatizer: Arc<Automatizer>,
     bot: Bot,
+    dialogue_storage: Arc<BirthdayStorage>,
+    repository: Repository,
+    shutdown_deadline: Duration,
 }
 
 impl Buongiornissimo {
     /// Initialize the bot
     pub async fn init() -> anyhow::Result<Self> {
         let config = Config::try_from_env()?;
+        media::set_placeholder_hashes(config.placeholder_hashes);
+        if let Some(location) = config.weather_location.as_deref() {
+            weather::set_location(location);
+        }
         let bot = Bot::from_env();
         let db = SqliteDb::connect(&config.database_url)
             .await
             .map_err(|e| anyhow::anyhow!("failed to connect to the database: {}", e))?;
-        let automatizer = Automatizer::start(db, bot.clone())
+        if let Some(address) = config.metrics_address {
+            crate::http::serve_metrics(address).await?;
+        }
+        let repository = Repository::new(db.clone());
+        let automatizer =
+            Automatizer::start(repository.clone(), bot.clone(), config.daily_greeting)
+                .await
+                .map_err(|e| anyhow::anyhow!("failed to start automatizer: {}", e))?;
+        if let Some(address) = config.health_address {
+            crate::http::serve_health(address, db.clone(), automatizer.clone()).await?;
+        }
+        let dialogue_storage = dialogue::open_storage(&config.database_url).await?;
+        let admins = config.admins.iter().copied().map(UserId).collect();
+        Ok(Self {
+            admins: Arc::new(admins),
+            automatizer,
+            bot,
+            dialogue_storage,
+            repository,
+            shutdown_deadline: Duration::from_secs(config.shutdown_deadline_secs),
+        })
+    }
+
+    /// Print delivery statistics to stdout, without starting the bot
+    pub async fn print_stats() -> anyhow::Result<()> {
+        let config = Config::try_from_env()?;
+        let db = SqliteDb::connect(&config.database_url)
             .await
-            .map_err(|e| anyhow::anyhow!("failed to start automatizer: {}", e))?;
-        if AUTOMATIZER.set(automatizer).is_err() {
-            anyhow::bail!("failed to set automatizer");
-        };
-        Ok(Self { bot })
+            .map_err(|e| anyhow::anyhow!("failed to connect to the database: {}", e))?;
+        let stats = Repository::new(db).get_delivery_stats().await?;
+        println!("{stats}");
+        Ok(())
     }
 
-    /// Run the bot
+    /// Run the bot until a shutdown signal is received
     pub async fn run(self) -> anyhow::Result<()> {
         info!("running bot without webhooks");
-        Command::repl(self.bot, Self::answer).await;
+        let mut dispatcher = Dispatcher::builder(self.bot, Self::handler())
+            .dependencies(dptree::deps![
+                self.admins,
+                self.automatizer.clone(),
+                self.repository,
+                self.dialogue_storage,
+                Arc::new(InlineCache::default())
+            ])
+            .default_handler(|_| async {})
+            .build();
+
+        let shutdown_token = dispatcher.shutdown_token();
+        let automatizer = self.automatizer.clone();
+        tokio::spawn(async move {
+            shutdown_utils::wait_for_signal().await;
+            info!("stopping dispatcher");
+            automatizer.stop_accepting_jobs();
+            match shutdown_token.shutdown() {
+                Ok(stopped) => stopped.await,
+                Err(err) => error!("failed to stop dispatcher: {}", err),
+            }
+        });
+        dispatcher.dispatch().await;
+
+        self.automatizer.shutdown(self.shutdown_deadline).await;
+        info!("bye!");
         Ok(())
     }
 
+    /// Build the update handler
+    fn handler() -> UpdateHandler<anyhow::Error> {
+        dptree::entry()
+            .branch(
+                Update::filter_message()
+                    .inspect_async(Self::remember_author)
+                    .enter_dialogue::<Message, BirthdayStorage, BirthdayState>()
+                    .branch(
+                        dptree::entry()
+                            .filter_command::<Command>()
+                            .map(
+                                |admins: Arc<Vec<UserId>>,
+                                 automatizer: Arc<Automatizer>,
+                                 dialogue_storage: Arc<BirthdayStorage>,
+                                 repository: Repository| Services {
+                                    admins,
+                                    automatizer,
+                                    dialogue_storage,
+                                    repository,
+                                },
+                            )
+                            .endpoint(Self::answer),
+                    )
+                    .branch(
+                        dptree::filter_map(|message: Message, me: Me| {
+                            let text = message.text().or_else(|| message.caption())?;
+                            Command::usage_hint(text, me.username())
+                        })
+                        .endpoint(Self::answer_usage),
+                    )
+                    .branch(dialogue::message_handler()),
+            )
+            .branch(
+                Update::filter_callback_query()
+                    .enter_dialogue::<CallbackQuery, BirthdayStorage, BirthdayState>()
+                    .endpoint(Self::answer_callback),
+            )
+            .branch(Update::filter_inline_query().endpoint(inline::answer))
+            .branch(Update::filter_chosen_inline_result().endpoint(inline::chosen))
+    }
+
     /// Answer handler for bot
-    async fn answer(bot: Bot, message: Message, command: Command) -> ResponseResult<()> {
+    async fn answer(
+        bot: Bot,
+        message: Message,
+        command: Command,
+        services: Services,
+        dialogue: BirthdayDialogue,
+    ) -> HandlerResult {
+        let Services {
+            admins,
+            automatizer,
+            dialogue_storage,
+            repository,
+        } = services;
         debug!("got command {:?}", command);
+        METRICS.command_received(command.name());
+        let lang = Self::language(&repository, message.chat.id, message.from.as_ref()).await;
         let answer = match command {
-            Command::Help => Answer::simple_text(Command::descriptions()),
-            Command::Start => Self::start(),
-            Command::Auguri { name } => Self::happy_birthday(&name).await,
-            Command::Buongiornissimo => Self::get_buongiornissimo_buongiorno().await,
-            Command::Buonanotte => Self::get_buongiornissimo(Greeting::BuonaNotte).await,
-            Command::Buonpomeriggio => Self::get_buongiornissimo(Greeting::BuonPomeriggio).await,
-            Command::Buonpranzo => Self::get_buongiornissimo(Greeting::BuonPranzo).await,
-            Command::Buonacena => Self::get_buongiornissimo(Greeting::BuonaCena).await,
-            Command::Buonaserata => Self::get_buongiornissimo(Greeting::BuonaSerata).await,
-            Command::Buonweekend => Self::get_buongiornissimo(Greeting::Weekend).await,
+            Command::Album { amount: Some(_) } if !Self::is_admin(&message, &admins) => {
+                Answer::simple_text(lang.text(Key::AdminOnly))
+            }
+            Command::Album {
+                amount: Some(amount),
+            } => Self::set_album_size(&repository, &message.chat.id, amount, lang).await,
+            Command::Album { amount: None } => {
+                Self::get_album_size(&repository, &message.chat.id, lang).await
+            }
+            Command::Annulla => dialogue::cancel(&dialogue, message.from.as_ref(), lang).await?,
+            Command::Help => Self::help(lang),
+            Command::Start => Self::start(lang),
+            Command::Menu => Self::menu(lang),
+            Command::Auguri { name } => {
+                Self::happy_birthday(&repository, message.chat.id, &name, lang).await
+            }
+            Command::Buongiornissimo { amount } => {
+                Self::get_buongiornissimo_buongiorno(
+                    &repository,
+                    automatizer.daily_greeting(),
+                    &message,
+                    amount,
+                    lang,
+                )
+                .await
+            }
+            Command::Buonanotte { amount } => {
+                Self::get_buongiornissimo_for(
+                    &repository,
+                    &message,
+                    Greeting::BuonaNotte,
+                    amount,
+                    lang,
+                )
+                .await
+            }
+            Command::Buonpomeriggio { amount } => {
+                Self::get_buongiornissimo_for(
+                    &repository,
+                    &message,
+                    Greeting::BuonPomeriggio,
+                    amount,
+                    lang,
+                )
+                .await
+            }
+            Command::Buonpranzo { amount } => {
+                Self::get_buongiornissimo_for(
+                    &repository,
+                    &message,
+                    Greeting::BuonPranzo,
+                    amount,
+                    lang,
+                )
+                .await
+            }
+            Command::Buonacena { amount } => {
+                Self::get_buongiornissimo_for(
+                    &repository,
+                    &message,
+                    Greeting::BuonaCena,
+                    amount,
+                    lang,
+                )
+                .await
+            }
+            Command::Buonaserata { amount } => {
+                Self::get_buongiornissimo_for(
+                    &repository,
+                    &message,
+                    Greeting::BuonaSerata,
+                    amount,
+                    lang,
+                )
+                .await
+            }
+            Command::Buonweekend { amount } => {
+                Self::get_buongiornissimo_for(
+                    &repository,
+                    &message,
+                    Greeting::Weekend,
+                    amount,
+                    lang,
+                )
+                .await
+            }
+            Command::CancellaDidascalia { .. } if !Self::is_admin(&message, &admins) => {
+                Answer::simple_text(lang.text(Key::AdminOnly))
+            }
+            Command::CancellaDidascalia { greeting } => {
+                Self::delete_caption(&repository, &message.chat.id, greeting, lang).await
+            }
+            Command::CancellaFiligrana if !Self::is_admin(&message, &admins) => {
+                Answer::simple_text(lang.text(Key::AdminOnly))
+            }
+            Command::CancellaFiligrana => {
+                Self::delete_watermark(&repository, &message.chat.id, lang).await
+            }
             Command::Compleanno { name, date } => {
-                Self::subscribe_birthday(&message.chat.id, name, date).await
+                match Self::birthday_target(&repository, &message, name).await {
+                    Some((name, user)) => {
+                        Self::subscribe_birthday(
+                            &automatizer,
+                            &message.chat.id,
+                            name,
+                            date,
+                            user,
+                            lang,
+                        )
+                        .await
+                    }
+                    None => Answer::simple_text(lang.text(Key::BirthdayTargetMissing)),
+                }
+            }
+            Command::Didascalia {
+                template: Some(_), ..
+            } if !Self::is_admin(&message, &admins) => {
+                Answer::simple_text(lang.text(Key::AdminOnly))
+            }
+            Command::Didascalia {
+                greeting,
+                template: Some(template),
+            } => Self::set_caption(&repository, &message.chat.id, greeting, &template, lang).await,
+            Command::Didascalia {
+                greeting,
+                template: None,
+            } => Self::get_caption(&repository, &message.chat.id, greeting, lang).await,
+            Command::Dimenticami => {
+                Self::forget_user(&automatizer, dialogue_storage, message.from.as_ref(), lang)
+                    .await?
+            }
+            Command::Filigrana { setting: Some(_) } if !Self::is_admin(&message, &admins) => {
+                Answer::simple_text(lang.text(Key::AdminOnly))
+            }
+            Command::Filigrana {
+                setting: Some(setting),
+            } => Self::set_watermark(&repository, &message.chat.id, setting, lang).await,
+            Command::Filigrana { setting: None } => {
+                Self::get_watermark(&repository, &message.chat.id, lang).await
+            }
+            Command::Genera { greeting } => {
+                let media = match greeting {
+                    Some(greeting) => greeting,
+                    None => {
+                        Self::greeting_of_the_day(&repository, automatizer.daily_greeting()).await
+                    }
+                };
+                Self::generate(&repository, &message, media, lang).await
+            }
+            Command::IlMioCompleanno { date } => {
+                Self::set_own_birthday(&automatizer, &message, date, lang).await
+            }
+            Command::Lingua {
+                language: Some(language),
+            } => Self::set_language(&repository, &message.chat.id, language).await,
+            Command::Lingua { language: None } => Self::languages_menu(lang),
+            Command::NuovoCompleanno => {
+                dialogue::start(&dialogue, message.from.as_ref(), lang).await?
+            }
+            Command::Preferiti => {
+                Self::favourites(&repository, message.from.as_ref(), 0, lang).await
+            }
+            Command::Proponi { greeting } => {
+                Self::submit_image(&bot, &repository, &message, greeting, &admins, lang).await
+            }
+            Command::Caffeee => {
+                Self::subscribe_to_automatizer(&automatizer, &message.chat.id, lang).await
+            }
+            Command::PuliziaKontatti => {
+                Self::unsubscribe_from_automatizer(&automatizer, &message.chat.id, lang).await
+            }
+            Command::Release => Self::get_release(lang),
+            Command::Statistiche => {
+                Self::delivery_stats(&repository, &message, &admins, lang).await
+            }
+        };
+
+        let mut answer = answer.reply_to(&message);
+        let delivery = answer.take_delivery();
+        let result = answer.send(&bot, message.chat.id).await;
+        if let Some(delivery) = delivery {
+            Self::log_delivery(&repository, delivery, &result).await;
+        }
+        result?;
+        Ok(())
+    }
+
+    /// Reply with the usage of a command which has been sent with bad arguments
+    async fn answer_usage(
+        bot: Bot,
+        message: Message,
+        usage: Key,
+        repository: Repository,
+    ) -> HandlerResult {
+        debug!("got command with bad arguments {:?}", message.text());
+        let lang = Self::language(&repository, message.chat.id, message.from.as_ref()).await;
+        Answer::simple_text(lang.text(usage))
+            .reply_to(&message)
+            .send(&bot, message.chat.id)
+            .await?;
+        Ok(())
+    }
+
+    /// Callback query handler for inline keyboard buttons
+    async fn answer_callback(
+        bot: Bot,
+        query: CallbackQuery,
+        automatizer: Arc<Automatizer>,
+        repository: Repository,
+        admins: Arc<Vec<UserId>>,
+        dialogue: BirthdayDialogue,
+    ) -> HandlerResult {
+        let Some(data) = query.data.as_deref().and_then(CallbackData::parse) else {
+            debug!("ignoring callback query with unknown data {:?}", query.data);
+            bot.answer_callback_query(query.id.clone()).await?;
+            return Ok(());
+        };
+        let Some(chat_id) = query.message.as_ref().map(|message| message.chat().id) else {
+            debug!("ignoring callback query without message");
+            bot.answer_callback_query(query.id.clone()).await?;
+            return Ok(());
+        };
+        debug!("got callback query {:?}", data);
+        // votes are confirmed with a popup instead of a message, not to flood the chat;
+        // the other buttons are acknowledged right away, since their answer may take a while
+        let is_vote = matches!(data, CallbackData::Vote { .. });
+        if !is_vote {
+            bot.answer_callback_query(query.id.clone()).await?;
+            METRICS.command_received("callback");
+        }
+        let lang = Self::language(&repository, chat_id, Some(&query.from)).await;
+        let mut answer = match data {
+            CallbackData::Vote { image, up } => {
+                let text = Self::vote(&repository, &query.from, chat_id, image, up, lang).await;
+                bot.answer_callback_query(query.id.clone())
+                    .text(text)
+                    .await?;
+                return Ok(());
+            }
+            CallbackData::Greeting(greeting) => {
+                let greeting = match greeting {
+                    Greeting::BuonGiorno => {
+                        Self::greeting_of_the_day(&repository, automatizer.daily_greeting()).await
+                    }
+                    greeting => greeting,
+                };
+                let context = caption::Context::today(lang).await;
+                Self::get_buongiornissimo(&repository, chat_id, greeting, 1, &context, lang).await
+            }
+            CallbackData::Birthdays => Self::birthdays_menu(lang),
+            CallbackData::NewBirthday => {
+                dialogue::start(&dialogue, Some(&query.from), lang).await?
+            }
+            CallbackData::ConfirmBirthday => {
+                dialogue::confirm(&dialogue, &automatizer, &query.from, lang).await?
+            }
+            CallbackData::CancelBirthday => {
+                dialogue::cancel(&dialogue, Some(&query.from), lang).await?
+            }
+            CallbackData::Subscriptions => Self::subscriptions_menu(lang),
+            CallbackData::Subscribe => {
+                Self::subscribe_to_automatizer(&automatizer, &chat_id, lang).await
+            }
+            CallbackData::Unsubscribe => {
+                Self::unsubscribe_from_automatizer(&automatizer, &chat_id, lang).await
+            }
+            CallbackData::Languages => Self::languages_menu(lang),
+            CallbackData::Language(language) => {
+                Self::set_language(&repository, &chat_id, language).await
+            }
+            CallbackData::ApproveSubmission(id) => {
+                Self::review_submission(&bot, &repository, &query.from, id, true, &admins, lang)
+                    .await
+            }
+            CallbackData::RejectSubmission(id) => {
+                Self::review_submission(&bot, &repository, &query.from, id, false, &admins, lang)
+                    .await
+            }
+            CallbackData::SaveFavourite(greeting) => {
+                Self::save_favourite(&repository, &query, greeting, lang).await
+            }
+            CallbackData::Favourites(page) => {
+                Self::favourites(&repository, Some(&query.from), page, lang).await
+            }
+            CallbackData::SendFavourite(id) => {
+                Self::send_favourite(&repository, &query.from, id, lang).await
+            }
+        };
+
+        let delivery = answer.take_delivery();
+        let result = answer.send(&bot, chat_id).await;
+        if let Some(delivery) = delivery {
+            Self::log_delivery(&repository, delivery, &result).await;
+        }
+        result?;
+        Ok(())
+    }
+
+    /// Write the `delivery` of a greeting sent on request to the delivery log, as failed if sending has failed.
+    /// Failures are only logged, since the greeting has already been answered
+    async fn log_delivery(
+        repository: &Repository,
+        delivery: Delivery,
+        result: &ResponseResult<()>,
+    ) {
+        let delivery = match result {
+            Err(err) if !delivery.is_failed() => delivery.failed(err),
+            _ => delivery,
+        };
+        if let Err(err) = repository.insert_delivery(delivery).await {
+            error!("failed to write delivery log: {}", err);
+        }
+    }
+
+    /// Get the language to answer in `chat`: the language chosen for the chat,
+    /// otherwise the language of the telegram client of `user`, if supported
+    pub(super) async fn language(
+        repository: &Repository,
+        chat: ChatId,
+        user: Option<&User>,
+    ) -> Language {
+        let language = match repository.get_language(chat).await {
+            Ok(language) => language,
+            Err(err) => {
+                error!("failed to get language of {}: {}", chat, err);
+                None
             }
-            Command::Caffeee => Self::subscribe_to_automatizer(&message.chat.id).await,
-            Command::PuliziaKontatti => Self::unsubscribe_from_automatizer(&message.chat.id).await,
-            Command::Release => Self::get_release(),
         };
+        language
+            .or_else(|| {
+                user.and_then(|user| user.language_code.as_deref())
+                    .and_then(Language::parse)
+            })
+            .unwrap_or_default()
+    }
 
-        answer.send(&bot, message.chat.id).await
+    /// The list of the available commands; untranslated descriptions fall back to italian
+    fn help(lang: Language) -> Answer {
+        let commands = Command::bot_commands()
+            .into_iter()
+            .map(|command| {
+                let name = command.command.trim_start_matches('/');
+                let description = lang
+                    .command_description(name)
+                    .unwrap_or(command.description.as_str());
+                format!("/{name} — {description}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Answer::simple_text(format!("{}\n\n{commands}", lang.text(Key::Help)))
     }
 
-    fn start() -> Answer {
+    fn start(lang: Language) -> Answer {
         AnswerBuilder::default()
-            .text("CAFFEE!? ☕ Entra subito nel mondo dei buongiornissimi con /caffeee o se vuoi un dolce assaggio dei miei contenuti /buongiornissimo altrimenti invia /help per vedere tutti i comandi disponibili")
+            .text(lang.text(Key::Start))
             .finalize()
     }
 
-    fn get_release() -> Answer {
-        Answer::simple_text(format!(
-            "buongiorno-caffe-bot ☕ {}. Sviluppato da @veeso97. Contribuisci al progetto su Github https://github.com/veeso/buongiorno-caffe-bot. Sostieni il mio progetto su Ko-Fi https://ko-fi.com/veeso",
-            env!("CARGO_PKG_VERSION")
-        ))
+    fn menu(lang: Language) -> Answer {
+        AnswerBuilder::default()
+            .text(lang.text(Key::Menu))
+            .keyboard(keyboard::menu(lang))
+            .finalize()
     }
 
-    /// Get a buongiorno only image
-    pub async fn get_buongiornissimo_buongiorno() -> Answer {
-        Self::get_buongiornissimo(buongiornissimo_rs::greeting_of_the_day(
-            Local::now().naive_local().into(),
-            *random_utils::choice(&[true, false]),
-        ))
-        .await
+    fn birthdays_menu(lang: Language) -> Answer {
+        AnswerBuilder::default()
+            .text(lang.text(Key::BirthdaysMenu))
+            .keyboard(keyboard::birthdays(lang))
+            .finalize()
     }
 
-    /// Get buongiornissimo for media type
-    pub async fn get_buongiornissimo(media: Greeting) -> Answer {
-        match Self::get_greeting_image(media).await {
-            Ok(image) => AnswerBuilder::default().image(image).finalize(),
-            Err(err) => Self::error(err),
+    fn subscriptions_menu(lang: Language) -> Answer {
+        AnswerBuilder::default()
+            .text(lang.text(Key::SubscriptionsMenu))
+            .keyboard(keyboard::subscriptions(lang))
+            .finalize()
+    }
+
+    fn languages_menu(lang: Language) -> Answer {
+        AnswerBuilder::default()
+            .text(lang.text(Key::LanguageMenu))
+            .keyboard(keyboard::languages())
+            .finalize()
+    }
+
+    fn get_release(lang: Language) -> Answer {
+        Answer::simple_text(lang.format(Key::Release, &[("version", &env!("CARGO_PKG_VERSION"))]))
+    }
+
+    /// Get `amount` images of the greeting of the day, in reply to `message`; the author of the message is the person greeted.
+    /// In daily greeting mode, the first image is the image of the day
+    pub async fn get_buongiornissimo_buongiorno(
+        repository: &Repository,
+        daily_greeting: bool,
+        message: &Message,
+        amount: usize,
+        lang: Language,
+    ) -> Answer {
+        let media = Self::greeting_of_the_day(repository, daily_greeting).await;
+        let images = if daily_greeting {
+            Self::get_greeting_images_of_the_day(repository, media, amount).await
+        } else {
+            Self::get_greeting_images(repository, media, amount).await
+        };
+        let images = match images {
+            Ok(images) => images,
+            Err(err) => return Self::scrape_error(media, lang, err),
+        };
+        let context = Self::greeted_context(message, lang).await;
+        let caption = Self::caption(repository, message.chat.id, media)
+            .await
+            .and_then(|template| template.render(&context));
+        Self::greeting_answer(repository, message.chat.id, &images, caption, media, lang)
+            .await
+            .with_delivery(Self::greeting_delivery(
+                message.chat.id,
+                ON_DEMAND_JOB,
+                media,
+                &images,
+            ))
+    }
+
+    /// Get `amount` buongiornissimo for media type in reply to `message`; the author of the message is the person greeted
+    async fn get_buongiornissimo_for(
+        repository: &Repository,
+        message: &Message,
+        media: Greeting,
+        amount: usize,
+        lang: Language,
+    ) -> Answer {
+        let context = Self::greeted_context(message, lang).await;
+        Self::get_buongiornissimo(repository, message.chat.id, media, amount, &context, lang).await
+    }
+
+    /// Get the caption context of today's greeting to the author of `message`
+    async fn greeted_context(message: &Message, lang: Language) -> caption::Context {
+        let context = caption::Context::today(lang).await;
+        match message.from.as_ref() {
+            Some(user) => context.with_name(&user.first_name),
+            None => context,
         }
     }
 
-    /// Get happy birthday answer
-    pub async fn happy_birthday(name: &str) -> Answer {
-        let image = match Self::get_greeting_image(Greeting::Compleanno).await {
-            Ok(url) => url,
-            Err(err) => return Self::error(err),
+    /// Generate an image for media type locally, in reply to `message`; the author of the message is the person greeted
+    async fn generate(
+        repository: &Repository,
+        message: &Message,
+        media: Greeting,
+        lang: Language,
+    ) -> Answer {
+        let context = Self::greeted_context(message, lang).await;
+        let caption = Self::caption(repository, message.chat.id, media)
+            .await
+            .and_then(|template| template.render(&context));
+        let images = [GreetingImage::generated(generator::compose(media))];
+        Self::greeting_answer(repository, message.chat.id, &images, caption, media, lang)
+            .await
+            .with_delivery(Self::greeting_delivery(
+                message.chat.id,
+                ON_DEMAND_JOB,
+                media,
+                &images,
+            ))
+    }
+
+    /// Get `amount` buongiornissimo for media type, with the caption chosen for `chat` rendered with `context`.
+    /// More images are sent as an album
+    pub async fn get_buongiornissimo(
+        repository: &Repository,
+        chat: ChatId,
+        media: Greeting,
+        amount: usize,
+        context: &caption::Context,
+        lang: Language,
+    ) -> Answer {
+        let images = match Self::get_greeting_images(repository, media, amount).await {
+            Ok(images) => images,
+            Err(err) => return Self::scrape_error(media, lang, err),
         };
-        AnswerBuilder::default()
-            .image(image)
-            .text(format!("Buon compleanno {}!", name))
+        let caption = Self::caption(repository, chat, media)
+            .await
+            .and_then(|template| template.render(context));
+        Self::greeting_answer(repository, chat, &images, caption, media, lang)
+            .await
+            .with_delivery(Self::greeting_delivery(chat, ON_DEMAND_JOB, media, &images))
+    }
+
+    /// Get the delivery of the `images` of `media` to `chat` for `job`, as written to the delivery log.
+    /// A message is recorded once, with its first image
+    pub(super) fn greeting_delivery(
+        chat: ChatId,
+        job: &str,
+        media: Greeting,
+        images: &[GreetingImage],
+    ) -> Delivery {
+        let image = images.first();
+        Delivery::new(
+            chat,
+            job,
+            format!("{:?}", media),
+            image.map(|image| image.provider.name().to_string()),
+            image.map(|image| image.url.to_string()),
+        )
+    }
+
+    /// The answer to return when no image of `media` could be found, recording the failure in the delivery log
+    fn scrape_error(media: Greeting, lang: Language, err: ImagesError) -> Answer {
+        let delivery = Delivery::scrape_failed(
+            ON_DEMAND_JOB,
+            format!("{:?}", media),
+            err.provider_name(),
+            &err,
+        );
+        Self::error(lang, err).with_delivery(delivery)
+    }
+
+    /// Build the answer with the `images` of `media` sent to `chat` and their caption.
+    /// A single image comes with the keyboard to get another one, while more images are sent as an album
+    pub(super) async fn greeting_answer(
+        repository: &Repository,
+        chat: ChatId,
+        images: &[GreetingImage],
+        caption: Option<(String, Vec<MessageEntity>)>,
+        media: Greeting,
+        lang: Language,
+    ) -> Answer {
+        let mut answer =
+            AnswerBuilder::default().album(Self::greeting_media(repository, chat, images).await);
+        if let Some((caption, entities)) = caption {
+            answer = answer.caption(caption, entities);
+        }
+        if let Some(image) = images.first() {
+            answer = answer.keyboard(keyboard::another_one(
+                lang,
+                media,
+                rating::image_key(&image.url),
+            ));
+        }
+        answer.finalize()
+    }
+
+    /// Get happy birthday answer, with the birthday wishes in the caption
+    pub async fn happy_birthday(
+        repository: &Repository,
+        chat: ChatId,
+        name: &str,
+        lang: Language,
+    ) -> Answer {
+        let image = match Self::get_greeting_image(repository, Greeting::Compleanno).await {
+            Ok(image) => image,
+            Err(err) => return Self::scrape_error(Greeting::Compleanno, lang, err),
+        };
+        let mut answer = AnswerBuilder::default()
+            .album(Self::greeting_media(repository, chat, std::slice::from_ref(&image)).await);
+        let context = caption::Context::today(lang).await.with_name(name);
+        if let Some((caption, entities)) = Self::birthday_caption(repository, chat, lang)
+            .await
+            .and_then(|template| template.render(&context))
+        {
+            answer = answer.caption(caption, entities);
+        }
+        let delivery = Self::greeting_delivery(
+            chat,
+            ON_DEMAND_JOB,
+            Greeting::Compleanno,
+            std::slice::from_ref(&image),
+        );
+        answer
+            .keyboard(keyboard::another_one(
+                lang,
+                Greeting::Compleanno,
+                rating::image_key(&image.url),
+            ))
             .finalize()
+            .with_delivery(delivery)
+    }
+
+    /// Get the files to send to `chat` for the `images`, stamped with the watermark chosen for the chat, if any.
+    /// Animations and videos are never stamped; failures are only logged, and the image is sent as it is.
+    /// Generated images which fail to render are left out.
+    /// The images are marked as sent, so they are not picked again for a while
+    pub(super) async fn greeting_media(
+        repository: &Repository,
+        chat: ChatId,
+        images: &[GreetingImage],
+    ) -> Vec<(InputFile, MediaKind)> {
+        let watermark = repository.get_watermark(chat).await.unwrap_or_else(|err| {
+            error!("failed to get watermark for {}: {}", chat, err);
+            None
+        });
+        let mut media = Vec::with_capacity(images.len());
+        for image in images {
+            match Self::greeting_file(image, watermark.as_ref()).await {
+                Ok(file) => media.push((file, image.kind)),
+                Err(err) if image.provider == Providers::Generator => {
+                    error!("failed to generate image {}: {}", image.url, err);
+                }
+                Err(err) => {
+                    error!("failed to stamp watermark on {}: {}", image.url, err);
+                    media.push((InputFile::url(image.url.clone()), image.kind));
+                }
+            }
+            if image.hash.is_some()
+                && let Err(err) = repository.mark_image_sent(image.url.as_str()).await
+            {
+                error!("failed to mark image {} as sent: {}", image.url, err);
+            }
+        }
+        media
+    }
+
+    /// Get the file to send for `image`: generated images are rendered and images are stamped with the `watermark`,
+    /// while the other media are sent by url. The images submitted by the users are already stored on telegram,
+    /// so they are sent by file id, without watermark
+    async fn greeting_file(
+        image: &GreetingImage,
+        watermark: Option<&watermark::Watermark>,
+    ) -> anyhow::Result<InputFile> {
+        if let Some(file_id) = image.file_id() {
+            return Ok(InputFile::file_id(FileId(file_id.to_string())));
+        }
+        let bytes = match (image.provider, watermark, image.kind) {
+            (Providers::Generator, _, _) => generator::generate(&image.url).await?,
+            (_, Some(_), MediaKind::Image) => media::download(&image.url).await?.0,
+            _ => return Ok(InputFile::url(image.url.clone())),
+        };
+        let bytes = match watermark {
+            Some(watermark) => watermark::stamp(bytes, watermark).await?,
+            None => bytes,
+        };
+        Ok(InputFile::memory(bytes).file_name("greeting.jpg"))
+    }
+
+    /// Get the caption template of `greeting` chosen for `chat`, if any.
+    /// Failures are only logged, since they must not prevent the image from being sent
+    pub(super) async fn caption(
+        repository: &Repository,
+        chat: ChatId,
+        greeting: Greeting,
+    ) -> Option<Template> {
+        match repository.get_caption(chat, greeting).await {
+            Ok(template) => template,
+            Err(err) => {
+                error!(
+                    "failed to get caption of {:?} for {}: {}",
+                    greeting, chat, err
+                );
+                None
+            }
+        }
+    }
+
+    /// Get the caption template of the birthday wishes for `chat`: the template chosen for the chat, otherwise the default one
+    pub(super) async fn birthday_caption(
+        repository: &Repository,
+        chat: ChatId,
+        lang: Language,
+    ) -> Option<Template> {
+        Self::caption(repository, chat, Greeting::Compleanno)
+            .await
+            .or_else(|| Template::default_for(Greeting::Compleanno, lang))
+    }
+
+    /// Get the greeting of the day. In daily greeting mode, it's decided once per day and stored in `repository`,
+    /// so it's the same for every chat; otherwise it's decided at every call.
+    /// Failures are only logged, and the greeting of the day is decided at random, as when daily greeting mode is disabled
+    pub(super) async fn greeting_of_the_day(
+        repository: &Repository,
+        daily_greeting: bool,
+    ) -> Greeting {
+        if !daily_greeting {
+            return greeting::of_the_day();
+        }
+        repository
+            .get_greeting_of_the_day()
+            .await
+            .unwrap_or_else(|err| {
+                error!("failed to get greeting of the day: {}", err);
+                greeting::of_the_day()
+            })
+    }
+
+    /// Get up to `amount` images of `media`, the greeting of the day, for the daily greeting mode.
+    /// The first image is the image of the day, which is chosen by the first request of the day,
+    /// while the others are picked as usual
+    pub async fn get_greeting_images_of_the_day(
+        repository: &Repository,
+        media: Greeting,
+        amount: usize,
+    ) -> ImagesResult<Vec<GreetingImage>> {
+        let image_of_the_day = match repository.get_image_of_the_day().await {
+            Ok(Some(image)) => image,
+            Ok(None) => {
+                let mut images = Self::get_greeting_images(repository, media, amount).await?;
+                // the image of the day could have been chosen concurrently, in which case it replaces the first image
+                match repository.set_image_of_the_day(&images[0]).await {
+                    Ok(Some(image)) if image.url != images[0].url => {
+                        images.retain(|other| other.url != image.url);
+                        images.insert(0, image);
+                        images.truncate(amount);
+                    }
+                    Ok(_) => {}
+                    Err(err) => error!("failed to set image of the day: {}", err),
+                }
+                return Ok(images);
+            }
+            Err(err) => {
+                error!("failed to get image of the day: {}", err);
+                return Self::get_greeting_images(repository, media, amount).await;
+            }
+        };
+        let mut images = vec![image_of_the_day.clone()];
+        if amount > 1 {
+            match Self::get_greeting_images(repository, media, amount).await {
+                Ok(others) => images.extend(
+                    others
+                        .into_iter()
+                        .filter(|other| other.url != image_of_the_day.url)
+                        .take(amount - 1),
+                ),
+                Err(err) => error!("failed to get more images of the day: {}", err),
+            }
+        }
+        Ok(images)
     }
 
-    /// Get greeting image for media type.
+    /// Get greeting image for media type, different from the images recently sent.
     /// At the first try it'll use a random provider; then if the media type is not supported, it tries all the different providers
-    pub async fn get_greeting_image(media: Greeting) -> ScrapeResult<Url> {
+    pub async fn get_greeting_image(
+        repository: &Repository,
+        media: Greeting,
+    ) -> ImagesResult<GreetingImage> {
+        Self::get_greeting_images(repository, media, 1)
+            .await
+            .map(|mut images| images.remove(0))
+    }
+
+    /// Get up to `amount` greeting images for media type, collected from the different providers
+    /// and from the images submitted by the users, in random order.
+    /// The images of each provider are picked in random order too, but the images voted up by the chats tend to come first.
+    /// Images near-identical to each other or to the images recently sent are only used if there aren't enough different images.
+    /// If no provider has images, the images are generated locally
+    pub async fn get_greeting_images(
+        repository: &Repository,
+        media: Greeting,
+        amount: usize,
+    ) -> ImagesResult<Vec<GreetingImage>> {
+        Self::get_greeting_images_with(repository, media, amount, Validation::Full).await
+    }
+
+    /// Get up to `amount` greeting images for media type as [`Buongiornissimo::get_greeting_images`] does,
+    /// validating the scraped images as thoroughly as told by `validation`
+    pub async fn get_greeting_images_with(
+        repository: &Repository,
+        media: Greeting,
+        amount: usize,
+        validation: Validation,
+    ) -> ImagesResult<Vec<GreetingImage>> {
         let mut providers = Providers::all().to_vec();
-        // shuffle
+        providers.push(Providers::Community);
         providers.shuffle(&mut rng());
 
+        let recent = repository
+            .get_recent_image_hashes()
+            .await
+            .unwrap_or_else(|err| {
+                error!("failed to get recent images: {}", err);
+                Vec::new()
+            });
+        let scores = repository.get_image_scores().await.unwrap_or_else(|err| {
+            error!("failed to get image scores: {}", err);
+            HashMap::new()
+        });
+        let mut picker = ImagePicker::new(amount, recent);
         let mut last_err = None;
-        // iterate over until we get Ok
         for provider in providers {
-            match Self::do_get_greeting_image(provider, media).await {
-                Ok(url) => return Ok(url),
+            if picker.is_full() {
+                break;
+            }
+            if provider == Providers::Community {
+                Self::pick_community_images(repository, media, &scores, &mut picker).await;
+                continue;
+            }
+            METRICS.scrape_attempt(provider.name());
+            match provider.scrape(media).await {
+                Ok(urls) => {
+                    let urls = rating::weighted_shuffle(urls, |url| rating::weight(&scores, url));
+                    Self::pick_images(repository, provider, urls, validation, &mut picker).await;
+                }
                 Err(ScrapeError::UnsupportedGreeting) => {
                     debug!(
                         "provider {:?} does not support greeting {:?}",
                         provider, media
                     );
-                    last_err = Some(ScrapeError::UnsupportedGreeting);
-                    continue;
+                    last_err = Some((provider, ScrapeError::UnsupportedGreeting));
+                }
+                Err(err) => {
+                    error!("failed to get images from provider {:?}: {}", provider, err);
+                    METRICS.scrape_failure(provider.name());
+                    last_err = Some((provider, err));
                 }
+            }
+        }
+
+        let mut images = picker.finish();
+        if images.is_empty() {
+            info!(
+                "no images from the providers for {:?} ({:?}), generating them",
+                media, last_err
+            );
+            let mut urls = generator::compositions(media);
+            urls.shuffle(&mut rng());
+            images = urls
+                .into_iter()
+                .take(amount)
+                .map(GreetingImage::generated)
+                .collect();
+        }
+        if images.is_empty() {
+            return Err(match last_err {
+                Some((provider, error)) => ImagesError {
+                    provider: Some(provider),
+                    error,
+                },
+                None => ImagesError {
+                    provider: None,
+                    error: ScrapeError::NoImages,
+                },
+            });
+        }
+        Ok(images)
+    }
+
+    /// Validate the `urls` scraped from `provider` and give the valid images to `picker` in order,
+    /// until it is full or [`MAX_DISCARDED_IMAGES`] images are discarded.
+    /// Up to [`MAX_CONCURRENT_VALIDATIONS`] images are validated at the same time; the validations still running when done are dropped.
+    /// The hashes of the valid images are stored, so the same image is recognized when scraped from another url
+    async fn pick_images(
+        repository: &Repository,
+        provider: Providers,
+        urls: Vec<Url>,
+        validation: Validation,
+        picker: &mut ImagePicker,
+    ) {
+        let mut validations = stream::iter(urls)
+            .map(|url| async move {
+                let result = GreetingImage::validate(provider, url.clone(), validation).await;
+                (url, result)
+            })
+            .buffered(MAX_CONCURRENT_VALIDATIONS);
+        let mut discarded = 0;
+        while !picker.is_full() && discarded < MAX_DISCARDED_IMAGES {
+            let Some((url, result)) = validations.next().await else {
+                break;
+            };
+            let image = match result {
+                Ok(image) => image,
                 Err(err) => {
-                    error!("failed to get image from provider {:?}: {}", provider, err);
-                    last_err = Some(err);
+                    debug!(
+                        "discarding image {} from provider {:?}: {}",
+                        url, provider, err
+                    );
+                    METRICS.discarded_image(provider.name(), err.reason());
+                    discarded += 1;
                     continue;
                 }
+            };
+            if let Some(hash) = image.hash {
+                let result = repository
+                    .insert_image_hash(image.url.as_str(), provider.name(), hash)
+                    .await;
+                if let Err(err) = result {
+                    error!("failed to store hash of image {}: {}", url, err);
+                }
+            }
+            if !picker.pick(image) {
+                debug!(
+                    "discarding image {} from provider {:?}: duplicate",
+                    url, provider
+                );
+                METRICS.discarded_image(provider.name(), "duplicate");
+                discarded += 1;
+            }
+        }
+    }
+
+    /// Give the approved images submitted by the users for media type to `picker`, in random order weighted by their `scores`
+    async fn pick_community_images(
+        repository: &Repository,
+        media: Greeting,
+        scores: &HashMap<u64, i64>,
+        picker: &mut ImagePicker,
+    ) {
+        let file_ids = match repository.get_community_images(media).await {
+            Ok(file_ids) => file_ids,
+            Err(err) => {
+                error!("failed to get community images for {:?}: {}", media, err);
+                return;
+            }
+        };
+        let images = file_ids
+            .into_iter()
+            .filter_map(|file_id| {
+                let image = GreetingImage::submitted(&file_id);
+                if image.is_none() {
+                    error!("bad file id of submitted image: {}", file_id);
+                }
+                image
+            })
+            .collect();
+        for image in rating::weighted_shuffle(images, |image| rating::weight(scores, &image.url)) {
+            if picker.is_full() {
+                break;
             }
+            picker.pick(image);
         }
+    }
 
-        // return last error
-        Err(last_err.expect("must be set"))
+    /// Record the vote of `user` in `chat` for the image with key `image`, returning the text of the popup confirming it.
+    /// Failures are logged, while the user gets a generic message
+    async fn vote(
+        repository: &Repository,
+        user: &User,
+        chat_id: ChatId,
+        image: u64,
+        up: bool,
+        lang: Language,
+    ) -> &'static str {
+        METRICS.command_received("vote");
+        match repository.vote_image(chat_id, user.id, image, up).await {
+            Ok(()) if up => lang.text(Key::VoteUp),
+            Ok(()) => lang.text(Key::VoteDown),
+            Err(err) => {
+                error!("failed to save vote of {}: {}", user.id, err);
+                lang.text(Key::VoteFailed)
+            }
+        }
     }
 
-    async fn do_get_greeting_image(provider: Providers, greeting: Greeting) -> ScrapeResult<Url> {
-        match provider.scrape(greeting).await {
-            Ok(urls) => Ok(random_utils::choice(&urls).clone()),
-            Err(err) => Err(err),
+    /// Store the photo of `message`, or of the message it replies to, as an image for `greeting` waiting for a review,
+    /// and send it to the admins with the buttons to approve or reject it
+    async fn submit_image(
+        bot: &Bot,
+        repository: &Repository,
+        message: &Message,
+        greeting: Greeting,
+        admins: &[UserId],
+        lang: Language,
+    ) -> Answer {
+        let Some(user) = message.from.as_ref() else {
+            return Answer::simple_text(lang.text(Key::UnknownUser));
+        };
+        let photo = message
+            .photo()
+            .or_else(|| message.reply_to_message().and_then(Message::photo))
+            .and_then(|sizes| sizes.last());
+        let Some(photo) = photo else {
+            return Answer::simple_text(lang.text(Key::SubmissionMissingPhoto));
+        };
+        let file_id = &photo.file.id.0;
+        let id = match repository
+            .insert_submission(message.chat.id, user.id, greeting, file_id)
+            .await
+        {
+            Ok(id) => id,
+            Err(err) => return Self::error(lang, err),
+        };
+        info!("user {} submitted image {} for {:?}", user.id, id, greeting);
+        for admin in admins {
+            let admin_chat = ChatId::from(*admin);
+            let admin_lang = Self::language(repository, admin_chat, None).await;
+            let text = admin_lang.format(
+                Key::SubmissionToReview,
+                &[
+                    ("name", &user.full_name()),
+                    ("greeting", &greeting::keyword(greeting)),
+                ],
+            );
+            let result = AnswerBuilder::default()
+                .media(
+                    InputFile::file_id(FileId(file_id.clone())),
+                    MediaKind::Image,
+                )
+                .caption(text, Vec::new())
+                .keyboard(keyboard::review(admin_lang, id))
+                .finalize()
+                .send(bot, admin_chat)
+                .await;
+            if let Err(err) = result {
+                error!(
+                    "failed to send submission {} to admin {}: {}",
+                    id, admin, err
+                );
+            }
+        }
+        Answer::simple_text(lang.format(
+            Key::SubmissionReceived,
+            &[("greeting", &greeting::keyword(greeting))],
+        ))
+    }
+
+    /// Approve or reject the submission `id`, notifying the chat it was proposed in; available to admins only
+    async fn review_submission(
+        bot: &Bot,
+        repository: &Repository,
+        reviewer: &User,
+        id: i64,
+        approved: bool,
+        admins: &[UserId],
+        lang: Language,
+    ) -> Answer {
+        if !admins.contains(&reviewer.id) {
+            return Answer::simple_text(lang.text(Key::AdminOnly));
+        }
+        let submission = match repository.review_submission(id, approved).await {
+            Ok(Some(submission)) => submission,
+            Ok(None) => return Answer::simple_text(lang.text(Key::ReviewAlreadyDone)),
+            Err(err) => return Self::error(lang, err),
+        };
+        info!(
+            "submission {} {} by {}",
+            submission.id(),
+            if approved { "approved" } else { "rejected" },
+            reviewer.id
+        );
+        let chat = submission.chat();
+        let chat_lang = Self::language(repository, chat, None).await;
+        let key = if approved {
+            Key::SubmissionApproved
+        } else {
+            Key::SubmissionRejected
+        };
+        let text = chat_lang.format(key, &[("greeting", &submission.greeting())]);
+        let result = Answer::simple_text(text).send(bot, chat).await;
+        if let Err(err) = result {
+            error!(
+                "failed to notify review of submission {} to {}: {}",
+                id, chat, err
+            );
+        }
+        Answer::simple_text(lang.text(if approved {
+            Key::ReviewApproved
+        } else {
+            Key::ReviewRejected
+        }))
+    }
+
+    /// Save the media of the message the button pressed in `query` is attached to among the favourites of the user
+    async fn save_favourite(
+        repository: &Repository,
+        query: &CallbackQuery,
+        greeting: Greeting,
+        lang: Language,
+    ) -> Answer {
+        let Some((file_id, kind)) = query.regular_message().and_then(Self::message_media) else {
+            return Answer::simple_text(lang.text(Key::FavouriteMissingMedia));
+        };
+        let user = &query.from;
+        let key = match repository
+            .insert_favourite(user.id, greeting, &file_id, kind)
+            .await
+        {
+            Ok(true) => Key::FavouriteSaved,
+            Ok(false) => Key::FavouriteAlreadySaved,
+            Err(err) => return Self::error(lang, err),
+        };
+        Answer::simple_text(lang.format(key, &[("name", &user.first_name)]))
+    }
+
+    /// Get the telegram file id and the kind of the media sent with `message`, if any
+    fn message_media(message: &Message) -> Option<(String, MediaKind)> {
+        if let Some(photo) = message.photo().and_then(|sizes| sizes.last()) {
+            Some((photo.file.id.0.clone(), MediaKind::Image))
+        } else if let Some(animation) = message.animation() {
+            Some((animation.file.id.0.clone(), MediaKind::Animation))
+        } else {
+            message
+                .video()
+                .map(|video| (video.file.id.0.clone(), MediaKind::Video))
+        }
+    }
+
+    /// List the favourites of `user` in `page`, with the buttons to send them and to browse the other pages
+    async fn favourites(
+        repository: &Repository,
+        user: Option<&User>,
+        page: usize,
+        lang: Language,
+    ) -> Answer {
+        let Some(user) = user else {
+            return Answer::simple_text(lang.text(Key::UnknownUser));
+        };
+        let (favourites, pages) = match repository.get_favourites(user.id, page).await {
+            Ok(favourites) => favourites,
+            Err(err) => return Self::error(lang, err),
+        };
+        if favourites.is_empty() {
+            return Answer::simple_text(lang.text(Key::FavouritesEmpty));
+        }
+        let buttons: Vec<(i64, String)> = favourites
+            .iter()
+            .enumerate()
+            .map(|(index, favourite)| {
+                let number = page * repository::FAVOURITES_PAGE_SIZE + index + 1;
+                (
+                    favourite.id(),
+                    format!("{number}. {}", favourite.greeting()),
+                )
+            })
+            .collect();
+        AnswerBuilder::default()
+            .text(lang.format(
+                Key::FavouritesTitle,
+                &[
+                    ("name", &user.first_name),
+                    ("page", &(page + 1)),
+                    ("pages", &pages),
+                ],
+            ))
+            .keyboard(keyboard::favourites(lang, &buttons, page, pages))
+            .finalize()
+    }
+
+    /// Send the favourite `id` of `user`
+    async fn send_favourite(
+        repository: &Repository,
+        user: &User,
+        id: i64,
+        lang: Language,
+    ) -> Answer {
+        match repository.get_favourite(user.id, id).await {
+            Ok(Some(favourite)) => {
+                let kind = MediaKind::from_name(favourite.kind()).unwrap_or_default();
+                AnswerBuilder::default()
+                    .media(
+                        InputFile::file_id(FileId(favourite.file_id().to_string())),
+                        kind,
+                    )
+                    .finalize()
+            }
+            Ok(None) => Answer::simple_text(lang.text(Key::FavouriteNotFound)),
+            Err(err) => Self::error(lang, err),
         }
     }
 
     /// Subscribe birthday
-    async fn subscribe_birthday(chat_id: &ChatId, name: String, date: NaiveDate) -> Answer {
-        match AUTOMATIZER.get().unwrap().add_birthday(chat_id, name.clone(), date).await {
+    async fn subscribe_birthday(
+        automatizer: &Automatizer,
+        chat_id: &ChatId,
+        name: String,
+        date: PartialDate,
+        user: Option<UserId>,
+        lang: Language,
+    ) -> Answer {
+        match automatizer
+            .add_birthday(chat_id, name.clone(), date, user)
+            .await
+        {
             Ok(_) => AnswerBuilder::default()
-            .text(format!("Buongiorno, CAFFEEE?! ☕☕☕  Da ora {} riceverà gli auguri il giorno del suo compleanno.", name))
-            .finalize(),
-            Err(err) => Self::error(err),
+                .text(lang.format(Key::BirthdaySubscribed, &[("name", &name)]))
+                .finalize(),
+            Err(err) => Self::error(lang, err),
+        }
+    }
+
+    /// Set the birthday of the author of `message` in the chat
+    async fn set_own_birthday(
+        automatizer: &Automatizer,
+        message: &Message,
+        date: PartialDate,
+        lang: Language,
+    ) -> Answer {
+        let Some(user) = message.from.as_ref() else {
+            return Answer::simple_text(lang.text(Key::UnknownUser));
+        };
+        match automatizer
+            .set_user_birthday(&message.chat.id, user.id, user.full_name(), date)
+            .await
+        {
+            Ok(()) => {
+                Answer::simple_text(lang.format(Key::OwnBirthdaySet, &[("date", &lang.date(date))]))
+            }
+            Err(err) => Self::error(lang, err),
+        }
+    }
+
+    /// Get the name and the user, if known, whose birthday is registered with `message`.
+    /// The `name` written in the message wins over the author of the replied message;
+    /// the user is taken from the mention in the message, if any, otherwise from the replied message when no name is written
+    async fn birthday_target(
+        repository: &Repository,
+        message: &Message,
+        name: Option<String>,
+    ) -> Option<(String, Option<UserId>)> {
+        match mention::mentioned(message) {
+            Some(mention::Mention::User(user)) => {
+                Some((name.unwrap_or_else(|| user.full_name()), Some(user.id)))
+            }
+            Some(mention::Mention::Username(username)) => {
+                let user = repository
+                    .get_user_by_username(username)
+                    .await
+                    .unwrap_or_else(|err| {
+                        error!("failed to resolve username {}: {}", username, err);
+                        None
+                    });
+                match user {
+                    Some((id, full_name)) => Some((name.unwrap_or(full_name), Some(id))),
+                    // telegram notifies the users mentioned by username anyway
+                    None => Some((name.unwrap_or_else(|| format!("@{username}")), None)),
+                }
+            }
+            None => match name {
+                Some(name) => Some((name, None)),
+                None => {
+                    mention::replied_user(message).map(|user| (user.full_name(), Some(user.id)))
+                }
+            },
+        }
+    }
+
+    /// Remember the username of the author of `message`, so that the mentions of the author can be resolved.
+    /// Failures are only logged, since they must not prevent the message from being answered
+    async fn remember_author(message: Message, repository: Repository) {
+        if let Some(user) = message.from.as_ref().filter(|user| !user.is_bot)
+            && let Err(err) = repository.remember_user(user).await
+        {
+            error!("failed to remember user {}: {}", user.id, err);
+        }
+    }
+
+    /// Delete every data about `user`
+    async fn forget_user(
+        automatizer: &Automatizer,
+        dialogue_storage: Arc<BirthdayStorage>,
+        user: Option<&User>,
+        lang: Language,
+    ) -> anyhow::Result<Answer> {
+        let Some(user) = user else {
+            return Ok(Answer::simple_text(lang.text(Key::UnknownUser)));
+        };
+        automatizer.forget_user(user.id).await?;
+        dialogue::forget(dialogue_storage, ChatId::from(user.id)).await?;
+        Ok(Answer::simple_text(lang.text(Key::UserForgotten)))
+    }
+
+    /// Show the amount of images sent to the chat in the automatic messages
+    async fn get_album_size(repository: &Repository, chat_id: &ChatId, lang: Language) -> Answer {
+        match repository.get_album_size(*chat_id).await {
+            Ok(size) => Answer::simple_text(lang.format(
+                Key::AlbumSizeCurrent,
+                &[
+                    ("amount", &size.unwrap_or(1)),
+                    ("max", &answer::MAX_ALBUM_SIZE),
+                ],
+            )),
+            Err(err) => Self::error(lang, err),
+        }
+    }
+
+    /// Set the amount of images sent to the chat in the automatic messages
+    async fn set_album_size(
+        repository: &Repository,
+        chat_id: &ChatId,
+        amount: usize,
+        lang: Language,
+    ) -> Answer {
+        match repository.set_album_size(*chat_id, amount).await {
+            Ok(()) => Answer::simple_text(lang.format(Key::AlbumSizeSet, &[("amount", &amount)])),
+            Err(err) => Self::error(lang, err),
+        }
+    }
+
+    /// Show the caption template of `greeting` in the chat
+    async fn get_caption(
+        repository: &Repository,
+        chat_id: &ChatId,
+        greeting: Greeting,
+        lang: Language,
+    ) -> Answer {
+        let template = match repository.get_caption(*chat_id, greeting).await {
+            Ok(template) => template.or_else(|| Template::default_for(greeting, lang)),
+            Err(err) => return Self::error(lang, err),
+        };
+        match template {
+            Some(template) => Self::caption_answer(Key::CaptionCurrent, greeting, &template, lang),
+            None => Answer::simple_text(lang.format(
+                Key::CaptionNotSet,
+                &[("greeting", &greeting::keyword(greeting))],
+            )),
+        }
+    }
+
+    /// Set the caption template of `greeting` in the chat
+    async fn set_caption(
+        repository: &Repository,
+        chat_id: &ChatId,
+        greeting: Greeting,
+        template: &str,
+        lang: Language,
+    ) -> Answer {
+        let template = match Template::parse(template) {
+            Ok(template) => template,
+            Err(TemplateError::TooLong) => {
+                return Answer::simple_text(
+                    lang.format(Key::CaptionTooLong, &[("max", &caption::MAX_TEMPLATE_LEN)]),
+                );
+            }
+            Err(err) => {
+                debug!("got bad caption template: {}", err);
+                return Answer::simple_text(lang.format(
+                    Key::CaptionBadTemplate,
+                    &[("placeholders", &Placeholder::list(lang))],
+                ));
+            }
+        };
+        match repository.set_caption(*chat_id, greeting, &template).await {
+            Ok(()) => Self::caption_answer(Key::CaptionSet, greeting, &template, lang),
+            Err(err) => Self::error(lang, err),
+        }
+    }
+
+    /// The answer showing the caption `template` of `greeting` as code, after the message `key`
+    fn caption_answer(key: Key, greeting: Greeting, template: &Template, lang: Language) -> Answer {
+        let markup = Markup::MarkdownV2;
+        let text = lang.format(key, &[("greeting", &greeting::keyword(greeting))]);
+        AnswerBuilder::default()
+            .formatted_text(
+                format!(
+                    "{}\n{}",
+                    markup.escape(&text),
+                    markup.code(&template.to_string())
+                ),
+                markup,
+            )
+            .finalize()
+    }
+
+    /// Remove the caption template of `greeting` in the chat
+    async fn delete_caption(
+        repository: &Repository,
+        chat_id: &ChatId,
+        greeting: Greeting,
+        lang: Language,
+    ) -> Answer {
+        match repository.delete_caption(*chat_id, greeting).await {
+            Ok(()) => Answer::simple_text(lang.format(
+                Key::CaptionDeleted,
+                &[("greeting", &greeting::keyword(greeting))],
+            )),
+            Err(err) => Self::error(lang, err),
+        }
+    }
+
+    /// Show the watermark stamped on the images sent to the chat
+    async fn get_watermark(repository: &Repository, chat_id: &ChatId, lang: Language) -> Answer {
+        match repository.get_watermark(*chat_id).await {
+            Ok(Some(watermark)) => Self::watermark_answer(Key::WatermarkCurrent, &watermark, lang),
+            Ok(None) => Answer::simple_text(lang.text(Key::WatermarkNotSet)),
+            Err(err) => Self::error(lang, err),
+        }
+    }
+
+    /// Change the watermark stamped on the images sent to the chat; logos are checked before being saved
+    async fn set_watermark(
+        repository: &Repository,
+        chat_id: &ChatId,
+        setting: WatermarkSetting,
+        lang: Language,
+    ) -> Answer {
+        let result = match setting {
+            WatermarkSetting::Mark(mark) => {
+                let logo = match &mark {
+                    Mark::Logo(url) => watermark::download_logo(url).await.map(|_| ()),
+                    Mark::Text(_) => Ok(()),
+                };
+                if let Err(err) = logo {
+                    debug!("got bad watermark logo: {}", err);
+                    return Answer::simple_text(lang.text(Key::WatermarkBadLogo));
+                }
+                repository.set_watermark_mark(*chat_id, &mark).await
+            }
+            WatermarkSetting::Position(position) => {
+                repository.set_watermark_position(*chat_id, position).await
+            }
+            WatermarkSetting::Opacity(opacity) => {
+                repository.set_watermark_opacity(*chat_id, opacity).await
+            }
+        };
+        if let Err(err) = result {
+            return Self::error(lang, err);
+        }
+        match repository.get_watermark(*chat_id).await {
+            Ok(Some(watermark)) => Self::watermark_answer(Key::WatermarkSet, &watermark, lang),
+            Ok(None) => Answer::simple_text(lang.text(Key::WatermarkMissingMark)),
+            Err(err) => Self::error(lang, err),
+        }
+    }
+
+    /// The answer describing the `watermark` with the message `key`
+    fn watermark_answer(key: Key, watermark: &watermark::Watermark, lang: Language) -> Answer {
+        Answer::simple_text(lang.format(
+            key,
+            &[
+                ("mark", &watermark.mark),
+                ("position", &watermark.position.name(lang)),
+                ("opacity", &watermark.opacity),
+            ],
+        ))
+    }
+
+    /// Remove the watermark from the images sent to the chat
+    async fn delete_watermark(repository: &Repository, chat_id: &ChatId, lang: Language) -> Answer {
+        match repository.delete_watermark(*chat_id).await {
+            Ok(()) => Answer::simple_text(lang.text(Key::WatermarkDeleted)),
+            Err(err) => Self::error(lang, err),
+        }
+    }
+
+    /// Set the language of the chat; the answer is given in the new language
+    async fn set_language(repository: &Repository, chat_id: &ChatId, language: Language) -> Answer {
+        match repository.set_language(*chat_id, language).await {
+            Ok(()) => Answer::simple_text(language.text(Key::LanguageSet)),
+            Err(err) => Self::error(language, err),
         }
     }
 
     /// Subscribe chat to the automatizer
-    async fn subscribe_to_automatizer(chat_id: &ChatId) -> Answer {
-        match AUTOMATIZER.get().unwrap().subscribe(chat_id).await {
+    async fn subscribe_to_automatizer(
+        automatizer: &Automatizer,
+        chat_id: &ChatId,
+        lang: Language,
+    ) -> Answer {
+        match automatizer.subscribe(chat_id).await {
             Ok(_) => AnswerBuilder::default()
-            .text("Buongiorno, CAFFEEE?! ☕☕☕  Da ora riceverei ogni giorno le migliori immagini di augurio.")
-            .finalize(),
-            Err(err) => Self::error(err),
+                .text(lang.text(Key::Subscribed))
+                .finalize(),
+            Err(err) => Self::error(lang, err),
         }
     }
 
-    async fn unsubscribe_from_automatizer(chat_id: &ChatId) -> Answer {
-        match AUTOMATIZER.get().unwrap().unsubscribe(chat_id).await {
+    async fn unsubscribe_from_automatizer(
+        automatizer: &Automatizer,
+        chat_id: &ChatId,
+        lang: Language,
+    ) -> Answer {
+        match automatizer.unsubscribe(chat_id).await {
             Ok(()) => AnswerBuilder::default()
-                .text("ti sei disinscritto dai messaggi automatici ☕")
+                .text(lang.text(Key::Unsubscribed))
                 .finalize(),
-            Err(err) => Self::error(err),
+            Err(err) => Self::error(lang, err),
         }
     }
 
-    /// The answer to return in case of an error
-    fn error(err: impl ToString) -> Answer {
-        AnswerBuilder::default().text(err).finalize()
+    /// Get delivery statistics; available to admins only
+    async fn delivery_stats(
+        repository: &Repository,
+        message: &Message,
+        admins: &[UserId],
+        lang: Language,
+    ) -> Answer {
+        if !Self::is_admin(message, admins) {
+            return Answer::simple_text(lang.text(Key::AdminOnly));
+        }
+        match repository.get_delivery_stats().await {
+            Ok(stats) => AnswerBuilder::default()
+                .formatted_text(stats.render(lang, Markup::Html), Markup::Html)
+                .finalize(),
+            Err(err) => Self::error(lang, err),
+        }
+    }
+
+    /// Whether the author of `message` is one of the `admins`
+    fn is_admin(message: &Message, admins: &[UserId]) -> bool {
+        message
+            .from
+            .as_ref()
+            .is_some_and(|user| admins.contains(&user.id))
+    }
+
+    /// The answer to return in case of an error; errors meant for the user are translated
+    fn error(lang: Language, err: impl Into<anyhow::Error>) -> Answer {
+        let err = err.into();
+        match err.downcast_ref::<UserError>() {
+            Some(err) => Answer::simple_text(err.text(lang)),
+            None => Answer::simple_text(err),
+        }
     }
 }
@@ -2,10 +2,21 @@
 //!
 //! This module cares of providing answer script types and sending messages
 
+use teloxide::RequestError;
 use teloxide::prelude::*;
-use teloxide::types::InputFile;
+use teloxide::types::{
+    InlineKeyboardMarkup, InputFile, InputMedia, InputMediaPhoto, InputMediaVideo, MessageEntity,
+    MessageId, ParseMode, ReplyParameters,
+};
+use teloxide::utils::{html, markdown};
 use tracing::debug;
-use url::Url;
+
+use super::media::MediaKind;
+use crate::metrics::METRICS;
+use crate::repository::delivery::Delivery;
+
+/// Maximum amount of media in an album, as allowed by telegram
+pub const MAX_ALBUM_SIZE: usize = 10;
 
 /// A helper to build composed answers
 #[derive(Default)]
@@ -15,15 +26,111 @@ pub struct AnswerBuilder {
 
 impl AnswerBuilder {
     /// Add text to script
-    pub fn text(mut self, text: impl ToString) -> Self {
-        self.answer.script.push(MessagePart::Text(text.to_string()));
+    pub fn text(self, text: impl ToString) -> Self {
+        self.text_with_entities(text, Vec::new())
+    }
+
+    /// Add text to script, formatted with `entities` (e.g. mentions)
+    pub fn text_with_entities(mut self, text: impl ToString, entities: Vec<MessageEntity>) -> Self {
+        self.answer.script.push(MessagePart::Text {
+            text: FormattedText {
+                text: text.to_string(),
+                entities,
+                markup: Markup::Plain,
+            },
+            keyboard: None,
+        });
         self
     }
 
-    pub fn image(mut self, url: Url) -> Self {
-        self.answer
-            .script
-            .push(MessagePart::Image(InputFile::url(url)));
+    /// Add text written in `markup` to script; the text must be escaped with [`Markup::escape`]
+    pub fn formatted_text(mut self, text: impl ToString, markup: Markup) -> Self {
+        self.answer.script.push(MessagePart::Text {
+            text: FormattedText {
+                text: text.to_string(),
+                entities: Vec::new(),
+                markup,
+            },
+            keyboard: None,
+        });
+        self
+    }
+
+    /// Add a media to script, sent according to its `kind`
+    pub fn media(mut self, file: InputFile, kind: MediaKind) -> Self {
+        let part = match kind {
+            MediaKind::Image => MessagePart::Image {
+                image: file,
+                caption: None,
+                keyboard: None,
+            },
+            MediaKind::Animation => MessagePart::Animation {
+                animation: file,
+                caption: None,
+                keyboard: None,
+            },
+            MediaKind::Video => MessagePart::Video {
+                video: file,
+                caption: None,
+                keyboard: None,
+            },
+        };
+        self.answer.script.push(part);
+        self
+    }
+
+    /// Add an album of media to script; a single media is added as it is.
+    /// Animations can't be grouped, so they are added after the album, one by one.
+    /// Media exceeding [`MAX_ALBUM_SIZE`] are discarded
+    pub fn album(mut self, media: Vec<(InputFile, MediaKind)>) -> Self {
+        let (animations, mut grouped): (Vec<_>, Vec<_>) = media
+            .into_iter()
+            .take(MAX_ALBUM_SIZE)
+            .partition(|(_, kind)| *kind == MediaKind::Animation);
+        if grouped.len() == 1 {
+            let (file, kind) = grouped.pop().expect("must have one media");
+            self = self.media(file, kind);
+        } else if !grouped.is_empty() {
+            self.answer.script.push(MessagePart::Album {
+                media: grouped,
+                caption: None,
+            });
+        }
+        for (file, kind) in animations {
+            self = self.media(file, kind);
+        }
+        self
+    }
+
+    /// Attach a caption, formatted with `entities` (e.g. mentions), to the last media or album in the script
+    pub fn caption(mut self, text: impl ToString, entities: Vec<MessageEntity>) -> Self {
+        if let Some(
+            MessagePart::Image { caption, .. }
+            | MessagePart::Animation { caption, .. }
+            | MessagePart::Video { caption, .. }
+            | MessagePart::Album { caption, .. },
+        ) = self.answer.script.last_mut()
+        {
+            *caption = Some(FormattedText {
+                text: text.to_string(),
+                entities,
+                markup: Markup::Plain,
+            });
+        }
+        self
+    }
+
+    /// Attach an inline keyboard to the last message in the script; albums can't have a keyboard
+    pub fn keyboard(mut self, markup: InlineKeyboardMarkup) -> Self {
+        if let Some(
+            MessagePart::Text { keyboard, .. }
+            | MessagePart::Image { keyboard, .. }
+            | MessagePart::Animation { keyboard, .. }
+            | MessagePart::Video { keyboard, .. },
+        ) = self.answer.script.last_mut()
+        {
+            *keyboard = Some(markup);
+        }
         self
     }
 
@@ -33,49 +140,430 @@ impl AnswerBuilder {
     }
 }
 
+/// The markup language a text is written in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Markup {
+    /// Plain text, which may be formatted with entities
+    #[default]
+    Plain,
+    Html,
+    MarkdownV2,
+}
+
+impl Markup {
+    /// Escape `text`, so it is displayed as it is
+    pub fn escape(self, text: &str) -> String {
+        match self {
+            Self::Plain => text.to_string(),
+            Self::Html => html::escape(text),
+            Self::MarkdownV2 => markdown::escape(text),
+        }
+    }
+
+    /// Format `text` in bold; `text` must be already escaped
+    pub fn bold(self, text: &str) -> String {
+        match self {
+            Self::Plain => text.to_string(),
+            Self::Html => html::bold(text),
+            Self::MarkdownV2 => markdown::bold(text),
+        }
+    }
+
+    /// Format `text` as inline code; `text` is escaped
+    pub fn code(self, text: &str) -> String {
+        match self {
+            Self::Plain => text.to_string(),
+            Self::Html => html::code_inline(text),
+            Self::MarkdownV2 => markdown::code_inline(text),
+        }
+    }
+
+    /// Get the telegram parse mode of the markup; plain text has none
+    fn parse_mode(self) -> Option<ParseMode> {
+        match self {
+            Self::Plain => None,
+            Self::Html => Some(ParseMode::Html),
+            Self::MarkdownV2 => Some(ParseMode::MarkdownV2),
+        }
+    }
+}
+
 /// The answer to send to the chat
 #[derive(Default, Clone)]
 pub struct Answer {
     script: Vec<MessagePart>,
+    options: SendOptions,
+    /// The greeting delivered by the answer, to write to the delivery log once sent
+    delivery: Option<Delivery>,
+}
+
+/// Options applied when sending the answer
+#[derive(Default, Clone, Copy, Debug)]
+struct SendOptions {
+    /// Send the messages without notification
+    silent: bool,
+    /// The message the first message of the answer replies to
+    reply_to: Option<MessageId>,
+}
+
+/// A text, formatted either with entities or with a markup language
+#[derive(Clone, Debug)]
+struct FormattedText {
+    text: String,
+    entities: Vec<MessageEntity>,
+    markup: Markup,
 }
 
 #[derive(Clone, Debug)]
 /// A media in the chat
 enum MessagePart {
-    Text(String),
-    Image(InputFile),
+    Text {
+        text: FormattedText,
+        keyboard: Option<InlineKeyboardMarkup>,
+    },
+    Image {
+        image: InputFile,
+        caption: Option<FormattedText>,
+        keyboard: Option<InlineKeyboardMarkup>,
+    },
+    /// An animated GIF
+    Animation {
+        animation: InputFile,
+        caption: Option<FormattedText>,
+        keyboard: Option<InlineKeyboardMarkup>,
+    },
+    /// An MP4 video
+    Video {
+        video: InputFile,
+        caption: Option<FormattedText>,
+        keyboard: Option<InlineKeyboardMarkup>,
+    },
+    /// A group of images and videos sent together; the caption is attached to the first media
+    Album {
+        media: Vec<(InputFile, MediaKind)>,
+        caption: Option<FormattedText>,
+    },
+}
+
+/// Attach `caption` to a request or to an album media
+macro_rules! with_caption {
+    ($request:expr, $caption:expr) => {{
+        let mut request = $request;
+        if let Some(caption) = $caption {
+            request = request.caption(caption.text);
+            if let Some(parse_mode) = caption.markup.parse_mode() {
+                request = request.parse_mode(parse_mode);
+            }
+            if !caption.entities.is_empty() {
+                request = request.caption_entities(caption.entities);
+            }
+        }
+        request
+    }};
+}
+
+/// Apply the send `options` to a request
+macro_rules! with_options {
+    ($request:expr, $options:expr) => {{
+        let mut request = $request;
+        if $options.silent {
+            request = request.disable_notification(true);
+        }
+        if let Some(message_id) = $options.reply_to {
+            request = request
+                .reply_parameters(ReplyParameters::new(message_id).allow_sending_without_reply());
+        }
+        request
+    }};
 }
 
 impl Answer {
     /// Build a simple one text answer
     pub fn simple_text(text: impl ToString) -> Self {
-        Self {
-            script: vec![MessagePart::Text(text.to_string())],
+        AnswerBuilder::default().text(text).finalize()
+    }
+
+    /// Send the answer without notification
+    pub fn silent(mut self) -> Self {
+        self.options.silent = true;
+        self
+    }
+
+    /// Set the greeting delivered by the answer
+    pub fn with_delivery(mut self, delivery: Delivery) -> Self {
+        self.delivery = Some(delivery);
+        self
+    }
+
+    /// Take the greeting delivered by the answer, if any
+    pub fn take_delivery(&mut self) -> Option<Delivery> {
+        self.delivery.take()
+    }
+
+    /// Make the answer reply to `message` if it has been sent in a group, so the answer doesn't get lost in the chatter
+    pub fn reply_to(mut self, message: &Message) -> Self {
+        if message.chat.is_group() || message.chat.is_supergroup() {
+            self.options.reply_to = Some(message.id);
         }
+        self
     }
 
     /// Send answer
     pub async fn send(self, bot: &Bot, chat_id: ChatId) -> ResponseResult<()> {
         debug!("sending answer to chat {chat_id}",);
+        let mut options = self.options;
         for message in self.script.into_iter() {
             debug!("sending message {message:?}");
-            match message {
-                MessagePart::Image(image) => Self::send_image(bot, chat_id, image).await?,
-                MessagePart::Text(text) => Self::send_text(bot, chat_id, text).await?,
+            let result = match message {
+                MessagePart::Image {
+                    image,
+                    caption,
+                    keyboard,
+                } => Self::send_image(bot, chat_id, image, caption, keyboard, options).await,
+                MessagePart::Animation {
+                    animation,
+                    caption,
+                    keyboard,
+                } => {
+                    Self::send_animation(bot, chat_id, animation, caption, keyboard, options).await
+                }
+                MessagePart::Video {
+                    video,
+                    caption,
+                    keyboard,
+                } => Self::send_video(bot, chat_id, video, caption, keyboard, options).await,
+                MessagePart::Text { text, keyboard } => {
+                    Self::send_text(bot, chat_id, text, keyboard, options).await
+                }
+                MessagePart::Album { media, caption } => {
+                    Self::send_album(bot, chat_id, media, caption, options).await
+                }
+            };
+            if let Err(err) = result {
+                METRICS.send_failure(Self::error_kind(&err));
+                return Err(err);
             }
+            // only the first message replies
+            options.reply_to = None;
         }
         Ok(())
     }
 
+    /// Get a short name describing the kind of `err`
+    fn error_kind(err: &RequestError) -> &'static str {
+        match err {
+            RequestError::Api(_) => "api",
+            RequestError::MigrateToChatId(_) => "migrate_to_chat_id",
+            RequestError::RetryAfter(_) => "retry_after",
+            RequestError::Network(_) => "network",
+            RequestError::InvalidJson { .. } => "invalid_json",
+            RequestError::Io(_) => "io",
+        }
+    }
+
     /// Write text to chat
-    async fn send_text(bot: &Bot, chat_id: ChatId, message: String) -> ResponseResult<()> {
+    async fn send_text(
+        bot: &Bot,
+        chat_id: ChatId,
+        message: FormattedText,
+        keyboard: Option<InlineKeyboardMarkup>,
+        options: SendOptions,
+    ) -> ResponseResult<()> {
         debug!("sending text {message:?} to chat {chat_id}");
-        bot.send_message(chat_id, message).await.map(|_| ())
+        let mut request = bot.send_message(chat_id, message.text);
+        if let Some(parse_mode) = message.markup.parse_mode() {
+            request = request.parse_mode(parse_mode);
+        }
+        if !message.entities.is_empty() {
+            request = request.entities(message.entities);
+        }
+        if let Some(keyboard) = keyboard {
+            request = request.reply_markup(keyboard);
+        }
+        with_options!(request, options).await.map(|_| ())
     }
 
-    /// Send image to chat
-    async fn send_image(bot: &Bot, chat_id: ChatId, image: InputFile) -> ResponseResult<()> {
+    /// Send image to chat, with its caption
+    async fn send_image(
+        bot: &Bot,
+        chat_id: ChatId,
+        image: InputFile,
+        caption: Option<FormattedText>,
+        keyboard: Option<InlineKeyboardMarkup>,
+        options: SendOptions,
+    ) -> ResponseResult<()> {
         debug!("sending image {image:?} to chat {chat_id}");
-        bot.send_photo(chat_id, image).await.map(|_| ())
+        let mut request = with_caption!(bot.send_photo(chat_id, image), caption);
+        if let Some(keyboard) = keyboard {
+            request = request.reply_markup(keyboard);
+        }
+        with_options!(request, options).await.map(|_| ())
+    }
+
+    /// Send animation to chat, with its caption
+    async fn send_animation(
+        bot: &Bot,
+        chat_id: ChatId,
+        animation: InputFile,
+        caption: Option<FormattedText>,
+        keyboard: Option<InlineKeyboardMarkup>,
+        options: SendOptions,
+    ) -> ResponseResult<()> {
+        debug!("sending animation {animation:?} to chat {chat_id}");
+        let mut request = with_caption!(bot.send_animation(chat_id, animation), caption);
+        if let Some(keyboard) = keyboard {
+            request = request.reply_markup(keyboard);
+        }
+        with_options!(request, options).await.map(|_| ())
+    }
+
+    /// Send video to chat, with its caption
+    async fn send_video(
+        bot: &Bot,
+        chat_id: ChatId,
+        video: InputFile,
+        caption: Option<FormattedText>,
+        keyboard: Option<InlineKeyboardMarkup>,
+        options: SendOptions,
+    ) -> ResponseResult<()> {
+        debug!("sending video {video:?} to chat {chat_id}");
+        let mut request = with_caption!(bot.send_video(chat_id, video), caption);
+        if let Some(keyboard) = keyboard {
+            request = request.reply_markup(keyboard);
+        }
+        with_options!(request, options).await.map(|_| ())
+    }
+
+    /// Send a group of images and videos to chat, with the caption attached to the first media
+    async fn send_album(
+        bot: &Bot,
+        chat_id: ChatId,
+        media: Vec<(InputFile, MediaKind)>,
+        mut caption: Option<FormattedText>,
+        options: SendOptions,
+    ) -> ResponseResult<()> {
+        debug!("sending album of {} media to chat {chat_id}", media.len());
+        let media: Vec<InputMedia> = media
+            .into_iter()
+            .map(|(file, kind)| match kind {
+                MediaKind::Video => {
+                    InputMedia::Video(with_caption!(InputMediaVideo::new(file), caption.take()))
+                }
+                MediaKind::Image | MediaKind::Animation => {
+                    InputMedia::Photo(with_caption!(InputMediaPhoto::new(file), caption.take()))
+                }
+            })
+            .collect();
+        with_options!(bot.send_media_group(chat_id, media), options)
+            .await
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use url::Url;
+
+    use super::*;
+
+    #[test]
+    fn should_build_album() {
+        let url = Url::parse("https://example.com/caffe.jpg").unwrap();
+        let answer = AnswerBuilder::default()
+            .album(vec![(InputFile::url(url.clone()), MediaKind::Image)])
+            .caption("Buongiorno", Vec::new())
+            .finalize();
+        assert!(matches!(
+            answer.script.as_slice(),
+            [MessagePart::Image {
+                caption: Some(_),
+                ..
+            }]
+        ));
+        let answer = AnswerBuilder::default()
+            .album(vec![
+                (InputFile::url(url), MediaKind::Image);
+                MAX_ALBUM_SIZE + 2
+            ])
+            .caption("Buongiorno", Vec::new())
+            .finalize();
+        assert!(matches!(
+            answer.script.as_slice(),
+            [MessagePart::Album {
+                media,
+                caption: Some(_),
+            }] if media.len() == MAX_ALBUM_SIZE
+        ));
+    }
+
+    #[test]
+    fn should_build_album_with_animations() {
+        let image = Url::parse("https://example.com/caffe.jpg").unwrap();
+        let video = Url::parse("https://example.com/caffe.mp4").unwrap();
+        let animation = Url::parse("https://example.com/caffe.gif").unwrap();
+        let answer = AnswerBuilder::default()
+            .album(vec![
+                (InputFile::url(image), MediaKind::Image),
+                (InputFile::url(animation), MediaKind::Animation),
+                (InputFile::url(video), MediaKind::Video),
+            ])
+            .caption("Buongiorno", Vec::new())
+            .finalize();
+        assert!(matches!(
+            answer.script.as_slice(),
+            [
+                MessagePart::Album {
+                    media,
+                    caption: None,
+                },
+                MessagePart::Animation {
+                    caption: Some(_),
+                    ..
+                }
+            ] if media.len() == 2
+        ));
+    }
+
+    #[test]
+    fn should_add_media_by_kind() {
+        let url = Url::parse("https://example.com/caffe").unwrap();
+        let answer = AnswerBuilder::default()
+            .media(InputFile::url(url.clone()), MediaKind::Image)
+            .media(InputFile::url(url.clone()), MediaKind::Animation)
+            .media(InputFile::url(url), MediaKind::Video)
+            .finalize();
+        assert!(matches!(
+            answer.script.as_slice(),
+            [
+                MessagePart::Image { .. },
+                MessagePart::Animation { .. },
+                MessagePart::Video { .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn should_escape_markup() {
+        assert_eq!(Markup::Plain.escape("<b>Zia_Maria</b>"), "<b>Zia_Maria</b>");
+        assert_eq!(
+            Markup::Html.escape("<b>Zia & Maria</b>"),
+            "&lt;b&gt;Zia &amp; Maria&lt;/b&gt;"
+        );
+        assert_eq!(
+            Markup::MarkdownV2.escape("Buon {giorno}!"),
+            "Buon \\{giorno\\}\\!"
+        );
+    }
+
+    #[test]
+    fn should_format_markup() {
+        assert_eq!(Markup::Plain.bold("Caffè"), "Caffè");
+        assert_eq!(Markup::Html.bold("Caffè"), "<b>Caffè</b>");
+        assert_eq!(Markup::MarkdownV2.bold("Caffè"), "*Caffè*");
+        assert_eq!(Markup::Html.code("a < b"), "<code>a &lt; b</code>");
+        assert_eq!(Markup::MarkdownV2.code("{nome}"), "`{nome}`");
     }
 }
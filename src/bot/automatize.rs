@@ -2,24 +2,42 @@
 //!
 //! A module to automatize messages
 
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::{Duration, Instant};
+
 use buongiornissimo_rs::Greeting;
-use chrono::{Datelike, Local, NaiveDate};
+use chrono::Local;
 use teloxide::prelude::*;
-use teloxide::types::ChatId;
+use teloxide::types::{ChatId, UserId};
 use thiserror::Error;
+use tokio::sync::RwLock;
 use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
-use super::AnswerBuilder;
+use super::caption::Context;
+use super::i18n::Language;
+use super::providers::{GreetingImage, ImagesResult};
 use super::repository::Repository;
-use crate::repository::SqliteDb;
-use crate::utils::random as random_utils;
+use super::{AnswerBuilder, Buongiornissimo, keyboard, rating};
+use crate::metrics::METRICS;
+use crate::repository::delivery::Delivery;
+use crate::utils::date::PartialDate;
+
+const HAPPY_BIRTHDAY_JOB: &str = "happy_birthday";
+const GOOD_MORNING_JOB: &str = "good_morning";
+const GOOD_NIGHT_JOB: &str = "good_night";
+/// Jobs running while people may be sleeping, whose messages are sent without notification
+const SILENT_JOBS: &[&str] = &[GOOD_MORNING_JOB, GOOD_NIGHT_JOB];
 
 type AutomatizerResult<T> = Result<T, AutomatizerError>;
 
 /// Automatizer error
 #[derive(Debug, Error)]
 pub enum AutomatizerError {
+    #[error("automatizer already started")]
+    AlreadyStarted,
     #[error("scheduler error: {0}")]
     Scheduler(JobSchedulerError),
 }
@@ -32,22 +50,48 @@ impl From<JobSchedulerError> for AutomatizerError {
 
 /// Automatizer takes care of sending messages to subscribed users
 pub struct Automatizer {
-    db: SqliteDb,
+    repository: Repository,
     bot: Bot,
-    /// Stored to keep the scheduler alive for the lifetime of the application.
-    #[expect(dead_code, reason = "held to keep the scheduler running")]
-    scheduler: JobScheduler,
+    /// Whether the greeting of the day and its image are decided once per day, so they're the same in every chat
+    daily_greeting: bool,
+    /// Whether new scheduled jobs can be run; cleared on shutdown
+    accepting_jobs: AtomicBool,
+    /// Held for reading by every running job, so shutdown can wait for in-flight broadcasts by acquiring it for writing
+    in_flight: RwLock<()>,
+    /// Scheduled jobs currently running, with the instant they were started at
+    running_jobs: Mutex<HashMap<String, Instant>>,
+    /// Set once the scheduled jobs have been registered and the scheduler started
+    scheduler: OnceLock<JobScheduler>,
 }
 
 impl Automatizer {
-    /// Start automatizer with the given database and bot
-    pub async fn start(db: SqliteDb, bot: Bot) -> AutomatizerResult<Self> {
+    /// Start automatizer with the given repository and bot; `daily_greeting` enables the daily greeting mode
+    pub async fn start(
+        repository: Repository,
+        bot: Bot,
+        daily_greeting: bool,
+    ) -> AutomatizerResult<Arc<Self>> {
         debug!("starting automatizer");
-        Ok(Self {
-            db,
+        let automatizer = Arc::new(Self {
+            repository,
             bot,
-            scheduler: Self::setup_cron_scheduler().await?,
-        })
+            daily_greeting,
+            accepting_jobs: AtomicBool::new(true),
+            in_flight: RwLock::new(()),
+            running_jobs: Mutex::new(HashMap::new()),
+            scheduler: OnceLock::new(),
+        });
+        let scheduler = Self::setup_cron_scheduler(Arc::downgrade(&automatizer)).await?;
+        if automatizer.scheduler.set(scheduler).is_err() {
+            return Err(AutomatizerError::AlreadyStarted);
+        }
+        automatizer.update_subscribers_metric().await;
+        Ok(automatizer)
+    }
+
+    /// Whether the greeting of the day and its image are decided once per day, so they're the same in every chat
+    pub fn daily_greeting(&self) -> bool {
+        self.daily_greeting
     }
 
     /// Subscribe a chat to the automatizer
@@ -55,6 +99,7 @@ impl Automatizer {
         let repository = self.repository();
         repository.insert_chat(*chat).await?;
         info!("subscribed {} to the automatizer", chat);
+        self.update_subscribers_metric().await;
         Ok(())
     }
 
@@ -65,6 +110,7 @@ impl Automatizer {
         info!("deleted birthdays associated to chat {}", chat);
         repository.delete_chat(*chat).await?;
         info!("unsubscribed {} from the automatizer", chat);
+        self.update_subscribers_metric().await;
         Ok(())
     }
 
@@ -73,11 +119,12 @@ impl Automatizer {
         &self,
         chat: &ChatId,
         name: String,
-        date: NaiveDate,
+        date: PartialDate,
+        user: Option<UserId>,
     ) -> anyhow::Result<()> {
         let repository = self.repository();
         repository
-            .insert_birthday(*chat, name.clone(), date)
+            .insert_birthday(*chat, name.clone(), date, user)
             .await?;
         info!(
             "registered birthday for {}, name {}, date: {}",
@@ -86,9 +133,84 @@ impl Automatizer {
         Ok(())
     }
 
+    /// Set the birthday of `user` in `chat`, replacing the previous one
+    pub async fn set_user_birthday(
+        &self,
+        chat: &ChatId,
+        user: UserId,
+        name: String,
+        date: PartialDate,
+    ) -> anyhow::Result<()> {
+        self.repository()
+            .set_user_birthday(*chat, user, name, date)
+            .await?;
+        info!(
+            "registered birthday for {}, user {}, date: {}",
+            chat, user, date
+        );
+        Ok(())
+    }
+
+    /// Forget all the data about `user`, including the subscription of the private chat with the user
+    pub async fn forget_user(&self, user: UserId) -> anyhow::Result<()> {
+        self.repository().forget_user(user).await?;
+        info!("forgot user {}", user);
+        self.update_subscribers_metric().await;
+        Ok(())
+    }
+
     /// Get subscribed chats
     pub async fn subscribed_chats(&self) -> anyhow::Result<Vec<ChatId>> {
-        self.repository().get_subscribed_chats().await
+        let chats = self.repository().get_subscribed_chats().await?;
+        METRICS.set_subscribers(chats.len());
+        Ok(chats)
+    }
+
+    /// Whether the job scheduler is running
+    pub async fn is_scheduler_running(&self) -> bool {
+        match self.scheduler.get() {
+            Some(scheduler) => {
+                self.accepting_jobs.load(Ordering::Relaxed) && scheduler.inited().await
+            }
+            None => false,
+        }
+    }
+
+    /// Stop accepting new scheduled jobs; jobs already running are not affected
+    pub fn stop_accepting_jobs(&self) {
+        info!("automatizer won't accept new jobs");
+        self.accepting_jobs.store(false, Ordering::Relaxed);
+    }
+
+    /// Stop accepting new scheduled jobs, wait up to `deadline` for in-flight broadcasts to complete,
+    /// then shut the scheduler down and close the database
+    pub async fn shutdown(&self, deadline: Duration) {
+        info!("shutting down automatizer");
+        self.stop_accepting_jobs();
+        match tokio::time::timeout(deadline, self.in_flight.write()).await {
+            Ok(_) => info!("all in-flight broadcasts completed"),
+            Err(_) => warn!(
+                "in-flight broadcasts didn't complete within {}s; shutting down anyway",
+                deadline.as_secs()
+            ),
+        }
+        if let Some(scheduler) = self.scheduler.get()
+            && let Err(err) = scheduler.clone().shutdown().await
+        {
+            error!("failed to shutdown scheduler: {}", err);
+        }
+        self.repository.close().await;
+        info!("automatizer stopped");
+    }
+
+    /// Get the scheduled jobs currently running and for how long they have been running
+    pub fn running_jobs(&self) -> Vec<(String, Duration)> {
+        self.running_jobs
+            .lock()
+            .expect("running jobs lock poisoned")
+            .iter()
+            .map(|(name, started_at)| (name.clone(), started_at.elapsed()))
+            .collect()
     }
 
     /// Send happy birthday greetings for today's birthdays
@@ -97,87 +219,235 @@ impl Automatizer {
         if today_birthdays.is_empty() {
             return Ok(());
         }
-        let image = super::Buongiornissimo::get_greeting_image(Greeting::Compleanno).await?;
-        for (chat, name, _) in today_birthdays.into_iter() {
-            if let Err(err) = AnswerBuilder::default()
-                .image(image.clone())
-                .text(format!("Buon compleanno {}!", name))
-                .finalize()
-                .send(&self.bot, chat)
+        let image =
+            match Buongiornissimo::get_greeting_image(&self.repository(), Greeting::Compleanno)
                 .await
             {
-                error!("failed to send happy birthday to {}: {}", chat, err);
+                Ok(image) => image,
+                Err(err) => {
+                    self.log_delivery(Delivery::scrape_failed(
+                        HAPPY_BIRTHDAY_JOB,
+                        format!("{:?}", Greeting::Compleanno),
+                        err.provider_name(),
+                        &err,
+                    ))
+                    .await;
+                    return Err(err.into());
+                }
+            };
+        for (chat, name, _, user) in today_birthdays.into_iter() {
+            let lang = self.language(chat).await;
+            let mut context = Context::today(lang).await.with_name(name);
+            if let Some(user) = user {
+                context = context.with_user(user);
+            }
+            let mut answer = AnswerBuilder::default().album(
+                Buongiornissimo::greeting_media(
+                    &self.repository(),
+                    chat,
+                    std::slice::from_ref(&image),
+                )
+                .await,
+            );
+            if let Some((caption, entities)) =
+                Buongiornissimo::birthday_caption(&self.repository(), chat, lang)
+                    .await
+                    .and_then(|template| template.render(&context))
+            {
+                answer = answer.caption(caption, entities);
+            }
+            let result = answer
+                .keyboard(keyboard::another_one(
+                    lang,
+                    Greeting::Compleanno,
+                    rating::image_key(&image.url),
+                ))
+                .finalize()
+                .send(&self.bot, chat)
+                .await;
+            let delivery = Buongiornissimo::greeting_delivery(
+                chat,
+                HAPPY_BIRTHDAY_JOB,
+                Greeting::Compleanno,
+                std::slice::from_ref(&image),
+            );
+            match result {
+                Ok(()) => self.log_delivery(delivery).await,
+                Err(err) => {
+                    error!("failed to send happy birthday to {}: {}", chat, err);
+                    self.log_delivery(delivery.failed(err)).await;
+                }
             }
         }
         Ok(())
     }
 
-    /// Send good morning greeting
+    /// Send good morning greeting; in daily greeting mode, it's the same greeting of the day sent by the commands
     pub async fn send_good_morning(&self) -> anyhow::Result<()> {
-        self.send_greeting(buongiornissimo_rs::greeting_of_the_day(
-            Local::now().naive_local().into(),
-            *random_utils::choice(&[true, false]),
-        ))
-        .await
+        let media =
+            Buongiornissimo::greeting_of_the_day(&self.repository(), self.daily_greeting).await;
+        self.send_greeting(GOOD_MORNING_JOB, media).await
     }
 
-    /// Send generic greeting to all subscribed chats
-    pub async fn send_greeting(&self, media: Greeting) -> anyhow::Result<()> {
+    /// Send generic greeting to all subscribed chats; `job` is the name of the job recorded in the delivery log
+    pub async fn send_greeting(&self, job: &str, media: Greeting) -> anyhow::Result<()> {
         let subscribed_chats = self.subscribed_chats().await?;
         if subscribed_chats.is_empty() {
             return Ok(());
         }
-        let greeting = super::Buongiornissimo::get_greeting_image(media).await?;
-        let answer = AnswerBuilder::default().image(greeting).finalize();
+        let mut album_sizes = Vec::with_capacity(subscribed_chats.len());
         for chat in subscribed_chats.iter() {
-            if let Err(err) = answer.clone().send(&self.bot, *chat).await {
-                error!("failed to send scheduled greeting to {}: {}", chat, err);
+            album_sizes.push(self.album_size(*chat).await);
+        }
+        let amount = album_sizes.iter().copied().max().unwrap_or(1);
+        let images = match self.greeting_images(job, media, amount).await {
+            Ok(images) => images,
+            Err(err) => {
+                self.log_delivery(Delivery::scrape_failed(
+                    job,
+                    format!("{:?}", media),
+                    err.provider_name(),
+                    &err,
+                ))
+                .await;
+                return Err(err.into());
+            }
+        };
+        for (chat, album_size) in subscribed_chats.iter().zip(album_sizes) {
+            let lang = self.language(*chat).await;
+            let images = &images[..album_size.min(images.len())];
+            let context = Context::today(lang).await;
+            let caption = Buongiornissimo::caption(&self.repository(), *chat, media)
+                .await
+                .and_then(|template| template.render(&context));
+            let mut answer = Buongiornissimo::greeting_answer(
+                &self.repository(),
+                *chat,
+                images,
+                caption,
+                media,
+                lang,
+            )
+            .await;
+            if SILENT_JOBS.contains(&job) {
+                answer = answer.silent();
+            }
+            let delivery = Buongiornissimo::greeting_delivery(*chat, job, media, images);
+            match answer.send(&self.bot, *chat).await {
+                Ok(()) => self.log_delivery(delivery).await,
+                Err(err) => {
+                    error!("failed to send scheduled greeting to {}: {}", chat, err);
+                    self.log_delivery(delivery.failed(err)).await;
+                }
             }
         }
         Ok(())
     }
 
+    /// Get up to `amount` images of `media` for the broadcast of `job`; in daily greeting mode the good morning gets the images of the day
+    async fn greeting_images(
+        &self,
+        job: &str,
+        media: Greeting,
+        amount: usize,
+    ) -> ImagesResult<Vec<GreetingImage>> {
+        let repository = self.repository();
+        if self.daily_greeting && job == GOOD_MORNING_JOB {
+            Buongiornissimo::get_greeting_images_of_the_day(&repository, media, amount).await
+        } else {
+            Buongiornissimo::get_greeting_images(&repository, media, amount).await
+        }
+    }
+
+    /// Write delivery to the delivery log. Failures are only logged, since they must not stop the broadcast
+    async fn log_delivery(&self, delivery: Delivery) {
+        if let Err(err) = self.repository().insert_delivery(delivery).await {
+            error!("failed to write delivery log: {}", err);
+        }
+    }
+
+    /// Refresh the subscribers gauge with the current amount of subscribed chats
+    async fn update_subscribers_metric(&self) {
+        if let Err(err) = self.subscribed_chats().await {
+            error!("failed to update subscribers metric: {}", err);
+        }
+    }
+
+    /// Get the amount of images to send to `chat`; failures are only logged, since they must not stop the broadcast
+    async fn album_size(&self, chat: ChatId) -> usize {
+        match self.repository().get_album_size(chat).await {
+            Ok(size) => size.unwrap_or(1),
+            Err(err) => {
+                error!("failed to get album size of {}: {}", chat, err);
+                1
+            }
+        }
+    }
+
+    /// Get the language of `chat`; failures are only logged, since they must not stop the broadcast
+    async fn language(&self, chat: ChatId) -> Language {
+        match self.repository().get_language(chat).await {
+            Ok(language) => language.unwrap_or_default(),
+            Err(err) => {
+                error!("failed to get language of {}: {}", chat, err);
+                Language::default()
+            }
+        }
+    }
+
     fn repository(&self) -> Repository {
-        Repository::new(self.db.clone())
+        self.repository.clone()
     }
 
     /// Retrieve today's birthdays
-    async fn today_birthdays(&self) -> anyhow::Result<Vec<(ChatId, String, NaiveDate)>> {
-        let today = Local::now().naive_local();
+    async fn today_birthdays(
+        &self,
+    ) -> anyhow::Result<Vec<(ChatId, String, PartialDate, Option<UserId>)>> {
+        let today = Local::now().date_naive();
         Ok(self
             .repository()
             .get_birthdays()
             .await?
             .into_iter()
-            .filter(|(_, _, date)| date.month() == today.month() && date.day() == today.day())
+            .filter(|(_, _, date, _)| date.is_anniversary_of(today))
             .collect())
     }
 
-    /// Setup cron scheduler
-    async fn setup_cron_scheduler() -> AutomatizerResult<JobScheduler> {
+    /// Setup cron scheduler.
+    ///
+    /// Jobs only hold a weak reference to the automatizer, since the automatizer owns the scheduler
+    async fn setup_cron_scheduler(automatizer: Weak<Self>) -> AutomatizerResult<JobScheduler> {
         let timezone = chrono::Local::now().timezone();
         let sched = JobScheduler::new().await?;
 
         let jobs: &[(&str, &str)] = &[
-            ("0 30 8 * * *", "happy_birthday"),
-            ("0 30 6 * * *", "good_morning"),
+            ("0 30 8 * * *", HAPPY_BIRTHDAY_JOB),
+            ("0 30 6 * * *", GOOD_MORNING_JOB),
             ("0 15 20 * * Fri", "good_weekend"),
             ("0 30 12 * * *", "good_lunch"),
             ("0 0 14 * * *", "good_afternoon"),
             ("0 0 18 * * *", "good_evening"),
             ("0 30 19 * * *", "good_dinner"),
-            ("0 30 21 * * *", "good_night"),
+            ("0 30 21 * * *", GOOD_NIGHT_JOB),
         ];
 
         for &(cron_expr, job_name) in jobs {
             let name = job_name.to_string();
+            let automatizer = automatizer.clone();
             let job = Job::new_async_tz(cron_expr, timezone, move |_, _| {
                 let name = name.clone();
+                let automatizer = automatizer.upgrade();
                 Box::pin(async move {
+                    let Some(automatizer) = automatizer else {
+                        debug!("automatizer has been dropped; skipping {name}_job");
+                        return;
+                    };
                     info!("running {name}_job");
-                    if let Err(err) = Self::run_scheduled_job(&name).await {
+                    let started_at = Instant::now();
+                    if let Err(err) = automatizer.run_scheduled_job(&name).await {
                         error!("{name}_job failed: {}", err);
                     }
+                    METRICS.job_run(&name, started_at.elapsed().as_secs_f64());
                 })
             })?;
             sched.add(job).await?;
@@ -190,22 +460,77 @@ impl Automatizer {
             .map_err(AutomatizerError::from)
     }
 
-    /// Run a scheduled job by name, dispatching to the AUTOMATIZER static
-    async fn run_scheduled_job(name: &str) -> anyhow::Result<()> {
-        let automatizer = super::AUTOMATIZER
-            .get()
-            .ok_or_else(|| anyhow::anyhow!("automatizer not initialized"))?;
+    /// Run a scheduled job by name, unless the automatizer is shutting down
+    async fn run_scheduled_job(&self, name: &str) -> anyhow::Result<()> {
+        let _in_flight = self.in_flight.read().await;
+        if !self.accepting_jobs.load(Ordering::Relaxed) {
+            info!("automatizer is shutting down; skipping {name}_job");
+            return Ok(());
+        }
+        self.running_jobs
+            .lock()
+            .expect("running jobs lock poisoned")
+            .insert(name.to_string(), Instant::now());
+        let result = self.dispatch_scheduled_job(name).await;
+        self.running_jobs
+            .lock()
+            .expect("running jobs lock poisoned")
+            .remove(name);
+
+        result
+    }
 
+    /// Dispatch the scheduled job with the given name
+    async fn dispatch_scheduled_job(&self, name: &str) -> anyhow::Result<()> {
         match name {
-            "happy_birthday" => automatizer.send_happy_birthday().await,
-            "good_morning" => automatizer.send_good_morning().await,
-            "good_weekend" => automatizer.send_greeting(Greeting::Weekend).await,
-            "good_lunch" => automatizer.send_greeting(Greeting::BuonPranzo).await,
-            "good_afternoon" => automatizer.send_greeting(Greeting::BuonPomeriggio).await,
-            "good_evening" => automatizer.send_greeting(Greeting::BuonaSerata).await,
-            "good_dinner" => automatizer.send_greeting(Greeting::BuonaCena).await,
-            "good_night" => automatizer.send_greeting(Greeting::BuonaNotte).await,
+            HAPPY_BIRTHDAY_JOB => self.send_happy_birthday().await,
+            GOOD_MORNING_JOB => self.send_good_morning().await,
+            "good_weekend" => self.send_greeting(name, Greeting::Weekend).await,
+            "good_lunch" => self.send_greeting(name, Greeting::BuonPranzo).await,
+            "good_afternoon" => self.send_greeting(name, Greeting::BuonPomeriggio).await,
+            "good_evening" => self.send_greeting(name, Greeting::BuonaSerata).await,
+            "good_dinner" => self.send_greeting(name, Greeting::BuonaCena).await,
+            GOOD_NIGHT_JOB => self.send_greeting(name, Greeting::BuonaNotte).await,
             _ => anyhow::bail!("unknown job: {name}"),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+    use tempfile::NamedTempFile;
+
+    use super::*;
+    use crate::repository::SqliteDb;
+
+    async fn setup_automatizer() -> (Arc<Automatizer>, NamedTempFile) {
+        let database = NamedTempFile::new().unwrap();
+        let db = SqliteDb::connect(&database.path().to_string_lossy())
+            .await
+            .unwrap();
+        let automatizer =
+            Automatizer::start(Repository::new(db), Bot::new("123456:dummy-token"), false)
+                .await
+                .unwrap();
+        (automatizer, database)
+    }
+
+    #[tokio::test]
+    async fn should_run_isolated_instances() {
+        let (first, _first_db) = setup_automatizer().await;
+        let (second, _second_db) = setup_automatizer().await;
+        assert!(first.is_scheduler_running().await);
+        assert!(second.is_scheduler_running().await);
+
+        assert!(first.subscribe(&ChatId(1)).await.is_ok());
+        assert_eq!(first.subscribed_chats().await.unwrap(), vec![ChatId(1)]);
+        assert!(second.subscribed_chats().await.unwrap().is_empty());
+
+        first.shutdown(Duration::from_secs(1)).await;
+        assert!(!first.is_scheduler_running().await);
+        assert!(second.is_scheduler_running().await);
+        second.shutdown(Duration::from_secs(1)).await;
+    }
+}
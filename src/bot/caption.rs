@@ -0,0 +1,453 @@
+//! # Caption
+//!
+//! This module implements the caption templates attached to greeting images
+
+use std::fmt;
+
+use buongiornissimo_rs::Greeting;
+use chrono::{Datelike, Local, NaiveDate};
+use teloxide::types::{MessageEntity, UserId};
+use thiserror::Error;
+
+use super::i18n::{Key, Language};
+use super::{mention, weather};
+use crate::utils::saints;
+
+/// Maximum length of a photo caption, in UTF-16 code units as telegram measures it
+const MAX_CAPTION_LEN: usize = 1024;
+/// Maximum length of a caption template, which is the maximum length of a photo caption
+pub const MAX_TEMPLATE_LEN: usize = MAX_CAPTION_LEN;
+/// Appended to the captions cut to the maximum length
+const ELLIPSIS: char = '…';
+
+/// Caption template error
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TemplateError {
+    #[error("unknown placeholder {{{0}}}")]
+    UnknownPlaceholder(String),
+    #[error("placeholder is not closed")]
+    UnclosedPlaceholder,
+    #[error("template is too long")]
+    TooLong,
+}
+
+/// A placeholder of a caption template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placeholder {
+    /// The name of the person greeted
+    Name,
+    /// Today's weekday
+    Day,
+    /// Today's date
+    Date,
+    /// The saint of the day
+    Saint,
+    /// Today's weather
+    Weather,
+}
+
+impl Placeholder {
+    pub const ALL: [Self; 5] = [
+        Self::Name,
+        Self::Day,
+        Self::Date,
+        Self::Saint,
+        Self::Weather,
+    ];
+
+    /// Get the placeholder from its name; both the italian and the english names are accepted
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "nome" | "name" => Some(Self::Name),
+            "giorno" | "day" => Some(Self::Day),
+            "data" | "date" => Some(Self::Date),
+            "santo" | "saint" => Some(Self::Saint),
+            "meteo" | "weather" => Some(Self::Weather),
+            _ => None,
+        }
+    }
+
+    /// Get the name of the placeholder in `lang`
+    pub fn name(self, lang: Language) -> &'static str {
+        match (self, lang) {
+            (Self::Name, Language::English) => "name",
+            (Self::Day, Language::English) => "day",
+            (Self::Date, Language::English) => "date",
+            (Self::Saint, Language::English) => "saint",
+            (Self::Weather, Language::English) => "weather",
+            (Self::Name, _) => "nome",
+            (Self::Day, _) => "giorno",
+            (Self::Date, _) => "data",
+            (Self::Saint, _) => "santo",
+            (Self::Weather, _) => "meteo",
+        }
+    }
+
+    /// List the placeholders, in `lang`, as they are written in the templates
+    pub fn list(lang: Language) -> String {
+        Self::ALL
+            .iter()
+            .map(|placeholder| format!("{{{}}}", placeholder.name(lang)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// A part of a caption template
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// A caption template, such as `Buon {giorno} {nome}! ☕`.
+///
+/// Placeholders are written between braces; literal braces are written doubled (`{{` and `}}`)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    /// Parse a caption template
+    pub fn parse(template: &str) -> Result<Self, TemplateError> {
+        if template.chars().count() > MAX_TEMPLATE_LEN {
+            return Err(TemplateError::TooLong);
+        }
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.next_if_eq(&'{').is_some() => text.push('{'),
+                '}' if chars.next_if_eq(&'}').is_some() => text.push('}'),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(TemplateError::UnclosedPlaceholder),
+                        }
+                    }
+                    let placeholder =
+                        Placeholder::parse(&name).ok_or(TemplateError::UnknownPlaceholder(name))?;
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Placeholder(placeholder));
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Get the default template for `greeting` in `lang`, if any.
+    ///
+    /// Birthday images always come with the wishes, while the other images have no caption by default
+    pub fn default_for(greeting: Greeting, lang: Language) -> Option<Self> {
+        match greeting {
+            Greeting::Compleanno => Self::parse(lang.text(Key::HappyBirthday)).ok(),
+            _ => None,
+        }
+    }
+
+    /// Render the template with the values in `context`; if the user of the context is set, the name mentions the user.
+    /// The spaces before a placeholder without value are dropped, and so are the following ones at the beginning of the caption.
+    ///
+    /// The placeholders may make the caption longer than telegram allows: in that case it's cut, with the entities which don't fit.
+    ///
+    /// Returns `None` if the rendered caption is empty
+    pub fn render(&self, context: &Context) -> Option<(String, Vec<MessageEntity>)> {
+        let mut caption = String::new();
+        let mut entities = Vec::new();
+        let mut trim_start = false;
+        for segment in self.segments.iter() {
+            let value = match segment {
+                Segment::Text(text) if trim_start => text.trim_start(),
+                Segment::Text(text) => text.as_str(),
+                Segment::Placeholder(Placeholder::Name) => match context.name.as_deref() {
+                    Some(name) => {
+                        if let Some(user) = context.user {
+                            entities.push(mention::entity(user, &caption, name));
+                        }
+                        name
+                    }
+                    None => "",
+                },
+                Segment::Placeholder(Placeholder::Day) => context.day.as_str(),
+                Segment::Placeholder(Placeholder::Date) => context.date.as_str(),
+                Segment::Placeholder(Placeholder::Saint) => context.saint,
+                Segment::Placeholder(Placeholder::Weather) => {
+                    context.weather.as_deref().unwrap_or_default()
+                }
+            };
+            if value.is_empty() && matches!(segment, Segment::Placeholder(_)) {
+                caption.truncate(caption.trim_end().len());
+                trim_start = caption.is_empty();
+                continue;
+            }
+            trim_start = trim_start && value.is_empty();
+            caption.push_str(value);
+        }
+        if caption.trim().is_empty() {
+            return None;
+        }
+        truncate(&mut caption, &mut entities);
+        Some((caption, entities))
+    }
+}
+
+/// Cut `caption` to the maximum caption length, ending it with an ellipsis, and drop the `entities` which don't fit anymore
+fn truncate(caption: &mut String, entities: &mut Vec<MessageEntity>) {
+    if caption.encode_utf16().count() <= MAX_CAPTION_LEN {
+        return;
+    }
+    let mut len = 0;
+    let end = caption
+        .char_indices()
+        .find(|(_, c)| {
+            len += c.len_utf16();
+            len > MAX_CAPTION_LEN - ELLIPSIS.len_utf16()
+        })
+        .map(|(index, _)| index)
+        .unwrap_or(caption.len());
+    caption.truncate(end);
+    caption.truncate(caption.trim_end().len());
+    let len = caption.encode_utf16().count();
+    entities.retain(|entity| entity.offset + entity.length <= len);
+    caption.push(ELLIPSIS);
+}
+
+/// Format the template as it can be parsed back
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Text(text) => f.write_str(&text.replace('{', "{{").replace('}', "}}"))?,
+                Segment::Placeholder(placeholder) => {
+                    write!(f, "{{{}}}", placeholder.name(Language::Italian))?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The values of the placeholders of a caption template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Context {
+    name: Option<String>,
+    user: Option<UserId>,
+    day: String,
+    date: String,
+    saint: &'static str,
+    weather: Option<String>,
+}
+
+impl Context {
+    /// Create the context for today in `lang`, with today's weather if available
+    pub async fn today(lang: Language) -> Self {
+        Self::new(Local::now().date_naive(), lang).with_weather(weather::forecast(lang).await)
+    }
+
+    /// Create the context for `date` in `lang`, without the weather
+    pub fn new(date: NaiveDate, lang: Language) -> Self {
+        Self {
+            name: None,
+            user: None,
+            day: lang.weekday(date.weekday()).to_string(),
+            date: lang.date(date.into()),
+            saint: saints::saint_of_the_day(date),
+            weather: None,
+        }
+    }
+
+    /// Set the weather forecast
+    pub fn with_weather(mut self, weather: Option<String>) -> Self {
+        self.weather = weather;
+        self
+    }
+
+    /// Set the name of the person greeted
+    pub fn with_name(mut self, name: impl ToString) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Set the user greeted, who is mentioned in the caption
+    pub fn with_user(mut self, user: UserId) -> Self {
+        self.user = Some(user);
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn context() -> Context {
+        Context::new(
+            NaiveDate::from_ymd_opt(2026, 10, 19).unwrap(),
+            Language::Italian,
+        )
+    }
+
+    #[test]
+    fn should_parse_template() {
+        assert_eq!(
+            Template::parse("Buon {giorno} {Nome}! {{☕}}").unwrap(),
+            Template {
+                segments: vec![
+                    Segment::Text("Buon ".to_string()),
+                    Segment::Placeholder(Placeholder::Day),
+                    Segment::Text(" ".to_string()),
+                    Segment::Placeholder(Placeholder::Name),
+                    Segment::Text("! {☕}".to_string()),
+                ]
+            }
+        );
+        assert_eq!(Template::parse("").unwrap(), Template::default());
+    }
+
+    #[test]
+    fn should_not_parse_bad_template() {
+        assert_eq!(
+            Template::parse("Buon {onomastico}"),
+            Err(TemplateError::UnknownPlaceholder("onomastico".to_string()))
+        );
+        assert_eq!(
+            Template::parse("Buon {giorno"),
+            Err(TemplateError::UnclosedPlaceholder)
+        );
+        assert_eq!(
+            Template::parse(&"☕".repeat(MAX_TEMPLATE_LEN + 1)),
+            Err(TemplateError::TooLong)
+        );
+    }
+
+    #[test]
+    fn should_format_template() {
+        let template = Template::parse("Happy {day} {name}! {{☕}}").unwrap();
+        assert_eq!(template.to_string(), "Happy {giorno} {nome}! {{☕}}");
+        assert_eq!(Template::parse(&template.to_string()).unwrap(), template);
+    }
+
+    #[test]
+    fn should_render_template() {
+        let template = Template::parse("Buon {giorno} {nome}, oggi è il {data} ☕").unwrap();
+        assert_eq!(
+            template.render(&context().with_name("Mario")),
+            Some((
+                "Buon lunedì Mario, oggi è il 19 ottobre 2026 ☕".to_string(),
+                Vec::new()
+            ))
+        );
+        assert_eq!(
+            template.render(&context()),
+            Some((
+                "Buon lunedì, oggi è il 19 ottobre 2026 ☕".to_string(),
+                Vec::new()
+            ))
+        );
+        assert_eq!(Template::parse("{nome}").unwrap().render(&context()), None);
+    }
+
+    #[test]
+    fn should_render_saint_and_weather() {
+        let template = Template::parse("Oggi è {santo} {meteo}").unwrap();
+        assert_eq!(
+            template.render(&context().with_weather(Some("☀️ Soleggiato +18°C".to_string()))),
+            Some((
+                "Oggi è San Paolo della Croce ☀️ Soleggiato +18°C".to_string(),
+                Vec::new()
+            ))
+        );
+        assert_eq!(
+            template.render(&context()),
+            Some(("Oggi è San Paolo della Croce".to_string(), Vec::new()))
+        );
+    }
+
+    #[test]
+    fn should_drop_spaces_around_empty_placeholders() {
+        let template = Template::parse("{meteo} {nome} Auguri {name}!").unwrap();
+        assert_eq!(
+            template.render(&context()),
+            Some(("Auguri!".to_string(), Vec::new()))
+        );
+        let template = Template::parse("{meteo} Auguri {nome}!").unwrap();
+        assert_eq!(
+            template.render(&context().with_name("Mario").with_user(UserId(42))),
+            Some((
+                "Auguri Mario!".to_string(),
+                vec![MessageEntity::text_link(UserId(42).url(), 7, 5)]
+            ))
+        );
+    }
+
+    #[test]
+    fn should_render_template_with_mention() {
+        let template = Template::parse("🎂 Auguri {nome}!").unwrap();
+        assert_eq!(
+            template.render(&context().with_name("Zia Maria").with_user(UserId(42))),
+            Some((
+                "🎂 Auguri Zia Maria!".to_string(),
+                vec![MessageEntity::text_link(UserId(42).url(), 10, 9)]
+            ))
+        );
+    }
+
+    #[test]
+    fn should_cut_captions_longer_than_telegram_allows() {
+        // the template fits, but the name doesn't
+        let template = format!("Auguri {{nome}} {}", "☕".repeat(MAX_TEMPLATE_LEN - 16));
+        let template = Template::parse(&template).unwrap();
+        let context = context().with_name("Zia Maria").with_user(UserId(42));
+        let (caption, entities) = template.render(&context).unwrap();
+        assert_eq!(caption.encode_utf16().count(), MAX_CAPTION_LEN);
+        assert!(caption.starts_with("Auguri Zia Maria ☕"));
+        assert!(caption.ends_with("☕…"));
+        assert_eq!(
+            entities,
+            vec![MessageEntity::text_link(UserId(42).url(), 7, 9)]
+        );
+        // a mention which doesn't fit is dropped
+        let template = format!("{} {{nome}}", "☕".repeat(MAX_TEMPLATE_LEN - 7));
+        let template = Template::parse(&template).unwrap();
+        let (caption, entities) = template.render(&context).unwrap();
+        assert!(caption.encode_utf16().count() <= MAX_CAPTION_LEN);
+        assert!(caption.ends_with('…'));
+        assert!(entities.is_empty());
+    }
+
+    #[test]
+    fn should_get_default_template() {
+        for language in Language::ALL {
+            let template = Template::default_for(Greeting::Compleanno, language).unwrap();
+            let (caption, _) = template.render(&context().with_name("Mario")).unwrap();
+            assert!(caption.contains("Mario"), "{caption} ({language:?})");
+        }
+        assert!(Template::default_for(Greeting::BuonaNotte, Language::Italian).is_none());
+    }
+
+    #[test]
+    fn should_list_placeholders() {
+        assert_eq!(
+            Placeholder::list(Language::Italian),
+            "{nome}, {giorno}, {data}, {santo}, {meteo}"
+        );
+        assert_eq!(
+            Placeholder::list(Language::English),
+            "{name}, {day}, {date}, {saint}, {weather}"
+        );
+    }
+}
@@ -2,8 +2,22 @@
 //!
 //! Big luca bot commands
 
-use chrono::NaiveDate;
-use teloxide::utils::command::BotCommands;
+use buongiornissimo_rs::Greeting;
+use teloxide::utils::command::{BotCommands, ParseError};
+use url::Url;
+
+use super::answer::MAX_ALBUM_SIZE;
+use super::greeting;
+use super::i18n::{Key, Language};
+use super::watermark::{Mark, Position};
+use crate::utils::date::{self as date_utils, PartialDate};
+
+/// Maximum amount of words a date can be made of (e.g. `12 marzo 1950`)
+const MAX_DATE_WORDS: usize = 3;
+/// Quotes which can wrap a name made of several words
+const QUOTES: &[(char, char)] = &[('"', '"'), ('“', '”'), ('«', '»'), ('\'', '\'')];
+/// Maximum length of the text of a watermark, in characters
+const MAX_WATERMARK_LENGTH: usize = 40;
 
 #[derive(BotCommands, Clone, Debug)]
 #[command(
@@ -11,35 +25,770 @@ use teloxide::utils::command::BotCommands;
     description = "Questi comandi sono disponibili:"
 )]
 pub enum Command {
+    #[command(
+        description = "scegli quante immagini ricevere nei messaggi automatici (es. /album 3)",
+        parse_with = parse_album_size
+    )]
+    Album { amount: Option<usize> },
+    #[command(description = "annulla l'operazione in corso")]
+    Annulla,
     #[command(
         description = "augura buon compleanno a qualcuno",
-        parse_with = "split"
+        parse_with = parse_name
     )]
     Auguri { name: String },
-    #[command(description = "ottieni un'immagine del buongiorno")]
-    Buongiornissimo,
-    #[command(description = "ottieni un'immagine del buon pomeriggio")]
-    Buonpomeriggio,
-    #[command(description = "ottieni un'immagine della buona notte")]
-    Buonanotte,
-    #[command(description = "ottieni un'immagine del buon pranzo")]
-    Buonpranzo,
-    #[command(description = "ottieni un'immagine di buona cena")]
-    Buonacena,
-    #[command(description = "ottieni un'immagine di buona serata")]
-    Buonaserata,
-    #[command(description = "ottieni un'immagine di buon weekend")]
-    Buonweekend,
+    #[command(
+        description = "ottieni un'immagine del buongiorno (es. /buongiornissimo 5 per un album)",
+        parse_with = parse_amount
+    )]
+    Buongiornissimo { amount: usize },
+    #[command(
+        description = "ottieni un'immagine del buon pomeriggio",
+        parse_with = parse_amount
+    )]
+    Buonpomeriggio { amount: usize },
+    #[command(
+        description = "ottieni un'immagine della buona notte",
+        parse_with = parse_amount
+    )]
+    Buonanotte { amount: usize },
+    #[command(
+        description = "ottieni un'immagine del buon pranzo",
+        parse_with = parse_amount
+    )]
+    Buonpranzo { amount: usize },
+    #[command(
+        description = "ottieni un'immagine di buona cena",
+        parse_with = parse_amount
+    )]
+    Buonacena { amount: usize },
+    #[command(
+        description = "ottieni un'immagine di buona serata",
+        parse_with = parse_amount
+    )]
+    Buonaserata { amount: usize },
+    #[command(
+        description = "ottieni un'immagine di buon weekend",
+        parse_with = parse_amount
+    )]
+    Buonweekend { amount: usize },
     #[command(description = "iscriviti ai messaggi automatici")]
     Caffeee,
-    #[command(description = "imposta un compleanno", parse_with = "split")]
-    Compleanno { name: String, date: NaiveDate },
+    #[command(
+        description = "rimuovi la didascalia di un saluto (es. /cancelladidascalia buonanotte)",
+        parse_with = parse_greeting
+    )]
+    CancellaDidascalia { greeting: Greeting },
+    #[command(description = "rimuovi la filigrana dalle immagini")]
+    CancellaFiligrana,
+    #[command(
+        description = "imposta un compleanno (es. /compleanno Zia Maria 12/03/1950 o /compleanno Mario 12 marzo)",
+        parse_with = parse_birthday
+    )]
+    Compleanno {
+        name: Option<String>,
+        date: PartialDate,
+    },
+    #[command(
+        description = "imposta la didascalia di un saluto (es. /didascalia buongiorno Buongiorno a tutti ☕)",
+        parse_with = parse_caption
+    )]
+    Didascalia {
+        greeting: Greeting,
+        template: Option<String>,
+    },
+    #[command(description = "cancella tutti i tuoi dati dal bot")]
+    Dimenticami,
+    #[command(
+        description = "imprimi un testo o un logo sulle immagini (es. /filigrana Caffè Club)",
+        parse_with = parse_watermark
+    )]
+    Filigrana { setting: Option<WatermarkSetting> },
+    #[command(
+        description = "genera un'immagine con il saluto, senza cercarla online (es. /genera buonanotte)",
+        parse_with = parse_optional_greeting
+    )]
+    Genera { greeting: Option<Greeting> },
+    #[command(
+        description = "registra il tuo compleanno in questo gruppo (es. /ilmiocompleanno 12 marzo)",
+        parse_with = parse_date
+    )]
+    IlMioCompleanno { date: PartialDate },
+    #[command(
+        description = "scegli la lingua del bot (es. /lingua en)",
+        parse_with = parse_language
+    )]
+    Lingua { language: Option<Language> },
+    #[command(description = "mostra il menu")]
+    Menu,
+    #[command(description = "registra un compleanno passo dopo passo")]
+    NuovoCompleanno,
+    #[command(description = "sfoglia le tue immagini preferite e inviane una nella chat")]
+    Preferiti,
+    #[command(
+        description = "proponi una tua immagine, con /proponi e il saluto nella didascalia di una foto",
+        parse_with = parse_greeting
+    )]
+    Proponi { greeting: Greeting },
     #[command(description = "disinscriviti dai messaggi automatici")]
     PuliziaKontatti,
     #[command(description = "ottieni la release attuale")]
     Release,
+    #[command(description = "visualizza le statistiche di consegna (solo amministratori)")]
+    Statistiche,
     #[command(description = "visualizza l'aiuto")]
     Help,
     #[command(description = "inizializza bot")]
     Start,
 }
+
+/// A watermark setting changed by the watermark command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatermarkSetting {
+    Mark(Mark),
+    Position(Position),
+    /// Opacity in percent
+    Opacity(u8),
+}
+
+impl Command {
+    /// Get the usage hint for the command in `text`, if it is a known command with bad arguments
+    pub fn usage_hint(text: &str, bot_name: &str) -> Option<Key> {
+        match Self::parse(text, bot_name) {
+            Ok(_) | Err(ParseError::UnknownCommand(_) | ParseError::WrongBotName(_)) => None,
+            Err(_) => {
+                let command = text
+                    .split_whitespace()
+                    .next()?
+                    .trim_start_matches('/')
+                    .split('@')
+                    .next()?
+                    .to_lowercase();
+                Some(match command.as_str() {
+                    "auguri" => Key::UsageAuguri,
+                    "compleanno" => Key::UsageCompleanno,
+                    "ilmiocompleanno" => Key::UsageIlMioCompleanno,
+                    "didascalia" => Key::UsageDidascalia,
+                    "cancelladidascalia" => Key::UsageCancellaDidascalia,
+                    "album" => Key::UsageAlbum,
+                    "filigrana" => Key::UsageFiligrana,
+                    "genera" => Key::UsageGenera,
+                    "proponi" => Key::UsageProponi,
+                    "buongiornissimo" | "buonpomeriggio" | "buonanotte" | "buonpranzo"
+                    | "buonacena" | "buonaserata" | "buonweekend" => Key::UsageGreeting,
+                    _ => Key::UsageGeneric,
+                })
+            }
+        }
+    }
+
+    /// Get the command name, without arguments
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Album { .. } => "album",
+            Self::Annulla => "annulla",
+            Self::Auguri { .. } => "auguri",
+            Self::Buongiornissimo { .. } => "buongiornissimo",
+            Self::Buonpomeriggio { .. } => "buonpomeriggio",
+            Self::Buonanotte { .. } => "buonanotte",
+            Self::Buonpranzo { .. } => "buonpranzo",
+            Self::Buonacena { .. } => "buonacena",
+            Self::Buonaserata { .. } => "buonaserata",
+            Self::Buonweekend { .. } => "buonweekend",
+            Self::Caffeee => "caffeee",
+            Self::CancellaDidascalia { .. } => "cancelladidascalia",
+            Self::CancellaFiligrana => "cancellafiligrana",
+            Self::Compleanno { .. } => "compleanno",
+            Self::Didascalia { .. } => "didascalia",
+            Self::Dimenticami => "dimenticami",
+            Self::Filigrana { .. } => "filigrana",
+            Self::Genera { .. } => "genera",
+            Self::IlMioCompleanno { .. } => "ilmiocompleanno",
+            Self::Lingua { .. } => "lingua",
+            Self::Menu => "menu",
+            Self::NuovoCompleanno => "nuovocompleanno",
+            Self::Preferiti => "preferiti",
+            Self::Proponi { .. } => "proponi",
+            Self::PuliziaKontatti => "puliziakontatti",
+            Self::Release => "release",
+            Self::Statistiche => "statistiche",
+            Self::Help => "help",
+            Self::Start => "start",
+        }
+    }
+}
+
+/// Parse a name made of the whole text, optionally wrapped in quotes
+fn parse_name(args: String) -> Result<(String,), ParseError> {
+    let args = args.trim();
+    let name = match split_quoted(args) {
+        Some((name, "")) => name,
+        Some(_) => return Err(too_many_arguments(1, "unexpected text after the name")),
+        None => args,
+    };
+    if name.is_empty() {
+        return Err(too_few_arguments(1, 0, "name is required"));
+    }
+
+    Ok((name.to_string(),))
+}
+
+/// Parse an optional language code or name
+fn parse_language(args: String) -> Result<(Option<Language>,), ParseError> {
+    let args = args.trim();
+    if args.is_empty() {
+        return Ok((None,));
+    }
+    Language::parse(args)
+        .map(|language| (Some(language),))
+        .ok_or_else(|| ParseError::IncorrectFormat(format!("unknown language {args}").into()))
+}
+
+/// Parse the optional amount of images to send, which defaults to one
+fn parse_amount(args: String) -> Result<(usize,), ParseError> {
+    amount(args.trim()).map(|amount| (amount.unwrap_or(1),))
+}
+
+/// Parse the optional amount of images to send in the automatic messages
+fn parse_album_size(args: String) -> Result<(Option<usize>,), ParseError> {
+    amount(args.trim()).map(|amount| (amount,))
+}
+
+/// Parse the kind of a greeting, such as `buongiorno` or `buonanotte`
+fn parse_greeting(args: String) -> Result<(Greeting,), ParseError> {
+    greeting_kind(args.trim()).map(|greeting| (greeting,))
+}
+
+/// Parse the optional kind of a greeting
+fn parse_optional_greeting(args: String) -> Result<(Option<Greeting>,), ParseError> {
+    let args = args.trim();
+    if args.is_empty() {
+        return Ok((None,));
+    }
+    greeting_kind(args).map(|greeting| (Some(greeting),))
+}
+
+/// Parse the arguments of the caption command: the kind of greeting followed by the optional caption template
+fn parse_caption(args: String) -> Result<(Greeting, Option<String>), ParseError> {
+    let args = args.trim();
+    let (greeting, template) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let template = template.trim();
+    Ok((
+        greeting_kind(greeting)?,
+        (!template.is_empty()).then(|| template.to_string()),
+    ))
+}
+
+/// Parse the arguments of the watermark command: the text of the watermark, `logo` followed by the url of the logo,
+/// `posizione` followed by the position or `opacità` followed by the opacity in percent
+fn parse_watermark(args: String) -> Result<(Option<WatermarkSetting>,), ParseError> {
+    let args = args.trim();
+    if args.is_empty() {
+        return Ok((None,));
+    }
+    let (option, value) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let value = value.trim();
+    let setting = match option.to_lowercase().as_str() {
+        "logo" if value.is_empty() => return Err(too_few_arguments(2, 1, "logo url is required")),
+        "logo" => Url::parse(value)
+            .map(|url| WatermarkSetting::Mark(Mark::Logo(url)))
+            .map_err(|_| ParseError::IncorrectFormat(format!("invalid logo url {value}").into()))?,
+        "posizione" | "position" => Position::parse(value)
+            .map(WatermarkSetting::Position)
+            .ok_or_else(|| {
+                ParseError::IncorrectFormat(format!("unknown position {value}").into())
+            })?,
+        "opacità" | "opacita" | "opacity" => match value.trim_end_matches('%').parse::<u8>() {
+            Ok(opacity) if opacity <= 100 => WatermarkSetting::Opacity(opacity),
+            _ => {
+                return Err(ParseError::IncorrectFormat(
+                    format!("invalid opacity {value}").into(),
+                ));
+            }
+        },
+        _ => {
+            let text = match split_quoted(args) {
+                Some((text, "")) => text,
+                _ => args,
+            };
+            if text.chars().count() > MAX_WATERMARK_LENGTH {
+                return Err(ParseError::IncorrectFormat(
+                    format!("watermark text is longer than {MAX_WATERMARK_LENGTH} characters")
+                        .into(),
+                ));
+            }
+            WatermarkSetting::Mark(Mark::Text(text.to_string()))
+        }
+    };
+
+    Ok((Some(setting),))
+}
+
+/// Parse a date in any supported format
+fn parse_date(args: String) -> Result<(PartialDate,), ParseError> {
+    let args = args.trim();
+    if args.is_empty() {
+        return Err(too_few_arguments(1, 0, "date is required"));
+    }
+    date_utils::parse_date(args)
+        .map(|date| (date,))
+        .ok_or_else(|| invalid_date(args))
+}
+
+/// Parse the arguments of the birthday command: the name followed by the date in any supported format.
+///
+/// The name may be made of several words, while the date is made of the last words of the text.
+/// The name may be omitted when the command replies to the message of the user celebrating the birthday
+fn parse_birthday(args: String) -> Result<(Option<String>, PartialDate), ParseError> {
+    let args = args.trim();
+    if let Some(date) = date_utils::parse_date(args) {
+        return Ok((None, date));
+    }
+    if let Some((name, date)) = split_quoted(args) {
+        if name.is_empty() || date.is_empty() {
+            return Err(too_few_arguments(2, 1, "name and date are required"));
+        }
+        let date = date_utils::parse_date(date).ok_or_else(|| invalid_date(date))?;
+        return Ok((Some(name.to_string()), date));
+    }
+
+    let words: Vec<&str> = args.split_whitespace().collect();
+    if words.len() < 2 {
+        return Err(too_few_arguments(
+            2,
+            words.len(),
+            "name and date are required",
+        ));
+    }
+    // prefer the longest date, so `12 marzo 1950` is not parsed as a name ending with `12 marzo`
+    (1..=MAX_DATE_WORDS.min(words.len() - 1))
+        .rev()
+        .find_map(|date_words| {
+            let (name, date) = words.split_at(words.len() - date_words);
+            date_utils::parse_date(&date.join(" ")).map(|date| (Some(name.join(" ")), date))
+        })
+        .ok_or_else(|| invalid_date(args))
+}
+
+/// Split the quoted text at the beginning of `text` from the rest of it
+fn split_quoted(text: &str) -> Option<(&str, &str)> {
+    QUOTES.iter().find_map(|(open, close)| {
+        let (quoted, rest) = text.strip_prefix(*open)?.split_once(*close)?;
+        Some((quoted.trim(), rest.trim()))
+    })
+}
+
+/// Parse an amount of images; amounts exceeding the size of an album are reduced to the maximum size
+fn amount(text: &str) -> Result<Option<usize>, ParseError> {
+    if text.is_empty() {
+        return Ok(None);
+    }
+    match text.parse::<usize>() {
+        Ok(0) | Err(_) => Err(ParseError::IncorrectFormat(
+            format!("invalid amount {text}").into(),
+        )),
+        Ok(amount) => Ok(Some(amount.min(MAX_ALBUM_SIZE))),
+    }
+}
+
+fn greeting_kind(text: &str) -> Result<Greeting, ParseError> {
+    if text.is_empty() {
+        return Err(too_few_arguments(1, 0, "greeting is required"));
+    }
+    greeting::parse_kind(text)
+        .ok_or_else(|| ParseError::IncorrectFormat(format!("unknown greeting {text}").into()))
+}
+
+fn too_few_arguments(expected: usize, found: usize, message: &str) -> ParseError {
+    ParseError::TooFewArguments {
+        expected,
+        found,
+        message: message.to_string(),
+    }
+}
+
+fn too_many_arguments(expected: usize, message: &str) -> ParseError {
+    ParseError::TooManyArguments {
+        expected,
+        found: expected + 1,
+        message: message.to_string(),
+    }
+}
+
+fn invalid_date(text: &str) -> ParseError {
+    ParseError::IncorrectFormat(format!("invalid date in {text}").into())
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn date(day: u32, month: u32, year: Option<i32>) -> PartialDate {
+        PartialDate::new(day, month, year).unwrap()
+    }
+
+    #[test]
+    fn should_parse_name() {
+        assert_eq!(
+            parse_name("Mario".to_string()).unwrap(),
+            ("Mario".to_string(),)
+        );
+        assert_eq!(
+            parse_name(" Zia Maria ".to_string()).unwrap(),
+            ("Zia Maria".to_string(),)
+        );
+        assert_eq!(
+            parse_name("\"Zia Maria\"".to_string()).unwrap(),
+            ("Zia Maria".to_string(),)
+        );
+        assert_eq!(
+            parse_name("«Zia Maria»".to_string()).unwrap(),
+            ("Zia Maria".to_string(),)
+        );
+    }
+
+    #[test]
+    fn should_not_parse_bad_name() {
+        assert!(matches!(
+            parse_name("".to_string()),
+            Err(ParseError::TooFewArguments { .. })
+        ));
+        assert!(matches!(
+            parse_name("\"\"".to_string()),
+            Err(ParseError::TooFewArguments { .. })
+        ));
+        assert!(matches!(
+            parse_name("\"Zia Maria\" pippo".to_string()),
+            Err(ParseError::TooManyArguments { .. })
+        ));
+    }
+
+    #[test]
+    fn should_parse_birthday_command() {
+        assert_eq!(
+            parse_birthday("Mario 12/03/1950".to_string()).unwrap(),
+            (Some("Mario".to_string()), date(12, 3, Some(1950)))
+        );
+        assert_eq!(
+            parse_birthday(" Mario  12 marzo ".to_string()).unwrap(),
+            (Some("Mario".to_string()), date(12, 3, None))
+        );
+        assert_eq!(
+            parse_birthday("Mario 1950-03-12".to_string()).unwrap(),
+            (Some("Mario".to_string()), date(12, 3, Some(1950)))
+        );
+    }
+
+    #[test]
+    fn should_parse_birthday_without_name() {
+        assert_eq!(
+            parse_birthday("12 marzo".to_string()).unwrap(),
+            (None, date(12, 3, None))
+        );
+        assert_eq!(
+            parse_birthday(" 12/03/1950 ".to_string()).unwrap(),
+            (None, date(12, 3, Some(1950)))
+        );
+    }
+
+    #[test]
+    fn should_parse_birthday_with_multi_word_name() {
+        assert_eq!(
+            parse_birthday("Zia Maria 12 marzo 1950".to_string()).unwrap(),
+            (Some("Zia Maria".to_string()), date(12, 3, Some(1950)))
+        );
+        assert_eq!(
+            parse_birthday("Zia Maria 12/03".to_string()).unwrap(),
+            (Some("Zia Maria".to_string()), date(12, 3, None))
+        );
+        assert_eq!(
+            parse_birthday("Papa Giovanni 23 12 mar".to_string()).unwrap(),
+            (Some("Papa Giovanni 23".to_string()), date(12, 3, None))
+        );
+        assert_eq!(
+            parse_birthday("\"Zia Maria\" 12 marzo".to_string()).unwrap(),
+            (Some("Zia Maria".to_string()), date(12, 3, None))
+        );
+        assert_eq!(
+            parse_birthday("“Zia Maria” 12/03/1950".to_string()).unwrap(),
+            (Some("Zia Maria".to_string()), date(12, 3, Some(1950)))
+        );
+    }
+
+    #[test]
+    fn should_parse_commands_from_text() {
+        let command = Command::parse("/compleanno Zia Maria 12 mar 1950", "bot").unwrap();
+        assert!(matches!(
+            command,
+            Command::Compleanno { name, date: birthday }
+                if name.as_deref() == Some("Zia Maria") && birthday == date(12, 3, Some(1950))
+        ));
+        let command = Command::parse("/auguri Zia Maria", "bot").unwrap();
+        assert!(matches!(command, Command::Auguri { name } if name == "Zia Maria"));
+    }
+
+    #[test]
+    fn should_not_parse_bad_birthday_command() {
+        assert!(matches!(
+            parse_birthday("".to_string()),
+            Err(ParseError::TooFewArguments { found: 0, .. })
+        ));
+        assert!(matches!(
+            parse_birthday("Mario".to_string()),
+            Err(ParseError::TooFewArguments { found: 1, .. })
+        ));
+        assert!(matches!(
+            parse_birthday("\"Zia Maria\"".to_string()),
+            Err(ParseError::TooFewArguments { .. })
+        ));
+        assert!(matches!(
+            parse_birthday("Mario domani".to_string()),
+            Err(ParseError::IncorrectFormat(_))
+        ));
+        assert!(matches!(
+            parse_birthday("\"Zia Maria\" domani".to_string()),
+            Err(ParseError::IncorrectFormat(_))
+        ));
+    }
+
+    #[test]
+    fn should_parse_own_birthday_command() {
+        let command = Command::parse("/ilmiocompleanno 12 marzo", "bot").unwrap();
+        assert!(matches!(
+            command,
+            Command::IlMioCompleanno { date: birthday } if birthday == date(12, 3, None)
+        ));
+        assert!(matches!(
+            parse_date(" ".to_string()),
+            Err(ParseError::TooFewArguments { .. })
+        ));
+        assert!(matches!(
+            parse_date("domani".to_string()),
+            Err(ParseError::IncorrectFormat(_))
+        ));
+    }
+
+    #[test]
+    fn should_parse_language_command() {
+        assert!(matches!(
+            Command::parse("/lingua", "bot").unwrap(),
+            Command::Lingua { language: None }
+        ));
+        assert!(matches!(
+            Command::parse("/lingua en", "bot").unwrap(),
+            Command::Lingua {
+                language: Some(Language::English)
+            }
+        ));
+        assert!(matches!(
+            parse_language("klingon".to_string()),
+            Err(ParseError::IncorrectFormat(_))
+        ));
+    }
+
+    #[test]
+    fn should_parse_caption_command() {
+        assert!(matches!(
+            Command::parse("/didascalia buongiorno Buon {giorno} {nome}!", "bot").unwrap(),
+            Command::Didascalia {
+                greeting: Greeting::BuonGiorno,
+                template: Some(template)
+            } if template == "Buon {giorno} {nome}!"
+        ));
+        assert!(matches!(
+            Command::parse("/didascalia buonanotte", "bot").unwrap(),
+            Command::Didascalia {
+                greeting: Greeting::BuonaNotte,
+                template: None
+            }
+        ));
+        assert!(matches!(
+            Command::parse("/cancelladidascalia compleanno", "bot").unwrap(),
+            Command::CancellaDidascalia {
+                greeting: Greeting::Compleanno
+            }
+        ));
+        assert!(matches!(
+            parse_caption("".to_string()),
+            Err(ParseError::TooFewArguments { .. })
+        ));
+        assert!(matches!(
+            parse_caption("asdrubale ciao".to_string()),
+            Err(ParseError::IncorrectFormat(_))
+        ));
+    }
+
+    #[test]
+    fn should_parse_submission_command() {
+        assert!(matches!(
+            Command::parse("/proponi buonanotte", "bot").unwrap(),
+            Command::Proponi {
+                greeting: Greeting::BuonaNotte
+            }
+        ));
+        assert_eq!(
+            Command::usage_hint("/proponi", "bot"),
+            Some(Key::UsageProponi)
+        );
+    }
+
+    #[test]
+    fn should_parse_generate_command() {
+        assert!(matches!(
+            Command::parse("/genera", "bot").unwrap(),
+            Command::Genera { greeting: None }
+        ));
+        assert!(matches!(
+            Command::parse("/genera buonanotte", "bot").unwrap(),
+            Command::Genera {
+                greeting: Some(Greeting::BuonaNotte)
+            }
+        ));
+        assert_eq!(
+            Command::usage_hint("/genera asdrubale", "bot"),
+            Some(Key::UsageGenera)
+        );
+    }
+
+    #[test]
+    fn should_parse_watermark_command() {
+        assert!(matches!(
+            Command::parse("/filigrana", "bot").unwrap(),
+            Command::Filigrana { setting: None }
+        ));
+        assert!(matches!(
+            Command::parse("/filigrana Caffè Club", "bot").unwrap(),
+            Command::Filigrana {
+                setting: Some(WatermarkSetting::Mark(Mark::Text(text)))
+            } if text == "Caffè Club"
+        ));
+        assert!(matches!(
+            Command::parse("/filigrana «Logo del bar»", "bot").unwrap(),
+            Command::Filigrana {
+                setting: Some(WatermarkSetting::Mark(Mark::Text(text)))
+            } if text == "Logo del bar"
+        ));
+        assert!(matches!(
+            Command::parse("/filigrana logo https://example.com/logo.png", "bot").unwrap(),
+            Command::Filigrana {
+                setting: Some(WatermarkSetting::Mark(Mark::Logo(url)))
+            } if url.as_str() == "https://example.com/logo.png"
+        ));
+        assert!(matches!(
+            Command::parse("/filigrana posizione alto sinistra", "bot").unwrap(),
+            Command::Filigrana {
+                setting: Some(WatermarkSetting::Position(Position::TopLeft))
+            }
+        ));
+        assert!(matches!(
+            Command::parse("/filigrana opacità 50%", "bot").unwrap(),
+            Command::Filigrana {
+                setting: Some(WatermarkSetting::Opacity(50))
+            }
+        ));
+        assert!(matches!(
+            Command::parse("/cancellafiligrana", "bot").unwrap(),
+            Command::CancellaFiligrana
+        ));
+    }
+
+    #[test]
+    fn should_not_parse_bad_watermark_command() {
+        assert!(matches!(
+            parse_watermark("logo".to_string()),
+            Err(ParseError::TooFewArguments { .. })
+        ));
+        assert!(matches!(
+            parse_watermark("logo caffè".to_string()),
+            Err(ParseError::IncorrectFormat(_))
+        ));
+        assert!(matches!(
+            parse_watermark("posizione sotto".to_string()),
+            Err(ParseError::IncorrectFormat(_))
+        ));
+        assert!(matches!(
+            parse_watermark("opacità 101".to_string()),
+            Err(ParseError::IncorrectFormat(_))
+        ));
+        assert!(matches!(
+            parse_watermark("a".repeat(MAX_WATERMARK_LENGTH + 1)),
+            Err(ParseError::IncorrectFormat(_))
+        ));
+        assert_eq!(
+            Command::usage_hint("/filigrana opacità tanta", "bot"),
+            Some(Key::UsageFiligrana)
+        );
+    }
+
+    #[test]
+    fn should_parse_amount_of_images() {
+        assert!(matches!(
+            Command::parse("/buongiornissimo", "bot").unwrap(),
+            Command::Buongiornissimo { amount: 1 }
+        ));
+        assert!(matches!(
+            Command::parse("/buonanotte 5", "bot").unwrap(),
+            Command::Buonanotte { amount: 5 }
+        ));
+        assert!(matches!(
+            Command::parse("/buonpranzo 100", "bot").unwrap(),
+            Command::Buonpranzo {
+                amount: MAX_ALBUM_SIZE
+            }
+        ));
+        assert!(matches!(
+            Command::parse("/album", "bot").unwrap(),
+            Command::Album { amount: None }
+        ));
+        assert!(matches!(
+            Command::parse("/album 3", "bot").unwrap(),
+            Command::Album { amount: Some(3) }
+        ));
+        assert!(matches!(
+            parse_amount("0".to_string()),
+            Err(ParseError::IncorrectFormat(_))
+        ));
+        assert!(matches!(
+            parse_amount("tante".to_string()),
+            Err(ParseError::IncorrectFormat(_))
+        ));
+        assert_eq!(
+            Command::usage_hint("/buongiornissimo tante", "bot"),
+            Some(Key::UsageGreeting)
+        );
+    }
+
+    #[test]
+    fn should_translate_all_command_descriptions() {
+        for command in Command::bot_commands() {
+            let name = command.command.trim_start_matches('/');
+            assert!(
+                Language::English.command_description(name).is_some(),
+                "missing english description for {name}"
+            );
+        }
+    }
+
+    #[test]
+    fn should_get_usage_hint() {
+        assert_eq!(
+            Command::usage_hint("/auguri", "bot"),
+            Some(Key::UsageAuguri)
+        );
+        assert_eq!(
+            Command::usage_hint("/compleanno@bot Mario domani", "bot"),
+            Some(Key::UsageCompleanno)
+        );
+        assert_eq!(
+            Command::usage_hint("/didascalia", "bot"),
+            Some(Key::UsageDidascalia)
+        );
+        assert_eq!(Command::usage_hint("/auguri Zia Maria", "bot"), None);
+        assert_eq!(Command::usage_hint("/asdrubale", "bot"), None);
+        assert_eq!(Command::usage_hint("/auguri@otherbot", "bot"), None);
+        assert_eq!(Command::usage_hint("ciao", "bot"), None);
+    }
+}
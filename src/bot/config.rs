@@ -2,11 +2,31 @@
 //!
 //! buongiorno-caffe-bot configuration
 
+use std::net::SocketAddr;
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 /// Application config
 pub struct Config {
+    /// Telegram user ids allowed to run admin commands
+    #[serde(default)]
+    pub admins: Vec<u64>,
     pub database_url: String,
+    /// Whether the greeting of the day and its image are decided once per day, instead of at every request
+    #[serde(default)]
+    pub daily_greeting: bool,
+    /// Address to serve the `/healthz` and `/readyz` endpoints at; disabled if unset
+    pub health_address: Option<SocketAddr>,
+    /// Address to serve the prometheus `/metrics` endpoint at; disabled if unset
+    pub metrics_address: Option<SocketAddr>,
+    /// SHA-256 digests of the known placeholder images, which are never sent
+    #[serde(default)]
+    pub placeholder_hashes: Vec<String>,
+    /// Seconds to wait for in-flight broadcasts to complete on shutdown
+    #[serde(default = "Config::default_shutdown_deadline_secs")]
+    pub shutdown_deadline_secs: u64,
     pub teloxide_token: String,
+    /// Place of the weather forecast of the `{meteo}` caption placeholder; the placeholder is empty if unset
+    pub weather_location: Option<String>,
 }
 
 impl Config {
@@ -15,4 +35,8 @@ impl Config {
         envy::from_env()
             .map_err(|e| anyhow::anyhow!("could not load config from environment: {}", e))
     }
+
+    fn default_shutdown_deadline_secs() -> u64 {
+        30
+    }
 }
@@ -0,0 +1,336 @@
+//! # Dialogue
+//!
+//! This module implements the guided dialogue to register a birthday step by step.
+//! The dialogue state is persisted in the sqlite database, so it survives bot restarts.
+//! The dialogue is carried on with the user who started it, so in groups the messages of the other members are ignored
+//! and only one dialogue at a time can be in progress in a chat
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use teloxide::dispatching::UpdateHandler;
+use teloxide::dispatching::dialogue::serializer::Json;
+use teloxide::dispatching::dialogue::{Dialogue, SqliteStorage, SqliteStorageError};
+use teloxide::prelude::*;
+use teloxide::types::User;
+use tracing::debug;
+
+use super::answer::{Answer, AnswerBuilder};
+use super::i18n::{Key, Language};
+use super::repository::Repository;
+use super::{Automatizer, Buongiornissimo, HandlerResult, keyboard};
+use crate::utils::date::{self as date_utils, PartialDate};
+
+pub type BirthdayStorage = SqliteStorage<Json>;
+pub type BirthdayDialogue = Dialogue<BirthdayState, BirthdayStorage>;
+
+/// The state of the birthday registration dialogue, with the user who started it
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub enum BirthdayState {
+    #[default]
+    Idle,
+    ReceiveName {
+        user: UserId,
+    },
+    ReceiveDate {
+        user: UserId,
+        name: String,
+        /// The user mentioned as the birthday person, to tag in the birthday greeting
+        #[serde(default)]
+        mentioned: Option<UserId>,
+    },
+    Confirm {
+        user: UserId,
+        name: String,
+        #[serde(default)]
+        mentioned: Option<UserId>,
+        date: PartialDate,
+    },
+}
+
+impl BirthdayState {
+    /// Whether the dialogue is in progress and has been started by `user`
+    fn is_started_by(&self, user: Option<&User>) -> bool {
+        let starter = match self {
+            Self::Idle => return false,
+            Self::ReceiveName { user }
+            | Self::ReceiveDate { user, .. }
+            | Self::Confirm { user, .. } => *user,
+        };
+        user.is_some_and(|user| user.id == starter)
+    }
+}
+
+/// Open the dialogue storage on the database at `database_url`
+pub async fn open_storage(database_url: &str) -> anyhow::Result<Arc<BirthdayStorage>> {
+    // teloxide wants a path, while sqlx accepts an url too
+    let path = database_url
+        .trim_start_matches("sqlite://")
+        .trim_start_matches("sqlite:");
+    SqliteStorage::open(path, Json)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to open dialogue storage: {}", e))
+}
+
+/// Build the handler for the messages sent while the dialogue is in progress
+pub fn message_handler() -> UpdateHandler<anyhow::Error> {
+    dptree::filter(|state: BirthdayState, message: Message| {
+        state.is_started_by(message.from.as_ref())
+    })
+    .branch(dptree::case![BirthdayState::ReceiveName { user }].endpoint(receive_name))
+    .branch(
+        dptree::case![BirthdayState::ReceiveDate {
+            user,
+            name,
+            mentioned
+        }]
+        .endpoint(receive_date),
+    )
+    .branch(
+        dptree::case![BirthdayState::Confirm {
+            user,
+            name,
+            mentioned,
+            date
+        }]
+        .endpoint(confirm_reminder),
+    )
+}
+
+/// Start the dialogue with `user` asking for the name, unless another user's dialogue is in progress
+pub async fn start(
+    dialogue: &BirthdayDialogue,
+    user: Option<&User>,
+    lang: Language,
+) -> anyhow::Result<Answer> {
+    let Some(user) = user else {
+        return Ok(Answer::simple_text(lang.text(Key::UnknownUser)));
+    };
+    let state = dialogue.get().await?.unwrap_or_default();
+    if !matches!(state, BirthdayState::Idle) && !state.is_started_by(Some(user)) {
+        debug!(
+            "ignoring dialogue of {} while another one is in progress",
+            user.id
+        );
+        return Ok(Answer::simple_text(lang.text(Key::DialogueInProgress)));
+    }
+    dialogue
+        .update(BirthdayState::ReceiveName { user: user.id })
+        .await?;
+    Ok(Answer::simple_text(lang.text(Key::AskName)))
+}
+
+/// Abort the dialogue, if started by `user`
+pub async fn cancel(
+    dialogue: &BirthdayDialogue,
+    user: Option<&User>,
+    lang: Language,
+) -> anyhow::Result<Answer> {
+    if !dialogue
+        .get()
+        .await?
+        .is_some_and(|state| state.is_started_by(user))
+    {
+        debug!("ignoring cancellation of a dialogue not started by the user");
+        return Ok(Answer::simple_text(lang.text(Key::NothingToConfirm)));
+    }
+    reset(dialogue).await?;
+    Ok(Answer::simple_text(lang.text(Key::DialogueCancelled)))
+}
+
+/// Forget the dialogue in `chat`, if any
+pub async fn forget(storage: Arc<BirthdayStorage>, chat: ChatId) -> anyhow::Result<()> {
+    reset(&BirthdayDialogue::new(storage, chat)).await
+}
+
+/// Save the birthday, if the dialogue started by `user` is waiting for a confirmation
+pub async fn confirm(
+    dialogue: &BirthdayDialogue,
+    automatizer: &Automatizer,
+    user: &User,
+    lang: Language,
+) -> anyhow::Result<Answer> {
+    let Some(BirthdayState::Confirm {
+        user: starter,
+        name,
+        mentioned,
+        date,
+    }) = dialogue.get().await?
+    else {
+        debug!("ignoring confirmation outside of the birthday dialogue");
+        return Ok(Answer::simple_text(lang.text(Key::NothingToConfirm)));
+    };
+    if starter != user.id {
+        debug!(
+            "ignoring confirmation of {} for the dialogue started by {}",
+            user.id, starter
+        );
+        return Ok(Answer::simple_text(lang.text(Key::NothingToConfirm)));
+    }
+    dialogue.exit().await?;
+    Ok(Buongiornissimo::subscribe_birthday(
+        automatizer,
+        &dialogue.chat_id(),
+        name,
+        date,
+        mentioned,
+        lang,
+    )
+    .await)
+}
+
+async fn receive_name(
+    bot: Bot,
+    dialogue: BirthdayDialogue,
+    user: UserId,
+    message: Message,
+    repository: Repository,
+) -> HandlerResult {
+    let lang = Buongiornissimo::language(&repository, message.chat.id, message.from.as_ref()).await;
+    let answer = match message
+        .text()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        Some(name) => {
+            // the birthday person can be mentioned, to be tagged in the birthday greeting
+            let typed_name = (!name.starts_with('@')).then(|| name.to_string());
+            let (name, mentioned) =
+                Buongiornissimo::birthday_target(&repository, &message, typed_name)
+                    .await
+                    .unwrap_or_else(|| (name.to_string(), None));
+            let answer = Answer::simple_text(lang.format(Key::AskDate, &[("name", &name)]));
+            dialogue
+                .update(BirthdayState::ReceiveDate {
+                    user,
+                    name,
+                    mentioned,
+                })
+                .await?;
+            answer
+        }
+        None => Answer::simple_text(lang.text(Key::AskNameAgain)),
+    };
+    answer
+        .reply_to(&message)
+        .send(&bot, message.chat.id)
+        .await?;
+    Ok(())
+}
+
+async fn receive_date(
+    bot: Bot,
+    dialogue: BirthdayDialogue,
+    (user, name, mentioned): (UserId, String, Option<UserId>),
+    message: Message,
+    repository: Repository,
+) -> HandlerResult {
+    let lang = Buongiornissimo::language(&repository, message.chat.id, message.from.as_ref()).await;
+    let answer = match message.text().and_then(date_utils::parse_date) {
+        Some(date) => ask_confirmation(&dialogue, user, (name, mentioned), date, lang).await?,
+        None => Answer::simple_text(lang.text(Key::BadDate)),
+    };
+    answer
+        .reply_to(&message)
+        .send(&bot, message.chat.id)
+        .await?;
+    Ok(())
+}
+
+/// Remind the user to press a button of the confirmation keyboard
+async fn confirm_reminder(
+    bot: Bot,
+    (_, name, _, date): (UserId, String, Option<UserId>, PartialDate),
+    message: Message,
+    repository: Repository,
+) -> HandlerResult {
+    let lang = Buongiornissimo::language(&repository, message.chat.id, message.from.as_ref()).await;
+    confirmation(&name, date, lang)
+        .reply_to(&message)
+        .send(&bot, message.chat.id)
+        .await?;
+    Ok(())
+}
+
+async fn ask_confirmation(
+    dialogue: &BirthdayDialogue,
+    user: UserId,
+    (name, mentioned): (String, Option<UserId>),
+    date: PartialDate,
+    lang: Language,
+) -> anyhow::Result<Answer> {
+    let answer = confirmation(&name, date, lang);
+    dialogue
+        .update(BirthdayState::Confirm {
+            user,
+            name,
+            mentioned,
+            date,
+        })
+        .await?;
+    Ok(answer)
+}
+
+/// Exit the dialogue; exiting a dialogue which is not in progress is not an error
+async fn reset(dialogue: &BirthdayDialogue) -> anyhow::Result<()> {
+    match dialogue.exit().await {
+        Ok(()) | Err(SqliteStorageError::DialogueNotFound) => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn confirmation(name: &str, date: PartialDate, lang: Language) -> Answer {
+    AnswerBuilder::default()
+        .text(lang.format(
+            Key::ConfirmBirthday,
+            &[("name", &name), ("date", &lang.date(date))],
+        ))
+        .keyboard(keyboard::confirm_birthday(lang))
+        .finalize()
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+    use teloxide::dispatching::dialogue::serializer::Serializer;
+
+    use super::*;
+
+    fn user(id: u64) -> User {
+        User {
+            id: UserId(id),
+            is_bot: false,
+            first_name: "Mario".to_string(),
+            last_name: None,
+            username: None,
+            language_code: None,
+            is_premium: false,
+            added_to_attachment_menu: false,
+        }
+    }
+
+    #[test]
+    fn should_tell_who_started_the_dialogue() {
+        let state = BirthdayState::ReceiveDate {
+            user: UserId(42),
+            name: "Zia Maria".to_string(),
+            mentioned: None,
+        };
+        assert!(state.is_started_by(Some(&user(42))));
+        assert!(!state.is_started_by(Some(&user(43))));
+        assert!(!state.is_started_by(None));
+        assert!(!BirthdayState::Idle.is_started_by(Some(&user(42))));
+    }
+
+    #[test]
+    fn should_read_states_saved_without_mention() {
+        let state: BirthdayState = Json
+            .deserialize(br#"{"ReceiveDate":{"user":42,"name":"Zia Maria"}}"#)
+            .unwrap();
+        let BirthdayState::ReceiveDate { mentioned, .. } = state else {
+            panic!("unexpected state {state:?}");
+        };
+        assert_eq!(mentioned, None);
+    }
+}
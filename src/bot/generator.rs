@@ -0,0 +1,367 @@
+//! # Generator
+//!
+//! This module generates greeting images locally, writing a greeting phrase on a bundled background,
+//! so that there's an image to send even when every provider is unreachable
+
+use ab_glyph::{FontRef, PxScale};
+use buongiornissimo_rs::Greeting;
+use image::{DynamicImage, ImageFormat, RgbImage, Rgba};
+use imageproc::drawing::{draw_text_mut, text_size};
+use url::Url;
+
+use super::{greeting, watermark};
+use crate::utils::random as random_utils;
+
+/// Scheme of the urls of the generated images, such as `generator:buongiorno?background=alba&text=...`
+const SCHEME: &str = "generator";
+/// The text height, as a fraction of the image height
+const TEXT_SCALE: f32 = 1.0 / 9.0;
+/// Minimum text height, in pixels
+const MIN_TEXT_SIZE: f32 = 20.0;
+/// Maximum width of a line of text, as a percentage of the image width
+const MAX_TEXT_WIDTH_PERCENT: u32 = 90;
+/// Height of a line of text, as a multiple of the text height
+const LINE_SPACING: f32 = 1.25;
+
+/// A background bundled with the bot
+struct Background {
+    name: &'static str,
+    bytes: &'static [u8],
+    /// Whether the background fits the evening greetings
+    night: bool,
+}
+
+const BACKGROUNDS: &[Background] = &[
+    Background {
+        name: "alba",
+        bytes: include_bytes!("../../assets/backgrounds/alba.png"),
+        night: false,
+    },
+    Background {
+        name: "cielo",
+        bytes: include_bytes!("../../assets/backgrounds/cielo.png"),
+        night: false,
+    },
+    Background {
+        name: "fiori",
+        bytes: include_bytes!("../../assets/backgrounds/fiori.png"),
+        night: false,
+    },
+    Background {
+        name: "tramonto",
+        bytes: include_bytes!("../../assets/backgrounds/tramonto.png"),
+        night: true,
+    },
+    Background {
+        name: "notte",
+        bytes: include_bytes!("../../assets/backgrounds/notte.png"),
+        night: true,
+    },
+];
+
+/// Greetings written on the night backgrounds
+const EVENING_GREETINGS: &[Greeting] = &[
+    Greeting::BuonaCena,
+    Greeting::BuonaSerata,
+    Greeting::BuonaNotte,
+    Greeting::Halloween,
+    Greeting::SanSilvestro,
+];
+
+/// Phrases written on the images of the greetings of the day and of the greetings without phrases
+const GOOD_MORNING_PHRASES: &[&str] = &[
+    "Buongiornissimo, kaffè?!",
+    "Buongiorno a tutti, kaffettino?!",
+    "Buongiorno, oggi kaffè doppio!",
+];
+
+/// Phrases written on the images of each greeting
+const PHRASES: &[(Greeting, &[&str])] = &[
+    (
+        Greeting::BuonPomeriggio,
+        &["Buon pomeriggio, kaffè?!", "Sereno pomeriggio a tutti"],
+    ),
+    (
+        Greeting::BuonPranzo,
+        &["Buon pranzo, e dopo kaffè!", "Buon appetito a tutti"],
+    ),
+    (
+        Greeting::BuonaCena,
+        &["Buona cena a tutti", "Buona cena, e poi ammazzakaffè!"],
+    ),
+    (
+        Greeting::BuonaSerata,
+        &["Buona serata, amici", "Serena serata a tutti"],
+    ),
+    (
+        Greeting::BuonaNotte,
+        &["Buonanotte, sogni d'oro", "Buonanotte, domani kaffè!"],
+    ),
+    (
+        Greeting::Weekend,
+        &["Buon weekend, kaffè?!", "Sereno fine settimana a tutti"],
+    ),
+    (
+        Greeting::Compleanno,
+        &[
+            "Tanti auguri di buon compleanno!",
+            "Buon compleanno, kaffè e torta?!",
+        ],
+    ),
+    (Greeting::Capodanno, &["Buon anno nuovo, kaffè?!"]),
+    (Greeting::Epifania, &["Buona Befana a tutti!"]),
+    (Greeting::SanValentino, &["Buon San Valentino a tutti"]),
+    (Greeting::FestaDelleDonne, &["Auguri a tutte le donne!"]),
+    (Greeting::FestaDelPapa, &["Auguri a tutti i papà!"]),
+    (Greeting::FestaDellaMamma, &["Auguri a tutte le mamme!"]),
+    (Greeting::Pasqua, &["Buona Pasqua a tutti!"]),
+    (Greeting::Pasquetta, &["Buona Pasquetta, grigliata?!"]),
+    (Greeting::Ferragosto, &["Buon Ferragosto, kaffè freddo?!"]),
+    (Greeting::Halloween, &["Buon Halloween a tutti!"]),
+    (Greeting::Ognissanti, &["Buon Ognissanti a tutti"]),
+    (Greeting::VigiliaDiNatale, &["Buona vigilia di Natale"]),
+    (Greeting::Natale, &["Buon Natale a tutti!"]),
+    (Greeting::SantoStefano, &["Buon Santo Stefano, avanzi?!"]),
+    (
+        Greeting::SanSilvestro,
+        &["Buon San Silvestro, kaffè e spumante?!"],
+    ),
+];
+
+/// Get the urls of all the images which can be generated for `greeting`
+pub fn compositions(greeting: Greeting) -> Vec<Url> {
+    let night = EVENING_GREETINGS.contains(&greeting);
+    BACKGROUNDS
+        .iter()
+        .filter(|background| background.night == night)
+        .flat_map(|background| {
+            phrases(greeting)
+                .iter()
+                .map(move |phrase| url(greeting, background, phrase))
+        })
+        .collect()
+}
+
+/// Get the url of a random image generated for `greeting`
+pub fn compose(greeting: Greeting) -> Url {
+    random_utils::choice(&compositions(greeting)).clone()
+}
+
+/// Generate the image at the generator `url` and encode it as JPEG
+pub async fn generate(url: &Url) -> anyhow::Result<Vec<u8>> {
+    let (background, text) = parse(url)?;
+    // rendering and encoding images is expensive, so it mustn't block the runtime
+    tokio::task::spawn_blocking(move || watermark::encode_jpeg(&render(background, &text)?)).await?
+}
+
+fn phrases(greeting: Greeting) -> &'static [&'static str] {
+    PHRASES
+        .iter()
+        .find(|(candidate, _)| *candidate == greeting)
+        .map(|(_, phrases)| *phrases)
+        .unwrap_or(GOOD_MORNING_PHRASES)
+}
+
+fn url(greeting: Greeting, background: &Background, text: &str) -> Url {
+    let mut url = Url::parse(&format!("{SCHEME}:{}", greeting::keyword(greeting)))
+        .expect("invalid generator url");
+    url.query_pairs_mut()
+        .append_pair("background", background.name)
+        .append_pair("text", text);
+    url
+}
+
+/// Get the background and the text of the image at the generator `url`
+fn parse(url: &Url) -> anyhow::Result<(&'static Background, String)> {
+    if url.scheme() != SCHEME {
+        anyhow::bail!("{url} is not a generated image");
+    }
+    let mut background = None;
+    let mut text = None;
+    for (key, value) in url.query_pairs() {
+        match &*key {
+            "background" => {
+                background = BACKGROUNDS
+                    .iter()
+                    .find(|background| background.name == value)
+            }
+            "text" => text = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+    match (background, text) {
+        (Some(background), Some(text)) => Ok((background, text)),
+        _ => anyhow::bail!("bad generated image {url}"),
+    }
+}
+
+/// Write `text` at the center of `background`; the text is wrapped and shrunk to fit the image
+fn render(background: &Background, text: &str) -> anyhow::Result<RgbImage> {
+    let mut image =
+        image::load_from_memory_with_format(background.bytes, ImageFormat::Png)?.to_rgba8();
+    let (width, height) = image.dimensions();
+    let font = watermark::font();
+    let max_width = width * MAX_TEXT_WIDTH_PERCENT / 100;
+    let scale = PxScale::from((height as f32 * TEXT_SCALE).max(MIN_TEXT_SIZE));
+    let (scale, lines) = fit(text, scale, &font, max_width);
+
+    let line_height = (scale.y * LINE_SPACING) as u32;
+    let mut y = height.saturating_sub(line_height * lines.len() as u32) / 2;
+    // the outline keeps the text readable on any background
+    let outline = (scale.y / 20.0).max(1.0) as i32;
+    for line in lines {
+        let x = (width.saturating_sub(text_size(scale, &font, &line).0) / 2) as i32;
+        for (dx, dy) in [
+            (-outline, -outline),
+            (outline, -outline),
+            (-outline, outline),
+            (outline, outline),
+        ] {
+            draw_text_mut(
+                &mut image,
+                Rgba([40, 20, 10, 255]),
+                x + dx,
+                y as i32 + dy,
+                scale,
+                &font,
+                &line,
+            );
+        }
+        draw_text_mut(
+            &mut image,
+            Rgba([255, 255, 255, 255]),
+            x,
+            y as i32,
+            scale,
+            &font,
+            &line,
+        );
+        y += line_height;
+    }
+
+    Ok(DynamicImage::ImageRgba8(image).to_rgb8())
+}
+
+/// Wrap `text` in lines no wider than `max_width`, shrinking the text from `scale` while any word is wider than a line.
+/// The text isn't shrunk below [`MIN_TEXT_SIZE`], so only a word too long to fit even at that size exceeds `max_width`
+fn fit(text: &str, scale: PxScale, font: &FontRef, max_width: u32) -> (PxScale, Vec<String>) {
+    let mut scale = scale;
+    let mut lines = wrap(text, scale, font, max_width);
+    while scale.y > MIN_TEXT_SIZE
+        && lines
+            .iter()
+            .any(|line| text_size(scale, font, line).0 > max_width)
+    {
+        scale = PxScale::from((scale.y * 0.9).max(MIN_TEXT_SIZE));
+        lines = wrap(text, scale, font, max_width);
+    }
+    (scale, lines)
+}
+
+/// Split `text` in lines no wider than `max_width`; words wider than a line get a line of their own
+fn wrap(text: &str, scale: PxScale, font: &FontRef, max_width: u32) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if text_size(scale, font, &format!("{line} {word}")).0 <= max_width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_list_compositions() {
+        let compositions = compositions(Greeting::BuonaNotte);
+        assert_eq!(compositions.len(), 4);
+        for url in compositions {
+            let (background, text) = parse(&url).unwrap();
+            assert!(background.night);
+            assert!(phrases(Greeting::BuonaNotte).contains(&text.as_str()));
+        }
+        let (background, text) = parse(&compose(Greeting::BuonGiorno)).unwrap();
+        assert!(!background.night);
+        assert!(GOOD_MORNING_PHRASES.contains(&text.as_str()));
+    }
+
+    #[test]
+    fn should_not_parse_bad_url() {
+        for url in [
+            "https://example.com/caffe.jpg",
+            "generator:buongiorno?text=Buongiornissimo",
+            "generator:buongiorno?background=deserto&text=Buongiornissimo",
+            "generator:buongiorno?background=alba",
+        ] {
+            assert!(parse(&Url::parse(url).unwrap()).is_err(), "{url}");
+        }
+    }
+
+    #[test]
+    fn should_wrap_text() {
+        let font = watermark::font();
+        let scale = PxScale::from(40.0);
+        assert_eq!(
+            wrap("Buongiornissimo, kaffè?!", scale, &font, 1000),
+            vec!["Buongiornissimo, kaffè?!".to_string()]
+        );
+        let lines = wrap("Buongiornissimo, oggi kaffè doppio!", scale, &font, 300);
+        assert!(lines.len() > 1);
+        assert_eq!(lines.join(" "), "Buongiornissimo, oggi kaffè doppio!");
+        // "Buongiornissimo," is wider than a line, so it gets a line of its own
+        assert!(text_size(scale, &font, "Buongiornissimo,").0 > 300);
+        assert!(lines.iter().all(|line| {
+            text_size(scale, &font, line).0 <= 300 || !line.contains(char::is_whitespace)
+        }));
+    }
+
+    #[test]
+    fn should_shrink_text_to_fit() {
+        let font = watermark::font();
+        let text = "Buongiornissimo, oggi kaffè doppio!";
+        let (scale, lines) = fit(text, PxScale::from(40.0), &font, 300);
+        assert!(scale.y < 40.0);
+        assert!(scale.y >= MIN_TEXT_SIZE);
+        assert_eq!(lines.join(" "), text);
+        assert!(
+            lines
+                .iter()
+                .all(|line| text_size(scale, &font, line).0 <= 300)
+        );
+        // text which fits is not shrunk
+        let (scale, lines) = fit(text, PxScale::from(40.0), &font, 1000);
+        assert_eq!(scale.y, 40.0);
+        assert_eq!(lines, vec![text.to_string()]);
+    }
+
+    #[test]
+    fn should_render_text_on_background() {
+        let background = &BACKGROUNDS[0];
+        let plain = image::load_from_memory(background.bytes).unwrap().to_rgb8();
+        let image = render(background, "Buongiornissimo, kaffè?!").unwrap();
+        assert_eq!(image.dimensions(), plain.dimensions());
+        let (width, height) = image.dimensions();
+        assert!(
+            (height / 3..height * 2 / 3)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .any(|(x, y)| image.get_pixel(x, y) != plain.get_pixel(x, y))
+        );
+        // corners are left untouched
+        assert_eq!(image.get_pixel(0, 0), plain.get_pixel(0, 0));
+    }
+
+    #[tokio::test]
+    async fn should_generate_jpeg() {
+        let jpeg = generate(&compose(Greeting::Compleanno)).await.unwrap();
+        assert_eq!(image::guess_format(&jpeg).unwrap(), ImageFormat::Jpeg);
+    }
+}
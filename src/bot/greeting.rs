@@ -0,0 +1,149 @@
+//! # Greeting
+//!
+//! This module maps text, such as inline queries, to greetings
+
+use buongiornissimo_rs::Greeting;
+use chrono::{Local, NaiveDate};
+
+use crate::utils::random as random_utils;
+
+/// Keywords associated to the greetings which can be searched
+const KEYWORDS: &[(&str, Greeting)] = &[
+    ("notte", Greeting::BuonaNotte),
+    ("pomeriggio", Greeting::BuonPomeriggio),
+    ("pranzo", Greeting::BuonPranzo),
+    ("cena", Greeting::BuonaCena),
+    ("sera", Greeting::BuonaSerata),
+    ("weekend", Greeting::Weekend),
+    ("compleanno", Greeting::Compleanno),
+    ("auguri", Greeting::Compleanno),
+    ("capodanno", Greeting::Capodanno),
+    ("epifania", Greeting::Epifania),
+    ("befana", Greeting::Epifania),
+    ("valentino", Greeting::SanValentino),
+    ("donne", Greeting::FestaDelleDonne),
+    ("papà", Greeting::FestaDelPapa),
+    ("papa", Greeting::FestaDelPapa),
+    ("mamma", Greeting::FestaDellaMamma),
+    ("pasquetta", Greeting::Pasquetta),
+    ("pasqua", Greeting::Pasqua),
+    ("ferragosto", Greeting::Ferragosto),
+    ("halloween", Greeting::Halloween),
+    ("santi", Greeting::Ognissanti),
+    ("vigilia", Greeting::VigiliaDiNatale),
+    ("natale", Greeting::Natale),
+    ("stefano", Greeting::SantoStefano),
+    ("silvestro", Greeting::SanSilvestro),
+];
+
+/// Get the kind of greeting matching `text`.
+///
+/// An empty text or a text which asks for a good morning is always [`Greeting::BuonGiorno`],
+/// which stands for the greeting of the day
+pub fn parse_kind(text: &str) -> Option<Greeting> {
+    let text = text.trim().to_lowercase();
+    if text.is_empty() || text.contains("giorn") || text.contains("caff") {
+        return Some(Greeting::BuonGiorno);
+    }
+
+    KEYWORDS
+        .iter()
+        .find(|(keyword, _)| text.contains(keyword))
+        .map(|(_, greeting)| *greeting)
+}
+
+/// Get the keyword which can be parsed back into `greeting`.
+///
+/// Good morning greetings and greetings without a keyword map to the greeting of the day
+pub fn keyword(greeting: Greeting) -> &'static str {
+    KEYWORDS
+        .iter()
+        .find(|(_, candidate)| *candidate == greeting)
+        .map(|(keyword, _)| *keyword)
+        .unwrap_or("buongiorno")
+}
+
+/// Get the greeting of the day, choosing randomly whether the good morning is based on the weekday
+pub fn of_the_day() -> Greeting {
+    of_date(today(), random_weekday())
+}
+
+/// Get the greeting of `date`; `use_weekday` tells whether the good morning is based on the weekday
+pub fn of_date(date: NaiveDate, use_weekday: bool) -> Greeting {
+    buongiornissimo_rs::greeting_of_the_day(date, use_weekday)
+}
+
+/// Choose randomly whether the good morning is based on the weekday
+pub fn random_weekday() -> bool {
+    *random_utils::choice(&[true, false])
+}
+
+/// Get today's date
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_parse_greeting() {
+        assert_eq!(parse_kind("notte"), Some(Greeting::BuonaNotte));
+        assert_eq!(parse_kind("Buonanotte"), Some(Greeting::BuonaNotte));
+        assert_eq!(parse_kind(" buona serata "), Some(Greeting::BuonaSerata));
+        assert_eq!(parse_kind("auguri"), Some(Greeting::Compleanno));
+        assert_eq!(parse_kind("buona pasquetta"), Some(Greeting::Pasquetta));
+        assert_eq!(parse_kind("pasqua"), Some(Greeting::Pasqua));
+        assert_eq!(parse_kind("asdrubale"), None);
+    }
+
+    #[test]
+    fn should_get_greeting_keyword() {
+        for greeting in [
+            Greeting::BuonaNotte,
+            Greeting::BuonPomeriggio,
+            Greeting::BuonPranzo,
+            Greeting::BuonaCena,
+            Greeting::BuonaSerata,
+            Greeting::Weekend,
+            Greeting::Compleanno,
+            Greeting::Pasqua,
+            Greeting::Pasquetta,
+            Greeting::Natale,
+        ] {
+            assert_eq!(parse_kind(keyword(greeting)), Some(greeting));
+        }
+        assert_eq!(keyword(Greeting::BuonGiorno), "buongiorno");
+    }
+
+    #[test]
+    fn should_parse_greeting_kind() {
+        assert_eq!(parse_kind("buongiorno"), Some(Greeting::BuonGiorno));
+        assert_eq!(parse_kind("caffè"), Some(Greeting::BuonGiorno));
+        assert_eq!(parse_kind("notte"), Some(Greeting::BuonaNotte));
+        assert_eq!(parse_kind("asdrubale"), None);
+    }
+
+    #[test]
+    fn should_parse_greeting_of_the_day() {
+        assert_eq!(parse_kind(""), Some(Greeting::BuonGiorno));
+        assert_eq!(parse_kind("buongiornissimo"), Some(Greeting::BuonGiorno));
+        assert_eq!(parse_kind("caffè"), Some(Greeting::BuonGiorno));
+    }
+
+    #[test]
+    fn should_get_greeting_of_date() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        assert_eq!(
+            of_date(date, true),
+            Greeting::BuonGiornoWeekday(chrono::Weekday::Mon)
+        );
+        assert_eq!(of_date(date, false), Greeting::BuonGiorno);
+        let christmas = NaiveDate::from_ymd_opt(2026, 12, 25).unwrap();
+        assert_eq!(of_date(christmas, true), Greeting::Natale);
+    }
+}
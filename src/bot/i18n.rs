@@ -0,0 +1,398 @@
+//! # I18n
+//!
+//! This module contains the message catalogues of the bot and the languages it speaks
+
+mod english;
+mod italian;
+mod neapolitan;
+mod venetian;
+
+use std::fmt;
+
+use chrono::Weekday;
+
+use crate::utils::date::{self as date_utils, PartialDate};
+
+/// A language spoken by the bot
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    Italian,
+    English,
+    Neapolitan,
+    Venetian,
+}
+
+/// The key of a message in the catalogues
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    // -- commands
+    Help,
+    Start,
+    Release,
+    Menu,
+    BirthdaysMenu,
+    SubscriptionsMenu,
+    LanguageMenu,
+    LanguageSet,
+    AdminOnly,
+    UnknownUser,
+    // -- subscriptions
+    Subscribed,
+    Unsubscribed,
+    AlreadySubscribed,
+    // -- birthdays
+    HappyBirthday,
+    BirthdaySubscribed,
+    BirthdayAlreadyExists,
+    BirthdayTargetMissing,
+    OwnBirthdaySet,
+    UserForgotten,
+    // -- captions
+    CaptionSet,
+    CaptionCurrent,
+    CaptionNotSet,
+    CaptionDeleted,
+    CaptionBadTemplate,
+    CaptionTooLong,
+    // -- albums
+    AlbumSizeSet,
+    AlbumSizeCurrent,
+    // -- watermarks
+    WatermarkSet,
+    WatermarkCurrent,
+    WatermarkNotSet,
+    WatermarkDeleted,
+    WatermarkBadLogo,
+    WatermarkMissingMark,
+    // -- submissions
+    SubmissionReceived,
+    SubmissionMissingPhoto,
+    SubmissionToReview,
+    SubmissionApproved,
+    SubmissionRejected,
+    ReviewApproved,
+    ReviewRejected,
+    ReviewAlreadyDone,
+    // -- favourites
+    FavouriteSaved,
+    FavouriteAlreadySaved,
+    FavouriteMissingMedia,
+    FavouriteNotFound,
+    FavouritesTitle,
+    FavouritesEmpty,
+    // -- votes
+    VoteUp,
+    VoteDown,
+    VoteFailed,
+    // -- birthday dialogue
+    AskName,
+    AskNameAgain,
+    AskDate,
+    BadDate,
+    ConfirmBirthday,
+    NothingToConfirm,
+    DialogueCancelled,
+    DialogueInProgress,
+    // -- usage hints
+    UsageAuguri,
+    UsageCompleanno,
+    UsageIlMioCompleanno,
+    UsageDidascalia,
+    UsageCancellaDidascalia,
+    UsageAlbum,
+    UsageFiligrana,
+    UsageGenera,
+    UsageProponi,
+    UsageGreeting,
+    UsageGeneric,
+    // -- buttons
+    ButtonGoodMorning,
+    ButtonGoodNight,
+    ButtonLunch,
+    ButtonAfternoon,
+    ButtonDinner,
+    ButtonEvening,
+    ButtonWeekend,
+    ButtonBirthdays,
+    ButtonSubscriptions,
+    ButtonSubscribe,
+    ButtonUnsubscribe,
+    ButtonNewBirthday,
+    ButtonConfirm,
+    ButtonCancel,
+    ButtonAnotherOne,
+    ButtonLanguage,
+    ButtonApprove,
+    ButtonReject,
+    ButtonSave,
+    ButtonPrevious,
+    ButtonNext,
+    // -- delivery statistics
+    StatsTitle,
+    StatsPerDay,
+    StatsNoDeliveries,
+    StatsPerProvider,
+    StatsNoProviders,
+    StatsMostActiveChats,
+    StatsNoChats,
+}
+
+/// An error to report to the user with a message of the catalogues.
+///
+/// The message is picked in the language of the chat through [`UserError::text`];
+/// the `Display` implementation only names the key, for the logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserError(pub Key);
+
+impl UserError {
+    /// Get the message for the user in `lang`
+    pub fn text(self, lang: Language) -> &'static str {
+        lang.text(self.0)
+    }
+}
+
+impl fmt::Display for UserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "user error {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UserError {}
+
+impl Language {
+    pub const ALL: [Self; 4] = [
+        Self::Italian,
+        Self::English,
+        Self::Neapolitan,
+        Self::Venetian,
+    ];
+
+    /// Get the language code (ISO 639)
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Italian => "it",
+            Self::English => "en",
+            Self::Neapolitan => "nap",
+            Self::Venetian => "vec",
+        }
+    }
+
+    /// Get the name of the language, in the language itself
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Italian => "🇮🇹 Italiano",
+            Self::English => "🇬🇧 English",
+            Self::Neapolitan => "🌋 Napulitano",
+            Self::Venetian => "🦁 Veneto",
+        }
+    }
+
+    /// Get the language from a language code, such as `it`, `en-US` or `nap`.
+    /// The name of the language is accepted too
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        let code = text.split(['-', '_']).next().unwrap_or_default();
+        Self::ALL.into_iter().find(|language| {
+            language.code() == code
+                || language
+                    .name()
+                    .split_once(' ')
+                    .is_some_and(|(_, name)| name.to_lowercase() == text)
+        })
+    }
+
+    /// Get the message for `key` in this language.
+    ///
+    /// Dialects fall back to italian for the messages they don't translate
+    pub fn text(self, key: Key) -> &'static str {
+        match self {
+            Self::Italian => italian::text(key),
+            Self::English => english::text(key),
+            Self::Neapolitan => neapolitan::text(key).unwrap_or_else(|| italian::text(key)),
+            Self::Venetian => venetian::text(key).unwrap_or_else(|| italian::text(key)),
+        }
+    }
+
+    /// Get the message for `key`, replacing the `{name}` placeholders with the values in `args`
+    pub fn format(self, key: Key, args: &[(&str, &dyn fmt::Display)]) -> String {
+        args.iter()
+            .fold(self.text(key).to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{name}}}"), &value.to_string())
+            })
+    }
+
+    /// Get the description of the bot command `command` (without the leading slash), if translated
+    pub fn command_description(self, command: &str) -> Option<&'static str> {
+        match self {
+            Self::English => english::command_description(command),
+            Self::Italian | Self::Neapolitan | Self::Venetian => None,
+        }
+    }
+
+    /// Format `date` in this language
+    pub fn date(self, date: PartialDate) -> String {
+        let months = match self {
+            Self::English => &date_utils::ENGLISH_MONTHS,
+            Self::Italian | Self::Neapolitan | Self::Venetian => &date_utils::MONTHS,
+        };
+        let month = months[date.month as usize - 1];
+        match (self, date.year) {
+            (Self::English, Some(year)) => format!("{month} {}, {year}", date.day),
+            (Self::English, None) => format!("{month} {}", date.day),
+            (_, Some(year)) => format!("{} {month} {year}", date.day),
+            (_, None) => format!("{} {month}", date.day),
+        }
+    }
+
+    /// Get the name of `weekday` in this language
+    pub fn weekday(self, weekday: Weekday) -> &'static str {
+        let weekdays = match self {
+            Self::English => &english::WEEKDAYS,
+            Self::Italian | Self::Neapolitan | Self::Venetian => &date_utils::WEEKDAYS,
+        };
+        weekdays[weekday.num_days_from_monday() as usize]
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_parse_language() {
+        assert_eq!(Language::parse("it"), Some(Language::Italian));
+        assert_eq!(Language::parse("en-US"), Some(Language::English));
+        assert_eq!(Language::parse("EN"), Some(Language::English));
+        assert_eq!(Language::parse("nap"), Some(Language::Neapolitan));
+        assert_eq!(Language::parse("vec"), Some(Language::Venetian));
+        assert_eq!(Language::parse("italiano"), Some(Language::Italian));
+        assert_eq!(Language::parse("Napulitano"), Some(Language::Neapolitan));
+        assert_eq!(Language::parse("de"), None);
+        assert_eq!(Language::parse(""), None);
+        for language in Language::ALL {
+            assert_eq!(Language::parse(language.code()), Some(language));
+        }
+    }
+
+    #[test]
+    fn should_format_message() {
+        assert_eq!(
+            Language::Italian.format(Key::HappyBirthday, &[("name", &"Mario")]),
+            "Buon compleanno Mario!"
+        );
+        assert_eq!(
+            Language::English.format(Key::HappyBirthday, &[("name", &"Mario")]),
+            "Happy birthday Mario!"
+        );
+    }
+
+    #[test]
+    fn should_fall_back_to_italian() {
+        assert_eq!(
+            Language::Neapolitan.text(Key::StatsTitle),
+            Language::Italian.text(Key::StatsTitle)
+        );
+        assert_ne!(
+            Language::Neapolitan.text(Key::Menu),
+            Language::Italian.text(Key::Menu)
+        );
+    }
+
+    #[test]
+    fn should_keep_placeholders_in_translations() {
+        for key in [
+            Key::Release,
+            Key::HappyBirthday,
+            Key::BirthdaySubscribed,
+            Key::OwnBirthdaySet,
+            Key::AskDate,
+            Key::ConfirmBirthday,
+            Key::LanguageSet,
+            Key::CaptionSet,
+            Key::CaptionCurrent,
+            Key::CaptionNotSet,
+            Key::CaptionDeleted,
+            Key::CaptionBadTemplate,
+            Key::CaptionTooLong,
+            Key::AlbumSizeSet,
+            Key::AlbumSizeCurrent,
+            Key::WatermarkSet,
+            Key::WatermarkCurrent,
+            Key::SubmissionReceived,
+            Key::SubmissionToReview,
+            Key::SubmissionApproved,
+            Key::SubmissionRejected,
+            Key::FavouriteSaved,
+            Key::FavouritesTitle,
+        ] {
+            let italian = Language::Italian.text(key);
+            for language in Language::ALL {
+                let text = language.text(key);
+                for placeholder in [
+                    "{name}",
+                    "{date}",
+                    "{version}",
+                    "{language}",
+                    "{greeting}",
+                    "{placeholders}",
+                    "{max}",
+                    "{amount}",
+                    "{mark}",
+                    "{position}",
+                    "{opacity}",
+                ] {
+                    assert_eq!(
+                        text.contains(placeholder),
+                        italian.contains(placeholder),
+                        "{placeholder} in {key:?} ({language:?})"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn should_format_date() {
+        let date = PartialDate::new(12, 3, Some(1950)).unwrap();
+        assert_eq!(Language::Italian.date(date), "12 marzo 1950");
+        assert_eq!(Language::English.date(date), "March 12, 1950");
+        let date = PartialDate::new(1, 12, None).unwrap();
+        assert_eq!(Language::Venetian.date(date), "1 dicembre");
+        assert_eq!(Language::English.date(date), "December 1");
+    }
+
+    #[test]
+    fn should_get_weekday() {
+        assert_eq!(Language::Italian.weekday(Weekday::Mon), "lunedì");
+        assert_eq!(Language::Neapolitan.weekday(Weekday::Sun), "domenica");
+        assert_eq!(Language::English.weekday(Weekday::Fri), "Friday");
+    }
+
+    #[test]
+    fn should_translate_user_error() {
+        let err = UserError(Key::AlreadySubscribed);
+        assert_eq!(
+            err.text(Language::English),
+            Language::English.text(Key::AlreadySubscribed)
+        );
+        assert_eq!(
+            err.text(Language::Italian),
+            Language::Italian.text(Key::AlreadySubscribed)
+        );
+        assert_eq!(err.to_string(), "user error AlreadySubscribed");
+    }
+
+    #[test]
+    fn should_translate_command_descriptions() {
+        assert!(
+            Language::English
+                .command_description("buongiornissimo")
+                .is_some()
+        );
+        assert!(Language::Italian.command_description("auguri").is_none());
+    }
+}
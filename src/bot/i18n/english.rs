@@ -0,0 +1,252 @@
+//! # English
+//!
+//! The english message catalogue
+
+use super::Key;
+
+pub const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Descriptions of the bot commands
+const COMMAND_DESCRIPTIONS: &[(&str, &str)] = &[
+    (
+        "album",
+        "choose how many images to receive in the automatic messages (e.g. /album 3)",
+    ),
+    ("annulla", "abort the ongoing operation"),
+    ("auguri", "wish someone a happy birthday"),
+    (
+        "buongiornissimo",
+        "get a good morning image (e.g. /buongiornissimo 5 for an album)",
+    ),
+    ("buonpomeriggio", "get a good afternoon image"),
+    ("buonanotte", "get a good night image"),
+    ("buonpranzo", "get a good lunch image"),
+    ("buonacena", "get a good dinner image"),
+    ("buonaserata", "get a good evening image"),
+    ("buonweekend", "get a good weekend image"),
+    ("caffeee", "subscribe to the automatic messages"),
+    ("cancelladidascalia", "remove the caption of a greeting"),
+    ("cancellafiligrana", "remove the watermark from the images"),
+    (
+        "compleanno",
+        "set a birthday (e.g. /compleanno Aunt Mary 12/03/1950 or /compleanno Mario 12 March)",
+    ),
+    (
+        "didascalia",
+        "set the caption of a greeting (e.g. /didascalia buongiorno Happy {day} {name}!)",
+    ),
+    ("dimenticami", "delete all your data from the bot"),
+    (
+        "filigrana",
+        "stamp a text or a logo on the images (e.g. /filigrana Coffee Club)",
+    ),
+    (
+        "genera",
+        "generate a greeting image, without searching online (e.g. /genera buonanotte)",
+    ),
+    (
+        "ilmiocompleanno",
+        "register your own birthday in this group (e.g. /ilmiocompleanno 12 March)",
+    ),
+    ("lingua", "choose the language of the bot"),
+    ("menu", "show the menu"),
+    ("nuovocompleanno", "register a birthday step by step"),
+    (
+        "preferiti",
+        "browse your favourite images and send one in the chat",
+    ),
+    (
+        "proponi",
+        "propose your own image, with /proponi and the greeting in the caption of a photo",
+    ),
+    ("puliziakontatti", "unsubscribe from the automatic messages"),
+    ("release", "get the current release"),
+    ("statistiche", "show the delivery statistics (admins only)"),
+    ("help", "show the help"),
+    ("start", "start the bot"),
+];
+
+pub fn command_description(command: &str) -> Option<&'static str> {
+    COMMAND_DESCRIPTIONS
+        .iter()
+        .find(|(name, _)| *name == command)
+        .map(|(_, description)| *description)
+}
+
+pub fn text(key: Key) -> &'static str {
+    match key {
+        Key::Help => "These commands are available:",
+        Key::Start => {
+            "COFFEE!? ☕ Step right into the world of buongiornissimi with /caffeee, or if you want a sweet taste of my contents send /buongiornissimo, otherwise send /help to see all the available commands"
+        }
+        Key::Release => {
+            "buongiorno-caffe-bot ☕ {version}. Developed by @veeso97. Contribute to the project on Github https://github.com/veeso/buongiorno-caffe-bot. Support my project on Ko-Fi https://ko-fi.com/veeso"
+        }
+        Key::Menu => "Good morning, COFFEEE?! ☕ What would you like?",
+        Key::BirthdaysMenu => {
+            "🎂 To wish someone a happy birthday send /auguri <name>. To remember a birthday press the button below or send /nuovocompleanno: the bot will send the wishes on that day!"
+        }
+        Key::SubscriptionsMenu => "🔔 Do you want to receive the best greeting images every day?",
+        Key::LanguageMenu => "🌍 Which language should I speak?",
+        Key::LanguageSet => "From now on I'll speak English ☕",
+        Key::AdminOnly => "This command is reserved to administrators ☕",
+        Key::UnknownUser => "I don't know who you are 🤔",
+        Key::Subscribed => {
+            "Good morning, COFFEEE?! ☕☕☕  From now on you will receive the best greeting images every day."
+        }
+        Key::Unsubscribed => "you have unsubscribed from the automatic messages ☕",
+        Key::AlreadySubscribed => "You are already subscribed to the automatic messages.",
+        Key::HappyBirthday => "Happy birthday {name}!",
+        Key::BirthdaySubscribed => {
+            "Good morning, COFFEEE?! ☕☕☕  From now on {name} will receive the wishes on their birthday."
+        }
+        Key::BirthdayAlreadyExists => "This birthday is already registered",
+        Key::BirthdayTargetMissing => {
+            "Reply to a message of the birthday person or write their name ☕"
+        }
+        Key::OwnBirthdaySet => "🎂 Got it! On {date} you will receive the wishes in this chat ☕",
+        Key::UserForgotten => {
+            "🧹 I have deleted your birthdays from every group and the data of our private chat. Goodbye ☕"
+        }
+        Key::CaptionSet => "📝 From now on the {greeting} images will have this caption:",
+        Key::CaptionCurrent => "📝 The caption of the {greeting} images is:",
+        Key::CaptionNotSet => "The {greeting} images have no caption ☕",
+        Key::CaptionDeleted => "🧹 I have removed the caption of the {greeting} images ☕",
+        Key::CaptionBadTemplate => {
+            "I didn't understand the caption 🤔 You can use the placeholders {placeholders}; to write a brace, double it ({{ or }})"
+        }
+        Key::CaptionTooLong => "The caption is too long: {max} characters at most ☕",
+        Key::AlbumSizeSet => {
+            "📸 From now on you will receive {amount} images for every automatic greeting ☕"
+        }
+        Key::AlbumSizeCurrent => {
+            "📸 You receive {amount} images for every automatic greeting. To change it send /album <number>, up to {max}"
+        }
+        Key::WatermarkSet => {
+            "🖋️ From now on the images will have the watermark {mark} at the {position}, with {opacity}% opacity ☕"
+        }
+        Key::WatermarkCurrent => {
+            "🖋️ The images have the watermark {mark} at the {position}, with {opacity}% opacity"
+        }
+        Key::WatermarkNotSet => "The images have no watermark ☕",
+        Key::WatermarkDeleted => "🧹 I have removed the watermark from the images ☕",
+        Key::WatermarkBadLogo => {
+            "I can't use this logo 🤔 It must be a PNG image reachable at the link"
+        }
+        Key::WatermarkMissingMark => {
+            "Done ☕ Now choose the text of the watermark with /filigrana <text> or a logo with /filigrana logo <link>"
+        }
+        Key::SubmissionReceived => {
+            "📬 Thank you! The administrators will review your {greeting} image ☕"
+        }
+        Key::SubmissionMissingPhoto => {
+            "Send a photo with /proponi <greeting> in the caption, or reply to a photo with /proponi <greeting> ☕"
+        }
+        Key::SubmissionToReview => "📬 {name} proposes this {greeting} image",
+        Key::SubmissionApproved => {
+            "🎉 Your {greeting} image has been approved: from now on it will be sent along with the others ☕"
+        }
+        Key::SubmissionRejected => {
+            "Your {greeting} image has not been approved, maybe next time ☕"
+        }
+        Key::ReviewApproved => "✅ Image approved",
+        Key::ReviewRejected => "❌ Image rejected",
+        Key::ReviewAlreadyDone => "This image has already been reviewed ☕",
+        Key::FavouriteSaved => {
+            "❤️ Image saved among the favourites of {name}, find it with /preferiti"
+        }
+        Key::FavouriteAlreadySaved => "{name}, this image is already among your favourites ☕",
+        Key::FavouriteMissingMedia => "I can't find the image to save anymore 🤔",
+        Key::FavouriteNotFound => "This image is not among your favourites 🤔",
+        Key::FavouritesTitle => {
+            "❤️ The favourites of {name} (page {page} of {pages}): choose one to send it here"
+        }
+        Key::FavouritesEmpty => {
+            "You have no favourites yet: press ❤️ Save under an image to save it ☕"
+        }
+        Key::VoteUp => "👍 Thanks for your vote, you'll see more like this one ☕",
+        Key::VoteDown => "👎 Got it, you'll see fewer like this one",
+        Key::VoteFailed => "😓 I couldn't save your vote, please try again later",
+        Key::AskName => "🎂 What's the name of the birthday person? (send /annulla to abort)",
+        Key::AskNameAgain => "Please, write me the name of the birthday person ☕",
+        Key::AskDate => {
+            "When was {name} born? Write the date like 12/03/1950 or 12 March (the year is optional)"
+        }
+        Key::BadDate => {
+            "I didn't understand the date 🤔 Write it like 12/03/1950 or 12 March (the year is optional)"
+        }
+        Key::ConfirmBirthday => "Do you confirm the birthday of {name} on {date}?",
+        Key::NothingToConfirm => "There is no birthday to confirm ☕",
+        Key::DialogueCancelled => "Ok, no birthday ☕",
+        Key::DialogueInProgress => {
+            "Someone else is already registering a birthday, wait until they're done ☕"
+        }
+        Key::UsageAuguri => "Usage: /auguri <name>, for example /auguri Aunt Mary",
+        Key::UsageCompleanno => {
+            "Usage: /compleanno <name> <date>, for example /compleanno Aunt Mary 12/03/1950 or /compleanno \"Aunt Mary\" 12/03. You can also reply to a message of the birthday person with /compleanno <date>"
+        }
+        Key::UsageIlMioCompleanno => {
+            "Usage: /ilmiocompleanno <date>, for example /ilmiocompleanno 12/03 or /ilmiocompleanno 12/03/1950"
+        }
+        Key::UsageDidascalia => {
+            "Usage: /didascalia <greeting> [text], for example /didascalia buongiorno Happy {day} {name}! ☕. You can use the placeholders {name}, {day}, {date}, {saint} and {weather}. Without text I'll show you the current caption"
+        }
+        Key::UsageCancellaDidascalia => {
+            "Usage: /cancelladidascalia <greeting>, for example /cancelladidascalia buonanotte"
+        }
+        Key::UsageAlbum => {
+            "Usage: /album [number], for example /album 3 to receive 3 images for every automatic greeting"
+        }
+        Key::UsageFiligrana => {
+            "Usage: /filigrana <text> or /filigrana logo <PNG link>, for example /filigrana Coffee Club. You can also choose /filigrana posizione top-left|top-right|bottom-left|bottom-right|center and /filigrana opacità <0-100>. Without arguments I'll show you the current watermark"
+        }
+        Key::UsageGenera => {
+            "Usage: /genera [greeting], for example /genera buonanotte. Without greeting I'll generate the image of the greeting of the day"
+        }
+        Key::UsageProponi => {
+            "Usage: send a photo with /proponi <greeting> in the caption, for example /proponi buonanotte, or reply to a photo with /proponi <greeting>"
+        }
+        Key::UsageGreeting => {
+            "Usage: /buongiornissimo [number], for example /buongiornissimo 5 to receive an album of 5 images"
+        }
+        Key::UsageGeneric => {
+            "I didn't understand the command 🤔 Send /help to see all the available commands"
+        }
+        Key::ButtonGoodMorning => "☀️ Good morning",
+        Key::ButtonGoodNight => "🌙 Good night",
+        Key::ButtonLunch => "🍝 Good lunch",
+        Key::ButtonAfternoon => "☕ Good afternoon",
+        Key::ButtonDinner => "🍷 Good dinner",
+        Key::ButtonEvening => "🌆 Good evening",
+        Key::ButtonWeekend => "🎉 Good weekend",
+        Key::ButtonBirthdays => "🎂 Birthdays",
+        Key::ButtonSubscriptions => "🔔 Subscriptions",
+        Key::ButtonSubscribe => "☕ Subscribe",
+        Key::ButtonUnsubscribe => "🧹 Unsubscribe",
+        Key::ButtonNewBirthday => "➕ New birthday",
+        Key::ButtonConfirm => "✅ Confirm",
+        Key::ButtonCancel => "❌ Cancel",
+        Key::ButtonAnotherOne => "🔄 Another one!",
+        Key::ButtonLanguage => "🌍 Language",
+        Key::ButtonApprove => "✅ Approve",
+        Key::ButtonReject => "❌ Reject",
+        Key::ButtonSave => "❤️ Save",
+        Key::ButtonPrevious => "◀️ Previous",
+        Key::ButtonNext => "Next ▶️",
+        Key::StatsTitle => "📊 Delivery statistics",
+        Key::StatsPerDay => "Deliveries per day:",
+        Key::StatsNoDeliveries => "no deliveries",
+        Key::StatsPerProvider => "Error rate per provider:",
+        Key::StatsNoProviders => "no providers",
+        Key::StatsMostActiveChats => "Most active chats:",
+        Key::StatsNoChats => "no chats",
+    }
+}
@@ -0,0 +1,175 @@
+//! # Italian
+//!
+//! The italian message catalogue; this is the reference catalogue of the bot
+
+use super::Key;
+
+pub fn text(key: Key) -> &'static str {
+    match key {
+        Key::Help => "Questi comandi sono disponibili:",
+        Key::Start => {
+            "CAFFEE!? ☕ Entra subito nel mondo dei buongiornissimi con /caffeee o se vuoi un dolce assaggio dei miei contenuti /buongiornissimo altrimenti invia /help per vedere tutti i comandi disponibili"
+        }
+        Key::Release => {
+            "buongiorno-caffe-bot ☕ {version}. Sviluppato da @veeso97. Contribuisci al progetto su Github https://github.com/veeso/buongiorno-caffe-bot. Sostieni il mio progetto su Ko-Fi https://ko-fi.com/veeso"
+        }
+        Key::Menu => "Buongiorno, CAFFEEE?! ☕ Cosa desideri?",
+        Key::BirthdaysMenu => {
+            "🎂 Per fare gli auguri a qualcuno invia /auguri <nome>. Per ricordarti di un compleanno premi il pulsante qui sotto o invia /nuovocompleanno: il bot invierà gli auguri quel giorno!"
+        }
+        Key::SubscriptionsMenu => "🔔 Vuoi ricevere ogni giorno le migliori immagini di augurio?",
+        Key::LanguageMenu => "🌍 In che lingua vuoi che ti parli?",
+        Key::LanguageSet => "Da ora ti parlo in italiano ☕",
+        Key::AdminOnly => "Questo comando è riservato agli amministratori ☕",
+        Key::UnknownUser => "Non so chi sei 🤔",
+        Key::Subscribed => {
+            "Buongiorno, CAFFEEE?! ☕☕☕  Da ora riceverei ogni giorno le migliori immagini di augurio."
+        }
+        Key::Unsubscribed => "ti sei disinscritto dai messaggi automatici ☕",
+        Key::AlreadySubscribed => "Sei già iscritto ai messaggi automatici.",
+        Key::HappyBirthday => "Buon compleanno {name}!",
+        Key::BirthdaySubscribed => {
+            "Buongiorno, CAFFEEE?! ☕☕☕  Da ora {name} riceverà gli auguri il giorno del suo compleanno."
+        }
+        Key::BirthdayAlreadyExists => "Questo compleanno è già presente registrato",
+        Key::BirthdayTargetMissing => {
+            "Rispondi a un messaggio del festeggiato oppure scrivi il suo nome ☕"
+        }
+        Key::OwnBirthdaySet => "🎂 Segnato! Il {date} riceverai gli auguri in questa chat ☕",
+        Key::UserForgotten => {
+            "🧹 Ho cancellato i tuoi compleanni da tutti i gruppi e i dati della nostra chat privata. Arrivederci ☕"
+        }
+        Key::CaptionSet => "📝 Da ora le immagini di {greeting} avranno questa didascalia:",
+        Key::CaptionCurrent => "📝 La didascalia delle immagini di {greeting} è:",
+        Key::CaptionNotSet => "Le immagini di {greeting} non hanno nessuna didascalia ☕",
+        Key::CaptionDeleted => "🧹 Ho rimosso la didascalia delle immagini di {greeting} ☕",
+        Key::CaptionBadTemplate => {
+            "Non ho capito la didascalia 🤔 Puoi usare i segnaposto {placeholders}; per scrivere una graffa raddoppiala ({{ o }})"
+        }
+        Key::CaptionTooLong => "La didascalia è troppo lunga: al massimo {max} caratteri ☕",
+        Key::AlbumSizeSet => {
+            "📸 Da ora nei messaggi automatici riceverai {amount} immagini per ogni saluto ☕"
+        }
+        Key::AlbumSizeCurrent => {
+            "📸 Nei messaggi automatici ricevi {amount} immagini per ogni saluto. Per cambiare invia /album <numero>, fino a {max}"
+        }
+        Key::WatermarkSet => {
+            "🖋️ Da ora le immagini avranno la filigrana {mark} in {position}, con opacità {opacity}% ☕"
+        }
+        Key::WatermarkCurrent => {
+            "🖋️ Le immagini hanno la filigrana {mark} in {position}, con opacità {opacity}%"
+        }
+        Key::WatermarkNotSet => "Le immagini non hanno nessuna filigrana ☕",
+        Key::WatermarkDeleted => "🧹 Ho rimosso la filigrana dalle immagini ☕",
+        Key::WatermarkBadLogo => {
+            "Non riesco a usare questo logo 🤔 Deve essere un'immagine PNG raggiungibile dal link"
+        }
+        Key::WatermarkMissingMark => {
+            "Fatto ☕ Ora scegli il testo della filigrana con /filigrana <testo> oppure un logo con /filigrana logo <link>"
+        }
+        Key::SubmissionReceived => {
+            "📬 Grazie! Gli amministratori valuteranno la tua immagine di {greeting} ☕"
+        }
+        Key::SubmissionMissingPhoto => {
+            "Invia una foto con /proponi <saluto> nella didascalia, oppure rispondi a una foto con /proponi <saluto> ☕"
+        }
+        Key::SubmissionToReview => "📬 {name} propone questa immagine di {greeting}",
+        Key::SubmissionApproved => {
+            "🎉 La tua immagine di {greeting} è stata approvata: da ora verrà inviata insieme alle altre ☕"
+        }
+        Key::SubmissionRejected => {
+            "La tua immagine di {greeting} non è stata approvata, sarà per la prossima ☕"
+        }
+        Key::ReviewApproved => "✅ Immagine approvata",
+        Key::ReviewRejected => "❌ Immagine rifiutata",
+        Key::ReviewAlreadyDone => "Questa immagine è già stata valutata ☕",
+        Key::FavouriteSaved => {
+            "❤️ Immagine salvata nei preferiti di {name}, ritrovala con /preferiti"
+        }
+        Key::FavouriteAlreadySaved => "{name}, questa immagine è già nei tuoi preferiti ☕",
+        Key::FavouriteMissingMedia => "Non trovo più l'immagine da salvare 🤔",
+        Key::FavouriteNotFound => "Questa immagine non è tra i tuoi preferiti 🤔",
+        Key::FavouritesTitle => {
+            "❤️ I preferiti di {name} (pagina {page} di {pages}): scegline uno per inviarlo qui"
+        }
+        Key::FavouritesEmpty => {
+            "Non hai ancora preferiti: premi ❤️ Salva sotto un'immagine per salvarla ☕"
+        }
+        Key::VoteUp => "👍 Grazie del voto, ne vedrai altre così ☕",
+        Key::VoteDown => "👎 Ricevuto, ne vedrai meno così",
+        Key::VoteFailed => "😓 Non sono riuscito a salvare il tuo voto, riprova più tardi",
+        Key::AskName => "🎂 Come si chiama il festeggiato? (invia /annulla per annullare)",
+        Key::AskNameAgain => "Scrivimi il nome del festeggiato per favore ☕",
+        Key::AskDate => {
+            "Quando è nato {name}? Scrivi la data come 12/03/1950 oppure 12 marzo (l'anno è facoltativo)"
+        }
+        Key::BadDate => {
+            "Non ho capito la data 🤔 Scrivila come 12/03/1950 oppure 12 marzo (l'anno è facoltativo)"
+        }
+        Key::ConfirmBirthday => "Confermi il compleanno di {name} il {date}?",
+        Key::NothingToConfirm => "Non c'è nessun compleanno da confermare ☕",
+        Key::DialogueCancelled => "Ok, niente compleanno ☕",
+        Key::DialogueInProgress => {
+            "Qualcun altro sta già registrando un compleanno, aspetta che abbia finito ☕"
+        }
+        Key::UsageAuguri => "Uso: /auguri <nome>, ad esempio /auguri Zia Maria",
+        Key::UsageCompleanno => {
+            "Uso: /compleanno <nome> <data>, ad esempio /compleanno Zia Maria 12 marzo 1950 oppure /compleanno \"Zia Maria\" 12/03. Puoi anche rispondere a un messaggio del festeggiato con /compleanno <data>"
+        }
+        Key::UsageIlMioCompleanno => {
+            "Uso: /ilmiocompleanno <data>, ad esempio /ilmiocompleanno 12 marzo oppure /ilmiocompleanno 12/03/1950"
+        }
+        Key::UsageDidascalia => {
+            "Uso: /didascalia <saluto> [testo], ad esempio /didascalia buongiorno Buon {giorno} {nome}! ☕. Puoi usare i segnaposto {nome}, {giorno}, {data}, {santo} e {meteo}. Senza testo ti mostro la didascalia attuale"
+        }
+        Key::UsageCancellaDidascalia => {
+            "Uso: /cancelladidascalia <saluto>, ad esempio /cancelladidascalia buonanotte"
+        }
+        Key::UsageAlbum => {
+            "Uso: /album [numero], ad esempio /album 3 per ricevere 3 immagini per ogni saluto automatico"
+        }
+        Key::UsageFiligrana => {
+            "Uso: /filigrana <testo> oppure /filigrana logo <link PNG>, ad esempio /filigrana Caffè Club. Puoi anche scegliere /filigrana posizione alto-sinistra|alto-destra|basso-sinistra|basso-destra|centro e /filigrana opacità <0-100>. Senza argomenti ti mostro la filigrana attuale"
+        }
+        Key::UsageGenera => {
+            "Uso: /genera [saluto], ad esempio /genera buonanotte. Senza saluto genero l'immagine del saluto del giorno"
+        }
+        Key::UsageProponi => {
+            "Uso: invia una foto con /proponi <saluto> nella didascalia, ad esempio /proponi buonanotte, oppure rispondi a una foto con /proponi <saluto>"
+        }
+        Key::UsageGreeting => {
+            "Uso: /buongiornissimo [numero], ad esempio /buongiornissimo 5 per ricevere un album di 5 immagini"
+        }
+        Key::UsageGeneric => {
+            "Non ho capito il comando 🤔 Invia /help per vedere tutti i comandi disponibili"
+        }
+        Key::ButtonGoodMorning => "☀️ Buongiorno",
+        Key::ButtonGoodNight => "🌙 Buonanotte",
+        Key::ButtonLunch => "🍝 Buon pranzo",
+        Key::ButtonAfternoon => "☕ Buon pomeriggio",
+        Key::ButtonDinner => "🍷 Buona cena",
+        Key::ButtonEvening => "🌆 Buona serata",
+        Key::ButtonWeekend => "🎉 Buon weekend",
+        Key::ButtonBirthdays => "🎂 Compleanni",
+        Key::ButtonSubscriptions => "🔔 Iscrizioni",
+        Key::ButtonSubscribe => "☕ Iscriviti",
+        Key::ButtonUnsubscribe => "🧹 Disiscriviti",
+        Key::ButtonNewBirthday => "➕ Nuovo compleanno",
+        Key::ButtonConfirm => "✅ Conferma",
+        Key::ButtonCancel => "❌ Annulla",
+        Key::ButtonAnotherOne => "🔄 Un'altra!",
+        Key::ButtonLanguage => "🌍 Lingua",
+        Key::ButtonApprove => "✅ Approva",
+        Key::ButtonReject => "❌ Rifiuta",
+        Key::ButtonSave => "❤️ Salva",
+        Key::ButtonPrevious => "◀️ Indietro",
+        Key::ButtonNext => "Avanti ▶️",
+        Key::StatsTitle => "📊 Statistiche consegne",
+        Key::StatsPerDay => "Consegne per giorno:",
+        Key::StatsNoDeliveries => "nessuna consegna",
+        Key::StatsPerProvider => "Tasso di errore per provider:",
+        Key::StatsNoProviders => "nessun provider",
+        Key::StatsMostActiveChats => "Chat più attive:",
+        Key::StatsNoChats => "nessuna chat",
+    }
+}
@@ -0,0 +1,32 @@
+//! # Neapolitan
+//!
+//! The neapolitan message catalogue; untranslated messages fall back to italian
+
+use super::Key;
+
+pub fn text(key: Key) -> Option<&'static str> {
+    let text = match key {
+        Key::Start => {
+            "CAFFÈ!? ☕ Trase mo' dint'ô munno d''e buongiornissimi cu /caffeee, o si vuò n'assaggio d''e cose meje manna /buongiornissimo, sinnò manna /help pe' vedé tutt''e cumanne"
+        }
+        Key::Menu => "Buongiorno, CAFFÈ?! ☕ Che vulite?",
+        Key::SubscriptionsMenu => "🔔 Vuò avé ogne juorno 'e meglie immagine d'augurio?",
+        Key::LanguageMenu => "🌍 Comme vuò ca te parlo?",
+        Key::LanguageSet => "Mo' te parlo napulitano ☕",
+        Key::UnknownUser => "Nun saccio chi si' 🤔",
+        Key::Subscribed => {
+            "Buongiorno, CAFFÈ?! ☕☕☕  Mo' ogne juorno te mann''e meglie immagine d'augurio."
+        }
+        Key::Unsubscribed => "Nun te manno cchiù 'e messagge automatice ☕",
+        Key::HappyBirthday => "Tanti auguri {name}! Cient'anne!",
+        Key::AskName => "🎂 Comme se chiamma 'o festeggiato? (manna /annulla pe' lassà sta')",
+        Key::DialogueCancelled => "Va buono, niente compleanno ☕",
+        Key::ButtonGoodMorning => "☀️ Buongiorno",
+        Key::ButtonGoodNight => "🌙 Bonanotte",
+        Key::ButtonBirthdays => "🎂 Compleanne",
+        Key::ButtonAnotherOne => "🔄 N'ata!",
+        Key::ButtonLanguage => "🌍 Lengua",
+        _ => return None,
+    };
+    Some(text)
+}
@@ -0,0 +1,32 @@
+//! # Venetian
+//!
+//! The venetian message catalogue; untranslated messages fall back to italian
+
+use super::Key;
+
+pub fn text(key: Key) -> Option<&'static str> {
+    let text = match key {
+        Key::Start => {
+            "CAFÈ!? ☕ Entra subito nel mondo dei buongiornissimi co /caffeee, o se ti vol un assagio dei me contenuti manda /buongiornissimo, se no manda /help par vardar tuti i comandi"
+        }
+        Key::Menu => "Bongiorno, CAFÈ?! ☕ Cossa ti vol?",
+        Key::SubscriptionsMenu => "🔔 Ti vol ricever ogni dì le mejo imagini de auguri?",
+        Key::LanguageMenu => "🌍 In che lengua ti vol che te parla?",
+        Key::LanguageSet => "Desso te parlo in vèneto ☕",
+        Key::UnknownUser => "No so chi ti xe 🤔",
+        Key::Subscribed => {
+            "Bongiorno, CAFÈ?! ☕☕☕  Da desso te rivarà ogni dì le mejo imagini de auguri."
+        }
+        Key::Unsubscribed => "No te rivarà più i messagi automatici ☕",
+        Key::HappyBirthday => "Bon compleano {name}!",
+        Key::AskName => "🎂 Come se ciama el festegià? (manda /annulla par lassar perder)",
+        Key::DialogueCancelled => "Va ben, gnente compleano ☕",
+        Key::ButtonGoodMorning => "☀️ Bongiorno",
+        Key::ButtonGoodNight => "🌙 Bonanote",
+        Key::ButtonBirthdays => "🎂 Compleani",
+        Key::ButtonAnotherOne => "🔄 N'altra!",
+        Key::ButtonLanguage => "🌍 Lengua",
+        _ => return None,
+    };
+    Some(text)
+}
@@ -0,0 +1,257 @@
+//! # Inline
+//!
+//! This module answers inline queries with a grid of greeting images.
+//! The results chosen by the users are reported back by telegram, if the inline feedback is enabled for the bot,
+//! so the images sent are recorded as the ones sent by the commands
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use buongiornissimo_rs::Greeting;
+use teloxide::prelude::*;
+use teloxide::types::{
+    FileId, InlineQueryResult, InlineQueryResultCachedPhoto, InlineQueryResultGif,
+    InlineQueryResultMpeg4Gif, InlineQueryResultPhoto,
+};
+use tracing::{debug, error};
+
+use super::media::{MediaKind, Validation};
+use super::providers::{GreetingImage, Providers};
+use super::repository::Repository;
+use super::{Automatizer, Buongiornissimo, HandlerResult, greeting, rating};
+use crate::metrics::METRICS;
+
+/// Maximum amount of images returned for an inline query
+const MAX_RESULTS: usize = 20;
+/// For how long scraped images are kept in the cache
+const CACHE_TTL: Duration = Duration::from_secs(15 * 60);
+/// For how long telegram may cache the inline query results, in seconds
+const TELEGRAM_CACHE_TIME: u32 = 300;
+
+/// Cache of the images scraped for each greeting
+#[derive(Default)]
+pub struct InlineCache {
+    entries: Mutex<HashMap<Greeting, (Instant, Vec<GreetingImage>)>>,
+}
+
+impl InlineCache {
+    /// Get the cached images for `greeting`, if not expired
+    fn get(&self, greeting: Greeting) -> Option<Vec<GreetingImage>> {
+        let entries = self.entries.lock().expect("inline cache lock poisoned");
+        entries
+            .get(&greeting)
+            .filter(|(cached_at, _)| cached_at.elapsed() < CACHE_TTL)
+            .map(|(_, images)| images.clone())
+    }
+
+    /// Find the cached image with the result id `id`, whatever its greeting
+    fn find(&self, id: &str) -> Option<GreetingImage> {
+        let entries = self.entries.lock().expect("inline cache lock poisoned");
+        entries
+            .values()
+            .flat_map(|(_, images)| images.iter())
+            .find(|image| result_id(image) == id)
+            .cloned()
+    }
+
+    /// Put the images for `greeting` in the cache
+    fn insert(&self, greeting: Greeting, images: Vec<GreetingImage>) {
+        self.entries
+            .lock()
+            .expect("inline cache lock poisoned")
+            .insert(greeting, (Instant::now(), images));
+    }
+}
+
+/// Answer an inline query with the images for the greeting matching the query text
+pub async fn answer(
+    bot: Bot,
+    query: InlineQuery,
+    cache: Arc<InlineCache>,
+    automatizer: Arc<Automatizer>,
+    repository: Repository,
+) -> HandlerResult {
+    debug!("got inline query {:?}", query.query);
+    METRICS.command_received("inline");
+    let images = match greeting::parse_kind(&query.query) {
+        Some(Greeting::BuonGiorno) => {
+            let daily_greeting = automatizer.daily_greeting();
+            let greeting = Buongiornissimo::greeting_of_the_day(&repository, daily_greeting).await;
+            let images = images(&cache, &repository, greeting).await;
+            match image_of_the_day(&repository, daily_greeting).await {
+                Some(image) => lead_with(images, image),
+                None => images,
+            }
+        }
+        Some(greeting) => images(&cache, &repository, greeting).await,
+        None => Vec::new(),
+    };
+    // telegram fetches the inline results by url, so the generated images can't be used
+    let results: Vec<InlineQueryResult> = images
+        .into_iter()
+        .filter(|image| image.provider != Providers::Generator)
+        .map(|image| result(result_id(&image), image))
+        .collect();
+
+    bot.answer_inline_query(query.id, results)
+        .cache_time(TELEGRAM_CACHE_TIME)
+        .await?;
+    Ok(())
+}
+
+/// Record the image of the inline query result chosen by the user as sent
+pub async fn chosen(
+    result: ChosenInlineResult,
+    cache: Arc<InlineCache>,
+    automatizer: Arc<Automatizer>,
+    repository: Repository,
+) -> HandlerResult {
+    debug!("got chosen inline result {:?}", result.result_id);
+    let image = match cache.find(&result.result_id) {
+        Some(image) => image,
+        None => match image_of_the_day(&repository, automatizer.daily_greeting())
+            .await
+            .filter(|image| result_id(image) == result.result_id)
+        {
+            Some(image) => image,
+            None => {
+                debug!("chosen inline result {} not cached", result.result_id);
+                return Ok(());
+            }
+        },
+    };
+    if image.hash.is_some() {
+        repository.mark_image_sent(image.url.as_str()).await?;
+    }
+    Ok(())
+}
+
+/// Get the image of the day stored in daily greeting mode, so that the inline good morning shows the same image as the commands.
+///
+/// Inline queries never choose it: the images they get only have a quick validation
+async fn image_of_the_day(repository: &Repository, daily_greeting: bool) -> Option<GreetingImage> {
+    if !daily_greeting {
+        return None;
+    }
+    match repository.get_image_of_the_day().await {
+        Ok(image) => image,
+        Err(err) => {
+            error!("failed to get image of the day for inline query: {}", err);
+            None
+        }
+    }
+}
+
+/// Put `image` first among `images`, without duplicating it
+fn lead_with(mut images: Vec<GreetingImage>, image: GreetingImage) -> Vec<GreetingImage> {
+    images.retain(|other| other.url != image.url);
+    images.insert(0, image);
+    images.truncate(MAX_RESULTS);
+    images
+}
+
+/// Get the id of the inline query result for `image`, which identifies the image as the votes do
+fn result_id(image: &GreetingImage) -> String {
+    format!("{:016x}", rating::image_key(&image.url))
+}
+
+/// Build the inline query result for `image`; the media itself is used as thumbnail,
+/// while the images submitted by the users are already stored on telegram
+fn result(id: String, image: GreetingImage) -> InlineQueryResult {
+    if let Some(file_id) = image.file_id() {
+        return InlineQueryResult::CachedPhoto(InlineQueryResultCachedPhoto::new(
+            id,
+            FileId(file_id.to_string()),
+        ));
+    }
+    let url = image.url;
+    match image.kind {
+        MediaKind::Image => {
+            InlineQueryResult::Photo(InlineQueryResultPhoto::new(id, url.clone(), url))
+        }
+        MediaKind::Animation => {
+            InlineQueryResult::Gif(InlineQueryResultGif::new(id, url.clone(), url))
+        }
+        MediaKind::Video => {
+            InlineQueryResult::Mpeg4Gif(InlineQueryResultMpeg4Gif::new(id, url.clone(), url))
+        }
+    }
+}
+
+/// Get the images for `greeting`, from the cache if available, otherwise from the providers.
+/// Telegram waits for the answer a few seconds only, so the scraped images get a quick validation
+async fn images(
+    cache: &InlineCache,
+    repository: &Repository,
+    greeting: Greeting,
+) -> Vec<GreetingImage> {
+    if let Some(images) = cache.get(greeting) {
+        debug!("using cached images for {:?}", greeting);
+        return images;
+    }
+    match Buongiornissimo::get_greeting_images_with(
+        repository,
+        greeting,
+        MAX_RESULTS,
+        Validation::Quick,
+    )
+    .await
+    {
+        Ok(images) => {
+            cache.insert(greeting, images.clone());
+            images
+        }
+        Err(err) => {
+            error!(
+                "failed to get images for inline query {:?}: {}",
+                greeting, err
+            );
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+    use url::Url;
+
+    use super::*;
+
+    #[test]
+    fn should_cache_images() {
+        let cache = InlineCache::default();
+        assert!(cache.get(Greeting::BuonaNotte).is_none());
+        let images = vec![GreetingImage {
+            provider: Providers::Augurando,
+            url: Url::parse("https://example.com/notte.gif").unwrap(),
+            kind: MediaKind::Animation,
+            hash: None,
+        }];
+        cache.insert(Greeting::BuonaNotte, images.clone());
+        assert_eq!(cache.get(Greeting::BuonaNotte), Some(images.clone()));
+        assert!(cache.get(Greeting::BuonPranzo).is_none());
+        assert_eq!(cache.find(&result_id(&images[0])), Some(images[0].clone()));
+        assert!(cache.find("0000000000000000").is_none());
+    }
+
+    #[test]
+    fn should_lead_with_image_of_the_day() {
+        let image = |name: &str| GreetingImage {
+            provider: Providers::Augurando,
+            url: Url::parse(&format!("https://example.com/{name}.jpg")).unwrap(),
+            kind: MediaKind::Image,
+            hash: None,
+        };
+        let images = (0..MAX_RESULTS).map(|i| image(&i.to_string())).collect();
+        let images = lead_with(images, image("caffe"));
+        assert_eq!(images.len(), MAX_RESULTS);
+        assert_eq!(images[0], image("caffe"));
+        assert_eq!(images[1], image("0"));
+        // the image of the day is not repeated
+        let images = lead_with(vec![image("notte"), image("caffe")], image("caffe"));
+        assert_eq!(images, vec![image("caffe"), image("notte")]);
+    }
+}
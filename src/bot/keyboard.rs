@@ -0,0 +1,355 @@
+//! # Keyboard
+//!
+//! This module provides the inline keyboards of the bot and the data of their callback queries
+
+use buongiornissimo_rs::Greeting;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+use super::greeting;
+use super::i18n::{Key, Language};
+
+/// The action requested by pressing an inline keyboard button
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallbackData {
+    /// Send an image for the greeting; [`Greeting::BuonGiorno`] stands for the greeting of the day
+    Greeting(Greeting),
+    /// Show the birthdays menu
+    Birthdays,
+    /// Start the guided birthday registration
+    NewBirthday,
+    /// Save the birthday being registered
+    ConfirmBirthday,
+    /// Abort the birthday registration
+    CancelBirthday,
+    /// Show the subscriptions menu
+    Subscriptions,
+    /// Subscribe the chat to the automatic messages
+    Subscribe,
+    /// Unsubscribe the chat from the automatic messages
+    Unsubscribe,
+    /// Show the languages menu
+    Languages,
+    /// Set the language of the chat
+    Language(Language),
+    /// Approve the proposed image with the submission id
+    ApproveSubmission(i64),
+    /// Reject the proposed image with the submission id
+    RejectSubmission(i64),
+    /// Save the image of the greeting among the favourites of the user
+    SaveFavourite(Greeting),
+    /// Show the page of the favourites of the user
+    Favourites(usize),
+    /// Send the favourite image with the id
+    SendFavourite(i64),
+    /// Vote the image with the key, up or down
+    Vote { image: u64, up: bool },
+}
+
+impl CallbackData {
+    /// Parse callback data from the callback query data
+    pub fn parse(data: &str) -> Option<Self> {
+        match data.split_once(':') {
+            Some(("greeting", keyword)) => greeting::parse_kind(keyword).map(Self::Greeting),
+            Some(("language", code)) => Language::parse(code).map(Self::Language),
+            Some(("approve", id)) => id.parse().ok().map(Self::ApproveSubmission),
+            Some(("reject", id)) => id.parse().ok().map(Self::RejectSubmission),
+            Some(("save", keyword)) => greeting::parse_kind(keyword).map(Self::SaveFavourite),
+            Some(("favourites", page)) => page.parse().ok().map(Self::Favourites),
+            Some(("favourite", id)) => id.parse().ok().map(Self::SendFavourite),
+            Some(("vote", vote)) => {
+                let (direction, image) = vote.split_once(':')?;
+                let image = u64::from_str_radix(image, 16).ok()?;
+                match direction {
+                    "up" => Some(Self::Vote { image, up: true }),
+                    "down" => Some(Self::Vote { image, up: false }),
+                    _ => None,
+                }
+            }
+            None if data == "birthdays" => Some(Self::Birthdays),
+            None if data == "new_birthday" => Some(Self::NewBirthday),
+            None if data == "confirm_birthday" => Some(Self::ConfirmBirthday),
+            None if data == "cancel_birthday" => Some(Self::CancelBirthday),
+            None if data == "subscriptions" => Some(Self::Subscriptions),
+            None if data == "subscribe" => Some(Self::Subscribe),
+            None if data == "unsubscribe" => Some(Self::Unsubscribe),
+            None if data == "languages" => Some(Self::Languages),
+            _ => None,
+        }
+    }
+
+    /// Get the callback query data to attach to a button
+    pub fn to_data(self) -> String {
+        match self {
+            Self::Greeting(greeting) => format!("greeting:{}", greeting::keyword(greeting)),
+            Self::Birthdays => "birthdays".to_string(),
+            Self::NewBirthday => "new_birthday".to_string(),
+            Self::ConfirmBirthday => "confirm_birthday".to_string(),
+            Self::CancelBirthday => "cancel_birthday".to_string(),
+            Self::Subscriptions => "subscriptions".to_string(),
+            Self::Subscribe => "subscribe".to_string(),
+            Self::Unsubscribe => "unsubscribe".to_string(),
+            Self::Languages => "languages".to_string(),
+            Self::Language(language) => format!("language:{}", language.code()),
+            Self::ApproveSubmission(id) => format!("approve:{id}"),
+            Self::RejectSubmission(id) => format!("reject:{id}"),
+            Self::SaveFavourite(greeting) => format!("save:{}", greeting::keyword(greeting)),
+            Self::Favourites(page) => format!("favourites:{page}"),
+            Self::SendFavourite(id) => format!("favourite:{id}"),
+            Self::Vote { image, up } => {
+                format!("vote:{}:{image:016x}", if up { "up" } else { "down" })
+            }
+        }
+    }
+}
+
+/// Build a button labelled with the `key` message in `lang`, which triggers `data` when pressed
+fn button(lang: Language, key: Key, data: CallbackData) -> InlineKeyboardButton {
+    InlineKeyboardButton::callback(lang.text(key), data.to_data())
+}
+
+/// The main menu
+pub fn menu(lang: Language) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([
+        vec![
+            button(
+                lang,
+                Key::ButtonGoodMorning,
+                CallbackData::Greeting(Greeting::BuonGiorno),
+            ),
+            button(
+                lang,
+                Key::ButtonGoodNight,
+                CallbackData::Greeting(Greeting::BuonaNotte),
+            ),
+        ],
+        vec![
+            button(
+                lang,
+                Key::ButtonLunch,
+                CallbackData::Greeting(Greeting::BuonPranzo),
+            ),
+            button(
+                lang,
+                Key::ButtonAfternoon,
+                CallbackData::Greeting(Greeting::BuonPomeriggio),
+            ),
+        ],
+        vec![
+            button(
+                lang,
+                Key::ButtonDinner,
+                CallbackData::Greeting(Greeting::BuonaCena),
+            ),
+            button(
+                lang,
+                Key::ButtonEvening,
+                CallbackData::Greeting(Greeting::BuonaSerata),
+            ),
+        ],
+        vec![
+            button(
+                lang,
+                Key::ButtonWeekend,
+                CallbackData::Greeting(Greeting::Weekend),
+            ),
+            button(lang, Key::ButtonBirthdays, CallbackData::Birthdays),
+        ],
+        vec![
+            button(lang, Key::ButtonSubscriptions, CallbackData::Subscriptions),
+            button(lang, Key::ButtonLanguage, CallbackData::Languages),
+        ],
+    ])
+}
+
+/// The subscriptions menu
+pub fn subscriptions(lang: Language) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([vec![
+        button(lang, Key::ButtonSubscribe, CallbackData::Subscribe),
+        button(lang, Key::ButtonUnsubscribe, CallbackData::Unsubscribe),
+    ]])
+}
+
+/// The birthdays menu
+pub fn birthdays(lang: Language) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([vec![button(
+        lang,
+        Key::ButtonNewBirthday,
+        CallbackData::NewBirthday,
+    )]])
+}
+
+/// The keyboard to confirm the birthday being registered
+pub fn confirm_birthday(lang: Language) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([vec![
+        button(lang, Key::ButtonConfirm, CallbackData::ConfirmBirthday),
+        button(lang, Key::ButtonCancel, CallbackData::CancelBirthday),
+    ]])
+}
+
+/// The keyboard to choose the language; languages are labelled with their own name
+pub fn languages() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(Language::ALL.map(|language| {
+        vec![InlineKeyboardButton::callback(
+            language.name(),
+            CallbackData::Language(language).to_data(),
+        )]
+    }))
+}
+
+/// The keyboard to attach under images, to request another image for the same greeting,
+/// to save the image among the favourites or to vote the image with key `image`
+pub fn another_one(lang: Language, greeting: Greeting, image: u64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([
+        vec![
+            button(
+                lang,
+                Key::ButtonAnotherOne,
+                CallbackData::Greeting(greeting),
+            ),
+            button(lang, Key::ButtonSave, CallbackData::SaveFavourite(greeting)),
+        ],
+        vec![
+            InlineKeyboardButton::callback("👍", CallbackData::Vote { image, up: true }.to_data()),
+            InlineKeyboardButton::callback("👎", CallbackData::Vote { image, up: false }.to_data()),
+        ],
+    ])
+}
+
+/// The keyboard listing the `favourites` in the page `page` of `pages`, as pairs of id and label,
+/// with the buttons to move to the previous and next pages
+pub fn favourites(
+    lang: Language,
+    favourites: &[(i64, String)],
+    page: usize,
+    pages: usize,
+) -> InlineKeyboardMarkup {
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = favourites
+        .iter()
+        .map(|(id, label)| {
+            vec![InlineKeyboardButton::callback(
+                label,
+                CallbackData::SendFavourite(*id).to_data(),
+            )]
+        })
+        .collect();
+    let mut navigation = Vec::new();
+    if page > 0 {
+        navigation.push(button(
+            lang,
+            Key::ButtonPrevious,
+            CallbackData::Favourites(page - 1),
+        ));
+    }
+    if page + 1 < pages {
+        navigation.push(button(
+            lang,
+            Key::ButtonNext,
+            CallbackData::Favourites(page + 1),
+        ));
+    }
+    if !navigation.is_empty() {
+        rows.push(navigation);
+    }
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// The keyboard to review the image proposed with the submission `id`
+pub fn review(lang: Language, id: i64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([vec![
+        button(
+            lang,
+            Key::ButtonApprove,
+            CallbackData::ApproveSubmission(id),
+        ),
+        button(lang, Key::ButtonReject, CallbackData::RejectSubmission(id)),
+    ]])
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_convert_callback_data() {
+        for data in [
+            CallbackData::Greeting(Greeting::BuonaNotte),
+            CallbackData::Greeting(Greeting::Natale),
+            CallbackData::Greeting(Greeting::Compleanno),
+            CallbackData::Birthdays,
+            CallbackData::NewBirthday,
+            CallbackData::ConfirmBirthday,
+            CallbackData::CancelBirthday,
+            CallbackData::Subscriptions,
+            CallbackData::Subscribe,
+            CallbackData::Unsubscribe,
+            CallbackData::Languages,
+            CallbackData::Language(Language::English),
+            CallbackData::Language(Language::Neapolitan),
+            CallbackData::ApproveSubmission(42),
+            CallbackData::RejectSubmission(7),
+            CallbackData::SaveFavourite(Greeting::BuonGiorno),
+            CallbackData::SaveFavourite(Greeting::BuonaNotte),
+            CallbackData::Favourites(3),
+            CallbackData::SendFavourite(42),
+            CallbackData::Vote {
+                image: u64::MAX,
+                up: true,
+            },
+            CallbackData::Vote {
+                image: 0,
+                up: false,
+            },
+        ] {
+            assert_eq!(CallbackData::parse(&data.to_data()), Some(data));
+        }
+    }
+
+    #[test]
+    fn should_convert_good_morning_to_greeting_of_the_day() {
+        assert_eq!(
+            CallbackData::Greeting(Greeting::BuonGiorno).to_data(),
+            "greeting:buongiorno"
+        );
+        assert_eq!(
+            CallbackData::parse("greeting:buongiorno"),
+            Some(CallbackData::Greeting(Greeting::BuonGiorno))
+        );
+    }
+
+    #[test]
+    fn should_not_parse_bad_callback_data() {
+        assert_eq!(CallbackData::parse("greeting:asdrubale"), None);
+        assert_eq!(CallbackData::parse("foo"), None);
+        assert_eq!(CallbackData::parse("foo:bar"), None);
+        assert_eq!(CallbackData::parse("language:klingon"), None);
+        assert_eq!(CallbackData::parse("approve:caffe"), None);
+        assert_eq!(CallbackData::parse("favourites:-1"), None);
+        assert_eq!(CallbackData::parse("vote:sideways:00ff"), None);
+        assert_eq!(CallbackData::parse("vote:up:kaffe"), None);
+    }
+
+    #[test]
+    fn should_translate_buttons() {
+        let keyboard = another_one(Language::English, Greeting::BuonaNotte, 42);
+        assert_eq!(keyboard.inline_keyboard[0][0].text, "🔄 Another one!");
+        let keyboard = another_one(Language::Italian, Greeting::BuonaNotte, 42);
+        assert_eq!(keyboard.inline_keyboard[0][0].text, "🔄 Un'altra!");
+        assert_eq!(keyboard.inline_keyboard[0][1].text, "❤️ Salva");
+    }
+
+    #[test]
+    fn should_paginate_favourites() {
+        let page = [(1, "1. notte".to_string()), (2, "2. pranzo".to_string())];
+        let keyboard = favourites(Language::Italian, &page, 0, 1);
+        assert_eq!(keyboard.inline_keyboard.len(), 2);
+        let keyboard = favourites(Language::Italian, &page, 0, 3);
+        assert_eq!(keyboard.inline_keyboard[2].len(), 1);
+        let keyboard = favourites(Language::Italian, &page, 1, 3);
+        assert_eq!(keyboard.inline_keyboard[2].len(), 2);
+        let keyboard = favourites(Language::Italian, &page, 2, 3);
+        assert_eq!(keyboard.inline_keyboard[2].len(), 1);
+    }
+}
@@ -0,0 +1,512 @@
+//! # Media
+//!
+//! This module downloads the media the greeting urls point to, detects their kind,
+//! checks they can be sent as greetings and computes their perceptual hash
+
+use std::collections::HashSet;
+use std::io::Cursor;
+use std::sync::{LazyLock, OnceLock};
+use std::time::Duration;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageError, ImageReader};
+use reqwest::header::CONTENT_TYPE;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::warn;
+use url::Url;
+
+/// Timeout of the requests made to download the media
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Maximum size of an image, as allowed by telegram for photos sent by url
+const MAX_IMAGE_SIZE: usize = 5 * 1024 * 1024;
+/// Maximum size of an animation or a video, as allowed by telegram for files sent by url
+const MAX_FILE_SIZE: usize = 20 * 1024 * 1024;
+/// Minimum width and height of an image; smaller images are thumbnails
+const MIN_DIMENSION: u32 = 200;
+/// Maximum amount of different bits between the perceptual hashes of two images considered the same image
+const DUPLICATE_DISTANCE: u32 = 5;
+/// Amount of bytes read to sniff the kind of a media
+const SNIFF_LEN: usize = 12;
+/// Brands of the ISO base media files which are MP4 videos; other brands, such as HEIC images or QuickTime movies, can't be sent
+const MP4_BRANDS: [&[u8]; 12] = [
+    b"isom", b"iso2", b"iso4", b"iso5", b"iso6", b"mp41", b"mp42", b"avc1", b"M4V ", b"M4VH",
+    b"M4VP", b"dash",
+];
+
+/// Content hashes of the known placeholder images, which are never sent
+static PLACEHOLDER_HASHES: OnceLock<HashSet<String>> = OnceLock::new();
+
+/// HTTP client used to download the media
+static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("failed to build http client")
+});
+
+/// Media validation error
+#[derive(Debug, Error)]
+pub enum MediaError {
+    #[error("failed to download media: {0}")]
+    Download(#[from] reqwest::Error),
+    #[error("unsupported content type {0}")]
+    UnsupportedType(String),
+    #[error("media is too big ({0} bytes)")]
+    TooBig(usize),
+    #[error("image is too small ({0}x{1})")]
+    TooSmall(u32, u32),
+    #[error("media is a known placeholder")]
+    Placeholder,
+    #[error("failed to decode image: {0}")]
+    Decode(#[from] ImageError),
+}
+
+impl MediaError {
+    /// Get a short name describing why the media is invalid
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::Download(_) => "download",
+            Self::UnsupportedType(_) => "unsupported_type",
+            Self::TooBig(_) => "too_big",
+            Self::TooSmall(..) => "too_small",
+            Self::Placeholder => "placeholder",
+            Self::Decode(_) => "decode",
+        }
+    }
+}
+
+/// How thoroughly a media is validated before being sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validation {
+    /// The media is downloaded entirely and the images are decoded and hashed
+    Full,
+    /// Only the first bytes of the media are read, to tell its kind; meant for the answers which must be quick, such as inline queries
+    Quick,
+}
+
+/// A media which can be sent as a greeting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MediaInfo {
+    pub kind: MediaKind,
+    /// The perceptual hash of the image; videos have none
+    pub hash: Option<u64>,
+}
+
+/// The kind of a media, which determines how it is sent
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaKind {
+    /// A static image, such as a JPEG or a PNG
+    #[default]
+    Image,
+    /// An animated GIF
+    Animation,
+    /// An MP4 video
+    Video,
+}
+
+impl MediaKind {
+    /// Get the kind of media from a MIME type, such as `image/gif`
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        let mime = mime.split(';').next().unwrap_or_default().trim();
+        match mime.to_lowercase().as_str() {
+            "image/gif" => Some(Self::Animation),
+            "video/mp4" => Some(Self::Video),
+            mime if mime.starts_with("image/") => Some(Self::Image),
+            _ => None,
+        }
+    }
+
+    /// Get the name of the kind of media, as stored in the repository
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Animation => "animation",
+            Self::Video => "video",
+        }
+    }
+
+    /// Get the kind of media from its [`MediaKind::name`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "image" => Some(Self::Image),
+            "animation" => Some(Self::Animation),
+            "video" => Some(Self::Video),
+            _ => None,
+        }
+    }
+
+    /// Sniff the kind of media from the first bytes of its content.
+    /// ISO base media files are videos only if their brand is an MP4 one
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(Self::Animation)
+        } else if let Some(brand) = iso_media_brand(bytes) {
+            MP4_BRANDS.contains(&brand).then_some(Self::Video)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF])
+            || bytes.starts_with(b"\x89PNG")
+            || (bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP".as_slice()))
+        {
+            Some(Self::Image)
+        } else {
+            None
+        }
+    }
+
+    /// Get the maximum size of the media of this kind, as allowed by telegram
+    fn max_size(self) -> usize {
+        match self {
+            Self::Image => MAX_IMAGE_SIZE,
+            Self::Animation | Self::Video => MAX_FILE_SIZE,
+        }
+    }
+}
+
+/// Get the brand of the ISO base media file (MP4, HEIC, QuickTime...) starting with `bytes`, if it is one
+fn iso_media_brand(bytes: &[u8]) -> Option<&[u8]> {
+    (bytes.get(4..8) == Some(b"ftyp".as_slice()))
+        .then(|| bytes.get(8..12))
+        .flatten()
+}
+
+/// Get the kind of the media starting with `bytes`: the kind is sniffed from the content, falling back to the `content_type`.
+/// ISO base media files which are not MP4 videos are not supported, whatever their content type
+fn media_kind(bytes: &[u8], content_type: Option<&str>) -> Result<MediaKind, MediaError> {
+    if let Some(kind) = MediaKind::sniff(bytes) {
+        return Ok(kind);
+    }
+    if let Some(brand) = iso_media_brand(bytes) {
+        return Err(MediaError::UnsupportedType(format!(
+            "ftyp/{}",
+            String::from_utf8_lossy(brand).trim()
+        )));
+    }
+    content_type
+        .and_then(MediaKind::from_mime)
+        .ok_or_else(|| MediaError::UnsupportedType(content_type.unwrap_or("unknown").to_string()))
+}
+
+/// Set the content hashes, as returned by [`content_hash`], of the known placeholder images
+pub fn set_placeholder_hashes(hashes: impl IntoIterator<Item = String>) {
+    let hashes = hashes
+        .into_iter()
+        .map(|hash| hash.trim().to_lowercase())
+        .collect();
+    if PLACEHOLDER_HASHES.set(hashes).is_err() {
+        warn!("placeholder hashes already set");
+    }
+}
+
+/// Get the hex encoded SHA-256 digest of `bytes`
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Compute the perceptual hash (dHash) of `image`: each bit tells whether a pixel of the image,
+/// scaled down to 9x8 in grayscale, is brighter than the pixel on its right.
+///
+/// Resized or recompressed copies of an image have the same hash, or a very close one
+pub fn perceptual_hash(image: &DynamicImage) -> u64 {
+    let pixels = image.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if pixels.get_pixel(x, y)[0] > pixels.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// Whether the perceptual hashes `a` and `b` belong to the same image
+pub fn is_duplicate(a: u64, b: u64) -> bool {
+    (a ^ b).count_ones() <= DUPLICATE_DISTANCE
+}
+
+/// Check the media at `url` can be sent as a greeting, as thoroughly as told by `validation`
+pub async fn validate(url: &Url, validation: Validation) -> Result<MediaInfo, MediaError> {
+    if validation == Validation::Quick {
+        return probe(url).await;
+    }
+    let (bytes, content_type) = download(url).await?;
+    check(
+        &bytes,
+        content_type.as_deref(),
+        PLACEHOLDER_HASHES.get_or_init(HashSet::new),
+    )
+}
+
+/// Check the kind and the declared size of the media at `url`, reading only its first bytes.
+/// The images are neither decoded nor hashed
+async fn probe(url: &Url) -> Result<MediaInfo, MediaError> {
+    let (mut response, content_type) = request(url).await?;
+    let mut bytes = Vec::with_capacity(SNIFF_LEN);
+    while bytes.len() < SNIFF_LEN {
+        match response.chunk().await? {
+            Some(chunk) => bytes.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+    let kind = media_kind(&bytes, content_type.as_deref())?;
+    check_declared_size(&response, kind.max_size())?;
+
+    Ok(MediaInfo { kind, hash: None })
+}
+
+/// Download the media at `url`, along with its content type.
+/// The download stops as soon as the media exceeds the maximum size allowed by telegram for its kind,
+/// which is told by the first bytes or by the content type
+pub async fn download(url: &Url) -> Result<(Vec<u8>, Option<String>), MediaError> {
+    let (mut response, content_type) = request(url).await?;
+    let declared_kind = content_type.as_deref().and_then(MediaKind::from_mime);
+    let max_size = |bytes: &[u8]| {
+        MediaKind::sniff(bytes)
+            .or(declared_kind)
+            .map_or(MAX_FILE_SIZE, MediaKind::max_size)
+    };
+    check_declared_size(
+        &response,
+        declared_kind.map_or(MAX_FILE_SIZE, MediaKind::max_size),
+    )?;
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > max_size(&bytes) {
+            return Err(MediaError::TooBig(bytes.len()));
+        }
+    }
+    Ok((bytes, content_type))
+}
+
+/// Request the media at `url`, returning the response, whose body is still to read, and the content type
+async fn request(url: &Url) -> Result<(reqwest::Response, Option<String>), MediaError> {
+    let response = HTTP_CLIENT
+        .get(url.clone())
+        .send()
+        .await?
+        .error_for_status()?;
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    Ok((response, content_type))
+}
+
+/// Check the size declared by the `Content-Length` of `response` doesn't exceed `max_size`
+fn check_declared_size(response: &reqwest::Response, max_size: usize) -> Result<(), MediaError> {
+    match response.content_length() {
+        Some(size) if size > max_size as u64 => Err(MediaError::TooBig(size as usize)),
+        _ => Ok(()),
+    }
+}
+
+/// Check the downloaded media `bytes`: the kind is sniffed from the content, falling back to the `content_type`;
+/// then the size, the dimensions of the images and the `placeholders` hashes are checked
+fn check(
+    bytes: &[u8],
+    content_type: Option<&str>,
+    placeholders: &HashSet<String>,
+) -> Result<MediaInfo, MediaError> {
+    let kind = media_kind(bytes, content_type)?;
+    if bytes.len() > kind.max_size() {
+        return Err(MediaError::TooBig(bytes.len()));
+    }
+    if placeholders.contains(&content_hash(bytes)) {
+        return Err(MediaError::Placeholder);
+    }
+    // videos can't be decoded, so their dimensions are not checked; animations are checked on their first frame
+    if kind == MediaKind::Video {
+        return Ok(MediaInfo { kind, hash: None });
+    }
+    let image = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(ImageError::IoError)?
+        .decode()?;
+    if image.width() < MIN_DIMENSION || image.height() < MIN_DIMENSION {
+        return Err(MediaError::TooSmall(image.width(), image.height()));
+    }
+
+    Ok(MediaInfo {
+        kind,
+        hash: Some(perceptual_hash(&image)),
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_get_kind_from_mime() {
+        assert_eq!(MediaKind::from_mime("image/jpeg"), Some(MediaKind::Image));
+        assert_eq!(
+            MediaKind::from_mime("Image/GIF; charset=binary"),
+            Some(MediaKind::Animation)
+        );
+        assert_eq!(MediaKind::from_mime("video/mp4"), Some(MediaKind::Video));
+        assert_eq!(MediaKind::from_mime("text/html"), None);
+    }
+
+    #[test]
+    fn should_convert_kind_to_name() {
+        for kind in [MediaKind::Image, MediaKind::Animation, MediaKind::Video] {
+            assert_eq!(MediaKind::from_name(kind.name()), Some(kind));
+        }
+        assert_eq!(MediaKind::from_name("audio"), None);
+    }
+
+    #[test]
+    fn should_sniff_kind() {
+        assert_eq!(
+            MediaKind::sniff(b"GIF89a\x01\x00"),
+            Some(MediaKind::Animation)
+        );
+        assert_eq!(
+            MediaKind::sniff(b"\x00\x00\x00\x18ftypmp42"),
+            Some(MediaKind::Video)
+        );
+        assert_eq!(
+            MediaKind::sniff(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Some(MediaKind::Image)
+        );
+        assert_eq!(
+            MediaKind::sniff(b"RIFF\x00\x00\x00\x00WEBPVP8 "),
+            Some(MediaKind::Image)
+        );
+        assert_eq!(MediaKind::sniff(b"\x00\x00\x00\x18ftypheic"), None);
+        assert_eq!(MediaKind::sniff(b"\x00\x00\x00\x14ftypqt  "), None);
+        assert_eq!(MediaKind::sniff(b"<!DOCTYPE html>"), None);
+        assert_eq!(MediaKind::sniff(b""), None);
+    }
+
+    #[test]
+    fn should_reject_iso_media_which_are_not_mp4() {
+        assert!(matches!(
+            media_kind(b"\x00\x00\x00\x18ftypheic", Some("image/heic")),
+            Err(MediaError::UnsupportedType(content_type)) if content_type == "ftyp/heic"
+        ));
+        assert!(matches!(
+            media_kind(b"\x00\x00\x00\x14ftypqt  ", Some("video/mp4")),
+            Err(MediaError::UnsupportedType(content_type)) if content_type == "ftyp/qt"
+        ));
+        assert_eq!(
+            media_kind(b"\x00\x00\x00\x18ftypisom", None).unwrap(),
+            MediaKind::Video
+        );
+    }
+
+    #[test]
+    fn should_accept_valid_media() {
+        let placeholders = HashSet::new();
+        assert_eq!(
+            check(&png(640, 480), Some("image/png"), &placeholders)
+                .unwrap()
+                .kind,
+            MediaKind::Image
+        );
+        assert_eq!(
+            check(
+                &png(640, 480),
+                Some("application/octet-stream"),
+                &placeholders
+            )
+            .unwrap()
+            .kind,
+            MediaKind::Image
+        );
+        assert_eq!(
+            check(b"\x00\x00\x00\x18ftypmp42", None, &placeholders)
+                .unwrap()
+                .kind,
+            MediaKind::Video
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_media() {
+        let placeholders = HashSet::new();
+        assert!(matches!(
+            check(b"<!DOCTYPE html>", Some("text/html"), &placeholders),
+            Err(MediaError::UnsupportedType(content_type)) if content_type == "text/html"
+        ));
+        assert!(matches!(
+            check(&png(150, 480), Some("image/png"), &placeholders),
+            Err(MediaError::TooSmall(150, 480))
+        ));
+        assert!(matches!(
+            check(b"<!DOCTYPE html>", Some("image/jpeg"), &placeholders),
+            Err(MediaError::Decode(_))
+        ));
+        let mut too_big = png(640, 480);
+        too_big.resize(MAX_IMAGE_SIZE + 1, 0);
+        assert!(matches!(
+            check(&too_big, Some("image/png"), &placeholders),
+            Err(MediaError::TooBig(_))
+        ));
+    }
+
+    #[test]
+    fn should_reject_placeholders() {
+        let placeholder = png(640, 480);
+        let placeholders = HashSet::from([content_hash(&placeholder)]);
+        assert!(matches!(
+            check(&placeholder, Some("image/png"), &placeholders),
+            Err(MediaError::Placeholder)
+        ));
+        assert!(check(&png(480, 640), Some("image/png"), &placeholders).is_ok());
+    }
+
+    #[test]
+    fn should_hash_content() {
+        assert_eq!(
+            content_hash(b"caffe"),
+            "c7e93c53f9ec70fd38b5520afcc8723c72fe36f13847427848bb298b26d7b592"
+        );
+    }
+
+    #[test]
+    fn should_detect_duplicate_images() {
+        let gradient = gradient(640, 480, false);
+        let hash = perceptual_hash(&gradient);
+        assert_eq!(hash, u64::MAX);
+        // resized and recompressed copies
+        let resized = gradient.resize_exact(320, 240, FilterType::Nearest);
+        assert!(is_duplicate(hash, perceptual_hash(&resized)));
+        let mut jpeg = Vec::new();
+        gradient
+            .write_to(&mut Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+        let recompressed = image::load_from_memory(&jpeg).unwrap();
+        assert!(is_duplicate(hash, perceptual_hash(&recompressed)));
+        // different image
+        let reversed = perceptual_hash(&self::gradient(640, 480, true));
+        assert_eq!(reversed, 0);
+        assert!(!is_duplicate(hash, reversed));
+        assert!(is_duplicate(0b1011, 0b0110));
+        assert!(!is_duplicate(0, 0b111111));
+    }
+
+    /// Build a horizontal grayscale gradient of `width` x `height`, getting darker from left to right unless `reversed`
+    fn gradient(width: u32, height: u32, reversed: bool) -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(width, height, |x, _| {
+            let value = (x * 255 / width) as u8;
+            let value = if reversed { value } else { 255 - value };
+            image::Rgb([value, value, value])
+        }))
+    }
+
+    /// Encode a black PNG image of `width` x `height`
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image::RgbImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+}
@@ -0,0 +1,107 @@
+//! # Mention
+//!
+//! This module cares of mentioning telegram users in messages
+
+use teloxide::types::{Message, MessageEntity, MessageEntityKind, MessageEntityRef, User, UserId};
+
+/// Build the entity mentioning `user` as `name`, where `name` follows `prefix` in the text
+pub fn entity(user: UserId, prefix: &str, name: &str) -> MessageEntity {
+    MessageEntity::text_link(user.url(), utf16_len(prefix), utf16_len(name))
+}
+
+/// A user mentioned in the text of a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mention<'a> {
+    /// A user mentioned by name, whose id is known
+    User(&'a User),
+    /// A user mentioned with their `@username`, whose id must be looked up
+    Username(&'a str),
+}
+
+/// Get the first user mentioned in the text of `message`, if any
+pub fn mentioned(message: &Message) -> Option<Mention<'_>> {
+    let text = message.text()?;
+    mentioned_in(text, message.entities().unwrap_or_default())
+}
+
+/// Get the author of the message `message` replies to, unless it's a bot
+pub fn replied_user(message: &Message) -> Option<&User> {
+    message
+        .reply_to_message()
+        .and_then(|reply| reply.from.as_ref())
+        .filter(|user| !user.is_bot)
+}
+
+/// Get the first user mentioned in `text`, formatted with `entities`
+fn mentioned_in<'a>(text: &'a str, entities: &'a [MessageEntity]) -> Option<Mention<'a>> {
+    MessageEntityRef::parse(text, entities)
+        .into_iter()
+        .find_map(|entity| match entity.kind() {
+            MessageEntityKind::TextMention { user } => Some(Mention::User(user)),
+            MessageEntityKind::Mention => {
+                Some(Mention::Username(entity.text().trim_start_matches('@')))
+            }
+            _ => None,
+        })
+}
+
+/// Telegram entities offsets and lengths are measured in UTF-16 code units
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_build_mention_entity() {
+        assert_eq!(
+            entity(UserId(42), "Buon compleanno ", "Mario"),
+            MessageEntity::text_link(UserId(42).url(), 16, 5)
+        );
+        assert_eq!(
+            entity(UserId(42), "🎂 Buon compleanno ", "Zia Maria"),
+            MessageEntity::text_link(UserId(42).url(), 19, 9)
+        );
+    }
+
+    #[test]
+    fn should_get_mentioned_user() {
+        let user = User {
+            id: UserId(42),
+            is_bot: false,
+            first_name: "Mario".to_string(),
+            last_name: None,
+            username: None,
+            language_code: None,
+            is_premium: false,
+            added_to_attachment_menu: false,
+        };
+        let text = "/compleanno Mario 12/03";
+        let entities = [
+            MessageEntity::new(MessageEntityKind::BotCommand, 0, 11),
+            MessageEntity::text_mention(user.clone(), 12, 5),
+        ];
+        assert_eq!(mentioned_in(text, &entities), Some(Mention::User(&user)));
+        let text = "/compleanno @mario_rossi 12/03";
+        let entities = [
+            MessageEntity::new(MessageEntityKind::BotCommand, 0, 11),
+            MessageEntity::new(MessageEntityKind::Mention, 12, 12),
+        ];
+        assert_eq!(
+            mentioned_in(text, &entities),
+            Some(Mention::Username("mario_rossi"))
+        );
+        assert_eq!(
+            mentioned_in(
+                "/compleanno Mario 12/03",
+                &[MessageEntity::new(MessageEntityKind::BotCommand, 0, 11)]
+            ),
+            None
+        );
+    }
+}
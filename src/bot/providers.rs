@@ -1,19 +1,155 @@
 use buongiornissimo_rs::{
-    Augurando, BuongiornissimoCaffe, BuongiornoImmagini, Greeting, Scrape, ScrapeResult,
-    TiCondivido,
+    Augurando, BuongiornissimoCaffe, BuongiornoImmagini, Greeting, Scrape, ScrapeError,
+    ScrapeResult, TiCondivido,
 };
+use thiserror::Error;
 use url::Url;
 
+use super::generator;
+use super::media::{self, MediaError, MediaKind, Validation};
+
+/// Scheme of the urls of the images submitted by the users, such as `telegram:<file_id>`
+const TELEGRAM_SCHEME: &str = "telegram";
+
+pub type ImagesResult<T> = Result<T, ImagesError>;
+
+/// The failure to get any image for a greeting, along with the provider which failed, if any
+#[derive(Debug, Error)]
+#[error("{error}")]
+pub struct ImagesError {
+    pub provider: Option<Providers>,
+    pub error: ScrapeError,
+}
+
+impl ImagesError {
+    /// Get the name of the provider which failed, as recorded in the delivery log
+    pub fn provider_name(&self) -> Option<String> {
+        self.provider.map(|provider| provider.name().to_string())
+    }
+}
+
+/// An image scraped for a greeting, along with the provider it was taken from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreetingImage {
+    pub provider: Providers,
+    pub url: Url,
+    /// The kind of media the url points to; greeting images may be GIFs or videos too
+    pub kind: MediaKind,
+    /// The perceptual hash of the image, used to recognize the same image scraped from different urls
+    pub hash: Option<u64>,
+}
+
+impl GreetingImage {
+    /// Create the greeting image scraped from `provider`, after checking the media at `url` can be sent
+    pub async fn validate(
+        provider: Providers,
+        url: Url,
+        validation: Validation,
+    ) -> Result<Self, MediaError> {
+        let info = media::validate(&url, validation).await?;
+        Ok(Self {
+            provider,
+            url,
+            kind: info.kind,
+            hash: info.hash,
+        })
+    }
+
+    /// Create the greeting image generated locally at the generator `url`
+    pub fn generated(url: Url) -> Self {
+        Self {
+            provider: Providers::Generator,
+            url,
+            kind: MediaKind::Image,
+            hash: None,
+        }
+    }
+
+    /// Create the greeting image submitted by a user and stored on telegram as `file_id`
+    pub fn submitted(file_id: &str) -> Option<Self> {
+        let url = Url::parse(&format!("{TELEGRAM_SCHEME}:{file_id}")).ok()?;
+        Some(Self {
+            provider: Providers::Community,
+            url,
+            kind: MediaKind::Image,
+            hash: None,
+        })
+    }
+
+    /// Get the telegram file id of the image, if it has been submitted by a user
+    pub fn file_id(&self) -> Option<&str> {
+        (self.url.scheme() == TELEGRAM_SCHEME).then(|| self.url.path())
+    }
+}
+
+/// Picks the greeting images to send, discarding the images near-identical to the ones already picked or recently sent
+#[derive(Debug)]
+pub struct ImagePicker {
+    amount: usize,
+    /// Hashes of the images picked or recently sent
+    seen: Vec<u64>,
+    images: Vec<GreetingImage>,
+    /// Duplicates discarded, used if there aren't enough different images
+    duplicates: Vec<GreetingImage>,
+}
+
+impl ImagePicker {
+    /// Create a picker for `amount` images, which are not duplicates of the `recent` hashes
+    pub fn new(amount: usize, recent: Vec<u64>) -> Self {
+        Self {
+            amount,
+            seen: recent,
+            images: Vec::with_capacity(amount),
+            duplicates: Vec::new(),
+        }
+    }
+
+    /// Whether enough images have been picked
+    pub fn is_full(&self) -> bool {
+        self.images.len() >= self.amount
+    }
+
+    /// Pick `image`, unless it is a duplicate; returns whether the image has been picked
+    pub fn pick(&mut self, image: GreetingImage) -> bool {
+        if let Some(hash) = image.hash {
+            if self
+                .seen
+                .iter()
+                .any(|seen| media::is_duplicate(*seen, hash))
+            {
+                self.duplicates.push(image);
+                return false;
+            }
+            self.seen.push(hash);
+        }
+        self.images.push(image);
+        true
+    }
+
+    /// Get the picked images; if there aren't enough, the duplicates fill the gap
+    pub fn finish(mut self) -> Vec<GreetingImage> {
+        let missing = self.amount.saturating_sub(self.images.len());
+        self.images
+            .extend(self.duplicates.into_iter().take(missing));
+        self.images
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Providers {
     BuongiornissimoCaffe,
     BuongiornoImmagini,
     Augurando,
     TiCondivido,
+    /// Images submitted by the users and approved by the admins
+    Community,
+    /// Images generated locally, used when the other providers fail
+    Generator,
 }
 
 impl Providers {
-    /// Get all providers
+    /// Get all the providers to scrape; the community images are read from the repository
+    /// and the generator is the last resort, so they are not included
     pub fn all() -> &'static [Providers] {
         &[
             Providers::BuongiornissimoCaffe,
@@ -23,14 +159,126 @@ impl Providers {
         ]
     }
 
+    /// Get provider name
+    pub fn name(self) -> &'static str {
+        match self {
+            Providers::BuongiornissimoCaffe => "buongiornissimo-caffe",
+            Providers::BuongiornoImmagini => "buongiorno-immagini",
+            Providers::Augurando => "augurando",
+            Providers::TiCondivido => "ticondivido",
+            Providers::Community => "community",
+            Providers::Generator => "generator",
+        }
+    }
+
+    /// Get the provider from its [`Providers::name`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .chain(&[Providers::Community, Providers::Generator])
+            .copied()
+            .find(|provider| provider.name() == name)
+    }
+
     pub async fn scrape(self, greeting: Greeting) -> ScrapeResult<Vec<Url>> {
         let urls = match self {
             Providers::BuongiornissimoCaffe => BuongiornissimoCaffe.scrape(greeting).await,
             Providers::Augurando => Augurando.scrape(greeting).await,
             Providers::BuongiornoImmagini => BuongiornoImmagini.scrape(greeting).await,
             Providers::TiCondivido => TiCondivido.scrape(greeting).await,
+            Providers::Community => Ok(Vec::new()),
+            Providers::Generator => Ok(generator::compositions(greeting)),
         }?;
 
         Ok(urls)
     }
 }
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn image(name: &str, hash: Option<u64>) -> GreetingImage {
+        GreetingImage {
+            provider: Providers::Augurando,
+            url: Url::parse(&format!("https://example.com/{name}.jpg")).unwrap(),
+            kind: MediaKind::Image,
+            hash,
+        }
+    }
+
+    #[test]
+    fn should_pick_different_images() {
+        let mut picker = ImagePicker::new(3, vec![0xff00]);
+        // recently sent
+        assert!(!picker.pick(image("sent", Some(0xff01))));
+        assert!(picker.pick(image("caffe", Some(0x00ff))));
+        // same image from another provider
+        assert!(!picker.pick(image("caffe-copy", Some(0x00fe))));
+        // videos have no hash
+        assert!(picker.pick(image("video", None)));
+        assert!(!picker.is_full());
+        assert!(picker.pick(image("notte", Some(u64::MAX))));
+        assert!(picker.is_full());
+        let urls: Vec<String> = picker
+            .finish()
+            .into_iter()
+            .map(|image| image.url.to_string())
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/caffe.jpg",
+                "https://example.com/video.jpg",
+                "https://example.com/notte.jpg"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_name_failed_provider() {
+        let err = ImagesError {
+            provider: Some(Providers::Augurando),
+            error: ScrapeError::NoImages,
+        };
+        assert_eq!(err.provider_name().as_deref(), Some("augurando"));
+        let err = ImagesError {
+            provider: None,
+            error: ScrapeError::NoImages,
+        };
+        assert_eq!(err.provider_name(), None);
+    }
+
+    #[test]
+    fn should_get_provider_from_name() {
+        for provider in [
+            Providers::Augurando,
+            Providers::Community,
+            Providers::Generator,
+        ] {
+            assert_eq!(Providers::from_name(provider.name()), Some(provider));
+        }
+        assert_eq!(Providers::from_name("asdrubale"), None);
+    }
+
+    #[test]
+    fn should_get_file_id_of_submitted_images() {
+        let submitted = GreetingImage::submitted("AgACAgQAAx-kB_9").unwrap();
+        assert_eq!(submitted.provider, Providers::Community);
+        assert_eq!(submitted.file_id(), Some("AgACAgQAAx-kB_9"));
+        assert_eq!(image("caffe", None).file_id(), None);
+    }
+
+    #[test]
+    fn should_fill_with_duplicates() {
+        let mut picker = ImagePicker::new(3, vec![0xff00]);
+        assert!(!picker.pick(image("sent", Some(0xff00))));
+        assert!(picker.pick(image("caffe", Some(0x00ff))));
+        assert!(!picker.pick(image("caffe-copy", Some(0x00ff))));
+        assert_eq!(picker.finish().len(), 3);
+        assert!(ImagePicker::new(2, Vec::new()).finish().is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+//! # Rating
+//!
+//! This module weights the selection of the greeting images with the votes they got in the chats.
+//! Images without votes keep a neutral weight and disliked images a minimum one, so that they still show up
+
+use std::collections::HashMap;
+
+use rand::RngExt as _;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+/// Weight of the images without votes
+const NEUTRAL_WEIGHT: f64 = 1.0;
+/// Weight added or removed by each vote
+const VOTE_WEIGHT: f64 = 0.5;
+/// Minimum weight, so that disliked images are still picked sometimes
+const MIN_WEIGHT: f64 = 0.2;
+/// Maximum weight, so that liked images don't hide the new ones
+const MAX_WEIGHT: f64 = 5.0;
+
+/// Get the key identifying the image at `url` in the votes; it's short enough to fit in the callback data
+pub fn image_key(url: &Url) -> u64 {
+    let digest = Sha256::digest(url.as_str().as_bytes());
+    u64::from_be_bytes(digest[..8].try_into().expect("digest is too short"))
+}
+
+/// Get the selection weight of the image at `url`, according to its score in `scores`
+pub fn weight(scores: &HashMap<u64, i64>, url: &Url) -> f64 {
+    let score = scores.get(&image_key(url)).copied().unwrap_or_default();
+    (NEUTRAL_WEIGHT + score as f64 * VOTE_WEIGHT).clamp(MIN_WEIGHT, MAX_WEIGHT)
+}
+
+/// Shuffle `items` so that the items with a greater `weight` tend to come first
+pub fn weighted_shuffle<T>(items: Vec<T>, weight: impl Fn(&T) -> f64) -> Vec<T> {
+    let mut rng = rand::rng();
+    // weighted random sampling by Efraimidis and Spirakis: each item is ranked by u^(1/w)
+    let mut ranked: Vec<(f64, T)> = items
+        .into_iter()
+        .map(|item| {
+            let rank = rng.random::<f64>().powf(1.0 / weight(&item));
+            (rank, item)
+        })
+        .collect();
+    ranked.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    ranked.into_iter().map(|(_, item)| item).collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn url(name: &str) -> Url {
+        Url::parse(&format!("https://example.com/{name}.jpg")).unwrap()
+    }
+
+    #[test]
+    fn should_get_image_key() {
+        assert_eq!(image_key(&url("caffe")), image_key(&url("caffe")));
+        assert_ne!(image_key(&url("caffe")), image_key(&url("notte")));
+    }
+
+    #[test]
+    fn should_weight_images_by_score() {
+        let scores = HashMap::from([
+            (image_key(&url("liked")), 2),
+            (image_key(&url("loved")), 100),
+            (image_key(&url("hated")), -100),
+        ]);
+        assert_eq!(weight(&scores, &url("new")), NEUTRAL_WEIGHT);
+        assert_eq!(weight(&scores, &url("liked")), 2.0);
+        assert_eq!(weight(&scores, &url("loved")), MAX_WEIGHT);
+        assert_eq!(weight(&scores, &url("hated")), MIN_WEIGHT);
+    }
+
+    #[test]
+    fn should_put_heavier_items_first() {
+        let mut firsts = 0;
+        for _ in 0..1000 {
+            let items = weighted_shuffle(vec![MIN_WEIGHT, MAX_WEIGHT], |weight| *weight);
+            assert_eq!(items.len(), 2);
+            if items[0] == MAX_WEIGHT {
+                firsts += 1;
+            }
+        }
+        // the heavier item comes first about 96% of the times, but the lighter one still shows up
+        assert!(firsts > 900 && firsts < 1000, "{firsts}");
+    }
+}
@@ -2,14 +2,112 @@
 //!
 //! This module contains the interface to the bot repository
 
-use chrono::NaiveDate;
-use teloxide::types::ChatId;
-use tracing::debug;
+use std::collections::HashMap;
+use std::fmt;
 
+use buongiornissimo_rs::Greeting;
+use chrono::{Duration, Utc};
+use teloxide::types::{ChatId, User, UserId};
+use tracing::{debug, error};
+use url::Url;
+
+use super::answer::Markup;
+use super::caption::Template;
+use super::greeting;
+use super::i18n::{Key, Language, UserError};
+use super::media::MediaKind;
+use super::providers::{GreetingImage, Providers};
+use super::watermark::{self, Mark, Position, Watermark};
 use crate::repository::SqliteDb;
 use crate::repository::birthday::Birthday;
 use crate::repository::chat::Chat;
+use crate::repository::daily_greeting::DailyGreeting;
+use crate::repository::delivery::Delivery;
+use crate::repository::favourite::Favourite;
+use crate::repository::image_hash::ImageHash;
+use crate::repository::known_user::KnownUser;
+use crate::repository::setting::Setting;
+use crate::repository::submission::{Submission, SubmissionStatus};
+use crate::repository::vote::Vote;
+use crate::utils::date::PartialDate;
+
+/// Amount of days to report in delivery statistics
+const STATS_DAYS: u64 = 7;
+/// Amount of chats to report in delivery statistics
+const STATS_TOP_CHATS: i64 = 5;
+/// Amount of days an image sent to a chat is considered recent, so it is not sent again
+const RECENT_IMAGES_DAYS: i64 = 7;
+/// Amount of favourites listed in a page
+pub const FAVOURITES_PAGE_SIZE: usize = 5;
+/// Setting key of the chat language
+const LANGUAGE_SETTING: &str = "language";
+/// Setting key of the amount of images sent in the automatic messages
+const ALBUM_SIZE_SETTING: &str = "album_size";
+/// Prefix of the setting keys of the caption templates, followed by the greeting keyword
+const CAPTION_SETTING_PREFIX: &str = "caption.";
+/// Setting key of the text stamped on the images
+const WATERMARK_TEXT_SETTING: &str = "watermark.text";
+/// Setting key of the url of the logo stamped on the images
+const WATERMARK_LOGO_SETTING: &str = "watermark.logo";
+/// Setting key of the watermark position
+const WATERMARK_POSITION_SETTING: &str = "watermark.position";
+/// Setting key of the watermark opacity
+const WATERMARK_OPACITY_SETTING: &str = "watermark.opacity";
+
+/// Aggregated statistics about delivered messages
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct DeliveryStats {
+    /// Deliveries per day, most recent first
+    pub per_day: Vec<(String, i64)>,
+    /// Total and failed deliveries per provider
+    pub per_provider: Vec<(String, i64, i64)>,
+    /// Chats with the most successful deliveries
+    pub most_active_chats: Vec<(ChatId, i64)>,
+}
+
+impl DeliveryStats {
+    /// Render the statistics as a report in `language`, written in `markup`
+    pub fn render(&self, language: Language, markup: Markup) -> String {
+        let title = |key: Key| markup.bold(&markup.escape(language.text(key)));
+        let line = |text: String| format!("  {}\n", markup.escape(&text));
+        let mut report = format!("{}\n", title(Key::StatsTitle));
+        report.push_str(&format!("\n{}\n", title(Key::StatsPerDay)));
+        if self.per_day.is_empty() {
+            report.push_str(&line(language.text(Key::StatsNoDeliveries).to_string()));
+        }
+        for (day, count) in self.per_day.iter() {
+            report.push_str(&line(format!("{day}: {count}")));
+        }
+        report.push_str(&format!("\n{}\n", title(Key::StatsPerProvider)));
+        if self.per_provider.is_empty() {
+            report.push_str(&line(language.text(Key::StatsNoProviders).to_string()));
+        }
+        for (provider, total, failed) in self.per_provider.iter() {
+            let rate = if *total > 0 {
+                (*failed as f64 / *total as f64) * 100.0
+            } else {
+                0.0
+            };
+            report.push_str(&line(format!("{provider}: {failed}/{total} ({rate:.1}%)")));
+        }
+        report.push_str(&format!("\n{}\n", title(Key::StatsMostActiveChats)));
+        if self.most_active_chats.is_empty() {
+            report.push_str(&line(language.text(Key::StatsNoChats).to_string()));
+        }
+        for (chat, count) in self.most_active_chats.iter() {
+            report.push_str(&line(format!("{chat}: {count}")));
+        }
+        report
+    }
+}
 
+impl fmt::Display for DeliveryStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(Language::default(), Markup::Plain))
+    }
+}
+
+#[derive(Clone)]
 pub struct Repository {
     db: SqliteDb,
 }
@@ -20,10 +118,15 @@ impl Repository {
         Self { db }
     }
 
+    /// Close the database connection
+    pub async fn close(&self) {
+        self.db.close().await;
+    }
+
     /// Insert a chat to database
     pub async fn insert_chat(&self, chat: ChatId) -> anyhow::Result<()> {
         if self.is_subscribed(&chat).await? {
-            anyhow::bail!("Sei già iscritto ai messaggi automatici.")
+            return Err(UserError(Key::AlreadySubscribed).into());
         }
         Chat::new(chat)
             .insert(self.db.pool())
@@ -74,17 +177,37 @@ impl Repository {
         &self,
         chat: ChatId,
         name: String,
-        date: NaiveDate,
+        date: PartialDate,
+        user: Option<UserId>,
     ) -> anyhow::Result<()> {
         if self.birthday_exists(&chat, &name, date).await? {
-            anyhow::bail!("Questo compleanno è già presente registrato")
+            return Err(UserError(Key::BirthdayAlreadyExists).into());
         }
-        Birthday::new(chat, name, date)
+        let mut birthday = Birthday::new(chat, name, date);
+        if let Some(user) = user {
+            birthday = birthday.with_user(user);
+        }
+        birthday
             .insert(self.db.pool())
             .await
             .map_err(|e| anyhow::anyhow!("failed to insert birthdate into the database: {}", e))
     }
 
+    /// Set the birthday of `user` in `chat`, replacing the previous one if any
+    pub async fn set_user_birthday(
+        &self,
+        chat: ChatId,
+        user: UserId,
+        name: String,
+        date: PartialDate,
+    ) -> anyhow::Result<()> {
+        Birthday::new(chat, name, date)
+            .with_user(user)
+            .upsert(self.db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to set user birthday into the database: {}", e))
+    }
+
     /// Delete birthdays by chat from database
     pub async fn delete_birthday_by_chat(&self, chat: ChatId) -> anyhow::Result<()> {
         Birthday::delete_by_chat(self.db.pool(), chat)
@@ -92,8 +215,10 @@ impl Repository {
             .map_err(|e| anyhow::anyhow!("failed to delete birthday from the database: {}", e))
     }
 
-    /// Get all birthdays
-    pub async fn get_birthdays(&self) -> anyhow::Result<Vec<(ChatId, String, NaiveDate)>> {
+    /// Get all birthdays, with the user celebrating them if known
+    pub async fn get_birthdays(
+        &self,
+    ) -> anyhow::Result<Vec<(ChatId, String, PartialDate, Option<UserId>)>> {
         Birthday::get_all(self.db.pool())
             .await
             .map_err(|e| anyhow::anyhow!("failed to collect birthdays: {}", e))
@@ -110,20 +235,502 @@ impl Repository {
                                 .map(|x| x.to_rfc3339())
                                 .unwrap_or_else(|_| String::from("date error"))
                         );
-                        (x.chat(), x.name().to_string(), date)
+                        (x.chat(), x.name().to_string(), date, x.user())
                     })
                     .collect()
             })
     }
 
+    // -- settings
+
+    /// Get the language chosen for `chat`, if any
+    pub async fn get_language(&self, chat: ChatId) -> anyhow::Result<Option<Language>> {
+        Setting::get(self.db.pool(), chat, LANGUAGE_SETTING)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to get chat language: {}", e))
+            .map(|language| language.as_deref().and_then(Language::parse))
+    }
+
+    /// Set the language of `chat`
+    pub async fn set_language(&self, chat: ChatId, language: Language) -> anyhow::Result<()> {
+        Setting::new(chat, LANGUAGE_SETTING, language.code())
+            .upsert(self.db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to set chat language: {}", e))
+    }
+
+    /// Get the amount of images to send to `chat` in the automatic messages, if chosen
+    pub async fn get_album_size(&self, chat: ChatId) -> anyhow::Result<Option<usize>> {
+        Setting::get(self.db.pool(), chat, ALBUM_SIZE_SETTING)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to get chat album size: {}", e))
+            .map(|size| size.and_then(|size| size.parse().ok()))
+    }
+
+    /// Set the amount of images to send to `chat` in the automatic messages
+    pub async fn set_album_size(&self, chat: ChatId, size: usize) -> anyhow::Result<()> {
+        Setting::new(chat, ALBUM_SIZE_SETTING, size)
+            .upsert(self.db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to set chat album size: {}", e))
+    }
+
+    /// Get the caption template chosen for `greeting` in `chat`, if any
+    pub async fn get_caption(
+        &self,
+        chat: ChatId,
+        greeting: Greeting,
+    ) -> anyhow::Result<Option<Template>> {
+        let template = Setting::get(self.db.pool(), chat, &Self::caption_setting(greeting))
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to get chat caption: {}", e))?;
+        Ok(
+            template.and_then(|template| match Template::parse(&template) {
+                Ok(template) => Some(template),
+                Err(err) => {
+                    error!("ignoring bad caption template {template:?} of {chat}: {err}");
+                    None
+                }
+            }),
+        )
+    }
+
+    /// Set the caption template of `greeting` in `chat`
+    pub async fn set_caption(
+        &self,
+        chat: ChatId,
+        greeting: Greeting,
+        template: &Template,
+    ) -> anyhow::Result<()> {
+        Setting::new(chat, &Self::caption_setting(greeting), template)
+            .upsert(self.db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to set chat caption: {}", e))
+    }
+
+    /// Delete the caption template of `greeting` in `chat`
+    pub async fn delete_caption(&self, chat: ChatId, greeting: Greeting) -> anyhow::Result<()> {
+        Setting::delete(self.db.pool(), chat, &Self::caption_setting(greeting))
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to delete chat caption: {}", e))
+    }
+
+    /// Get the setting key of the caption template of `greeting`
+    fn caption_setting(greeting: Greeting) -> String {
+        format!("{CAPTION_SETTING_PREFIX}{}", greeting::keyword(greeting))
+    }
+
+    /// Get the watermark chosen for `chat`, if a text or a logo is set
+    pub async fn get_watermark(&self, chat: ChatId) -> anyhow::Result<Option<Watermark>> {
+        let text = self
+            .get_watermark_setting(chat, WATERMARK_TEXT_SETTING)
+            .await?;
+        let logo = self
+            .get_watermark_setting(chat, WATERMARK_LOGO_SETTING)
+            .await?;
+        let mark = match (text, logo) {
+            (Some(text), _) => Mark::Text(text),
+            (None, Some(logo)) => match Url::parse(&logo) {
+                Ok(url) => Mark::Logo(url),
+                Err(err) => {
+                    error!("ignoring bad watermark logo {logo:?} of {chat}: {err}");
+                    return Ok(None);
+                }
+            },
+            (None, None) => return Ok(None),
+        };
+        let position = self
+            .get_watermark_setting(chat, WATERMARK_POSITION_SETTING)
+            .await?
+            .as_deref()
+            .and_then(Position::parse)
+            .unwrap_or_default();
+        let opacity = self
+            .get_watermark_setting(chat, WATERMARK_OPACITY_SETTING)
+            .await?
+            .and_then(|opacity| opacity.parse::<u8>().ok())
+            .map(|opacity| opacity.min(100))
+            .unwrap_or(watermark::DEFAULT_OPACITY);
+
+        Ok(Some(Watermark {
+            mark,
+            position,
+            opacity,
+        }))
+    }
+
+    /// Set the text or the logo stamped on the images sent to `chat`, replacing the other one
+    pub async fn set_watermark_mark(&self, chat: ChatId, mark: &Mark) -> anyhow::Result<()> {
+        let (key, value, other) = match mark {
+            Mark::Text(text) => (WATERMARK_TEXT_SETTING, text.clone(), WATERMARK_LOGO_SETTING),
+            Mark::Logo(url) => (
+                WATERMARK_LOGO_SETTING,
+                url.to_string(),
+                WATERMARK_TEXT_SETTING,
+            ),
+        };
+        Setting::new(chat, key, value)
+            .upsert(self.db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to set chat watermark: {}", e))?;
+        Setting::delete(self.db.pool(), chat, other)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to set chat watermark: {}", e))
+    }
+
+    /// Set the position of the watermark of `chat`
+    pub async fn set_watermark_position(
+        &self,
+        chat: ChatId,
+        position: Position,
+    ) -> anyhow::Result<()> {
+        Setting::new(
+            chat,
+            WATERMARK_POSITION_SETTING,
+            position.name(Language::English),
+        )
+        .upsert(self.db.pool())
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to set chat watermark position: {}", e))
+    }
+
+    /// Set the opacity, in percent, of the watermark of `chat`
+    pub async fn set_watermark_opacity(&self, chat: ChatId, opacity: u8) -> anyhow::Result<()> {
+        Setting::new(chat, WATERMARK_OPACITY_SETTING, opacity.min(100))
+            .upsert(self.db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to set chat watermark opacity: {}", e))
+    }
+
+    /// Delete the watermark of `chat`, with its position and opacity
+    pub async fn delete_watermark(&self, chat: ChatId) -> anyhow::Result<()> {
+        for key in [
+            WATERMARK_TEXT_SETTING,
+            WATERMARK_LOGO_SETTING,
+            WATERMARK_POSITION_SETTING,
+            WATERMARK_OPACITY_SETTING,
+        ] {
+            Setting::delete(self.db.pool(), chat, key)
+                .await
+                .map_err(|e| anyhow::anyhow!("failed to delete chat watermark: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_watermark_setting(
+        &self,
+        chat: ChatId,
+        key: &str,
+    ) -> anyhow::Result<Option<String>> {
+        Setting::get(self.db.pool(), chat, key)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to get chat watermark: {}", e))
+    }
+
+    // -- users
+
+    /// Delete every data about `user`: the birthdays of the user in any chat
+    /// and everything related to the private chat with the user
+    pub async fn forget_user(&self, user: UserId) -> anyhow::Result<()> {
+        let private_chat = ChatId::from(user);
+        Birthday::delete_by_user(self.db.pool(), user)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to delete user birthdays: {}", e))?;
+        self.delete_birthday_by_chat(private_chat).await?;
+        self.delete_chat(private_chat).await?;
+        Setting::delete_by_chat(self.db.pool(), private_chat)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to delete user settings: {}", e))?;
+        Submission::delete_by_user(self.db.pool(), user)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to delete user submissions: {}", e))?;
+        Favourite::delete_by_user(self.db.pool(), user)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to delete user favourites: {}", e))?;
+        Vote::delete_by_user(self.db.pool(), user)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to delete user votes: {}", e))?;
+        KnownUser::delete_by_user(self.db.pool(), user)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to delete known user: {}", e))?;
+        Delivery::delete_by_chat(self.db.pool(), private_chat)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to delete user deliveries: {}", e))
+    }
+
+    // -- known users
+
+    /// Remember the username of `user`, so that the mentions of the user can be resolved
+    pub async fn remember_user(&self, user: &User) -> anyhow::Result<()> {
+        let Some(username) = user.username.as_deref() else {
+            return Ok(());
+        };
+        KnownUser::new(user.id, username, user.full_name())
+            .upsert(self.db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to remember user: {}", e))
+    }
+
+    /// Get the id and the full name of the user with `username`, if known
+    pub async fn get_user_by_username(
+        &self,
+        username: &str,
+    ) -> anyhow::Result<Option<(UserId, String)>> {
+        KnownUser::get_by_username(self.db.pool(), username)
+            .await
+            .map(|user| user.map(|user| (user.user_id(), user.name().to_string())))
+            .map_err(|e| anyhow::anyhow!("failed to get user by username: {}", e))
+    }
+
+    // -- submissions
+
+    /// Insert the image `file_id` proposed by `user` in `chat` for `greeting`, waiting for a review; returns the submission id
+    pub async fn insert_submission(
+        &self,
+        chat: ChatId,
+        user: UserId,
+        greeting: Greeting,
+        file_id: &str,
+    ) -> anyhow::Result<i64> {
+        Submission::new(chat, user, greeting::keyword(greeting), file_id)
+            .insert(self.db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to insert submission into the database: {}", e))
+    }
+
+    /// Approve or reject the submission `id`; returns the submission, unless it doesn't exist or has already been reviewed
+    pub async fn review_submission(
+        &self,
+        id: i64,
+        approved: bool,
+    ) -> anyhow::Result<Option<Submission>> {
+        let status = if approved {
+            SubmissionStatus::Approved
+        } else {
+            SubmissionStatus::Rejected
+        };
+        let reviewed = Submission::review(self.db.pool(), id, status)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to review submission: {}", e))?;
+        if !reviewed {
+            return Ok(None);
+        }
+        Submission::get(self.db.pool(), id)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to get submission: {}", e))
+    }
+
+    /// Get the file ids of the approved images proposed for `greeting`
+    pub async fn get_community_images(&self, greeting: Greeting) -> anyhow::Result<Vec<String>> {
+        Submission::get_approved(self.db.pool(), greeting::keyword(greeting))
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to get approved submissions: {}", e))
+    }
+
+    // -- favourites
+
+    /// Save the telegram file `file_id`, a media of `kind` sent for `greeting`, among the favourites of `user`;
+    /// returns whether it wasn't saved yet
+    pub async fn insert_favourite(
+        &self,
+        user: UserId,
+        greeting: Greeting,
+        file_id: &str,
+        kind: MediaKind,
+    ) -> anyhow::Result<bool> {
+        Favourite::new(user, greeting::keyword(greeting), file_id, kind.name())
+            .insert(self.db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to insert favourite into the database: {}", e))
+    }
+
+    /// Get the favourites of `user` in `page`, most recent first, along with the amount of pages
+    pub async fn get_favourites(
+        &self,
+        user: UserId,
+        page: usize,
+    ) -> anyhow::Result<(Vec<Favourite>, usize)> {
+        let count = Favourite::count_by_user(self.db.pool(), user)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to count favourites: {}", e))?;
+        let pages = (count as usize).div_ceil(FAVOURITES_PAGE_SIZE);
+        let favourites = Favourite::get_by_user(
+            self.db.pool(),
+            user,
+            FAVOURITES_PAGE_SIZE as i64,
+            (page * FAVOURITES_PAGE_SIZE) as i64,
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get favourites: {}", e))?;
+        Ok((favourites, pages))
+    }
+
+    /// Get the favourite `id` of `user`, if any
+    pub async fn get_favourite(&self, user: UserId, id: i64) -> anyhow::Result<Option<Favourite>> {
+        Favourite::get(self.db.pool(), user, id)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to get favourite: {}", e))
+    }
+
+    // -- votes
+
+    /// Set the vote of `user` in `chat` for the image with key `image`, replacing the previous one
+    pub async fn vote_image(
+        &self,
+        chat: ChatId,
+        user: UserId,
+        image: u64,
+        up: bool,
+    ) -> anyhow::Result<()> {
+        Vote::new(chat, user, image, up)
+            .upsert(self.db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to set vote: {}", e))
+    }
+
+    /// Get the score of every voted image, by image key
+    pub async fn get_image_scores(&self) -> anyhow::Result<HashMap<u64, i64>> {
+        Vote::get_scores(self.db.pool())
+            .await
+            .map(|scores| scores.into_iter().collect())
+            .map_err(|e| anyhow::anyhow!("failed to get image scores: {}", e))
+    }
+
+    // -- greeting of the day
+
+    /// Get the greeting of the day stored for today, which is decided by the first call of the day
+    pub async fn get_greeting_of_the_day(&self) -> anyhow::Result<Greeting> {
+        let today = greeting::today();
+        DailyGreeting::get_or_insert(self.db.pool(), today, greeting::random_weekday())
+            .await
+            .map(|daily| greeting::of_date(today, daily.use_weekday()))
+            .map_err(|e| anyhow::anyhow!("failed to get greeting of the day: {}", e))
+    }
+
+    /// Get the image of the greeting of the day, if it has already been chosen today
+    pub async fn get_image_of_the_day(&self) -> anyhow::Result<Option<GreetingImage>> {
+        let daily = DailyGreeting::get_or_insert(
+            self.db.pool(),
+            greeting::today(),
+            greeting::random_weekday(),
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get image of the day: {}", e))?;
+        let Some((provider, url, kind)) = daily.image() else {
+            return Ok(None);
+        };
+        match (
+            Providers::from_name(provider),
+            Url::parse(url),
+            MediaKind::from_name(kind),
+        ) {
+            (Some(provider), Ok(url), Some(kind)) => Ok(Some(GreetingImage {
+                provider,
+                url,
+                kind,
+                hash: daily.hash(),
+            })),
+            _ => anyhow::bail!("bad image of the day {} from {} ({})", url, provider, kind),
+        }
+    }
+
+    /// Set `image` as the image of the greeting of the day, unless another one has already been chosen today;
+    /// returns the image of the day.
+    ///
+    /// Generated images are only a fallback and are never stored, so that a scraped image can still be chosen later
+    pub async fn set_image_of_the_day(
+        &self,
+        image: &GreetingImage,
+    ) -> anyhow::Result<Option<GreetingImage>> {
+        if image.provider == Providers::Generator {
+            return self.get_image_of_the_day().await;
+        }
+        DailyGreeting::set_image(
+            self.db.pool(),
+            greeting::today(),
+            image.provider.name(),
+            image.url.as_str(),
+            image.kind.name(),
+            image.hash,
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to set image of the day: {}", e))?;
+        self.get_image_of_the_day().await
+    }
+
+    // -- deliveries
+
+    /// Insert a delivery into the delivery log
+    pub async fn insert_delivery(&self, delivery: Delivery) -> anyhow::Result<()> {
+        delivery
+            .insert(self.db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to insert delivery into the database: {}", e))
+    }
+
+    // -- image hashes
+
+    /// Store the perceptual `hash` of the image at `url` scraped from `provider`
+    pub async fn insert_image_hash(
+        &self,
+        url: &str,
+        provider: &str,
+        hash: u64,
+    ) -> anyhow::Result<()> {
+        ImageHash::new(url, provider, hash)
+            .upsert(self.db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to insert image hash into the database: {}", e))
+    }
+
+    /// Record that the image at `url` has just been sent
+    pub async fn mark_image_sent(&self, url: &str) -> anyhow::Result<()> {
+        ImageHash::mark_sent(self.db.pool(), url, Utc::now())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to mark image as sent: {}", e))
+    }
+
+    /// Get the perceptual hashes of the images sent in the last days
+    pub async fn get_recent_image_hashes(&self) -> anyhow::Result<Vec<u64>> {
+        ImageHash::get_sent_since(
+            self.db.pool(),
+            Utc::now() - Duration::days(RECENT_IMAGES_DAYS),
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to collect recent image hashes: {}", e))
+    }
+
+    /// Get aggregated delivery statistics
+    pub async fn get_delivery_stats(&self) -> anyhow::Result<DeliveryStats> {
+        let per_day = Delivery::count_per_day(self.db.pool(), Utc::now().date_naive(), STATS_DAYS)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to collect deliveries per day: {}", e))?;
+        let per_provider = Delivery::count_per_provider(self.db.pool())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to collect deliveries per provider: {}", e))?;
+        let most_active_chats = Delivery::most_active_chats(self.db.pool(), STATS_TOP_CHATS)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to collect most active chats: {}", e))?
+            .into_iter()
+            .map(|(chat, count)| (ChatId(chat), count))
+            .collect();
+
+        Ok(DeliveryStats {
+            per_day,
+            per_provider,
+            most_active_chats,
+        })
+    }
+
     /// Check whether a birthday exists
     async fn birthday_exists(
         &self,
         chat_id: &ChatId,
         name: &str,
-        date: NaiveDate,
+        date: PartialDate,
     ) -> anyhow::Result<bool> {
-        Birthday::exists(self.db.pool(), chat_id.0, name, &date.to_string())
+        Birthday::exists(self.db.pool(), chat_id.0, name, &date.to_iso_string())
             .await
             .map_err(|e| anyhow::anyhow!("failed to check birthday existence: {}", e))
     }
@@ -132,6 +739,7 @@ impl Repository {
 #[cfg(test)]
 mod test {
 
+    use chrono::NaiveDate;
     use pretty_assertions::assert_eq;
     use tempfile::NamedTempFile;
 
@@ -167,7 +775,8 @@ mod test {
                 .insert_birthday(
                     ChatId(1),
                     "Christian".to_string(),
-                    NaiveDate::from_ymd_opt(1997, 5, 30).unwrap()
+                    NaiveDate::from_ymd_opt(1997, 5, 30).unwrap().into(),
+                    None
                 )
                 .await
                 .is_ok()
@@ -177,7 +786,8 @@ mod test {
                 .insert_birthday(
                     ChatId(1),
                     "Chiara".to_string(),
-                    NaiveDate::from_ymd_opt(1999, 6, 24).unwrap()
+                    NaiveDate::from_ymd_opt(1999, 6, 24).unwrap().into(),
+                    None
                 )
                 .await
                 .is_ok()
@@ -188,15 +798,528 @@ mod test {
                 .insert_birthday(
                     ChatId(1),
                     "Chiara".to_string(),
-                    NaiveDate::from_ymd_opt(1999, 6, 24).unwrap()
+                    NaiveDate::from_ymd_opt(1999, 6, 24).unwrap().into(),
+                    None
                 )
                 .await
                 .is_err()
         );
+        // without year
+        assert!(
+            repository
+                .insert_birthday(
+                    ChatId(1),
+                    "Chiara".to_string(),
+                    PartialDate::new(24, 6, None).unwrap(),
+                    Some(UserId(42))
+                )
+                .await
+                .is_ok()
+        );
         // get birthdays
-        assert_eq!(repository.get_birthdays().await.unwrap().len(), 2);
+        let birthdays = repository.get_birthdays().await.unwrap();
+        assert_eq!(birthdays.len(), 3);
+        assert_eq!(birthdays[2].3, Some(UserId(42)));
         // delete
         assert!(repository.delete_birthday_by_chat(ChatId(1)).await.is_ok());
         assert!(repository.get_birthdays().await.unwrap().is_empty());
     }
+
+    #[tokio::test]
+    async fn should_set_user_birthday() {
+        let (repository, _database) = setup_repository().await;
+        for date in [
+            PartialDate::new(24, 6, None).unwrap(),
+            PartialDate::new(24, 6, Some(1999)).unwrap(),
+        ] {
+            assert!(
+                repository
+                    .set_user_birthday(ChatId(-1), UserId(42), "Chiara".to_string(), date)
+                    .await
+                    .is_ok()
+            );
+        }
+        assert_eq!(
+            repository.get_birthdays().await.unwrap(),
+            vec![(
+                ChatId(-1),
+                "Chiara".to_string(),
+                PartialDate::new(24, 6, Some(1999)).unwrap(),
+                Some(UserId(42))
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_handle_language() {
+        let (repository, _database) = setup_repository().await;
+        assert_eq!(repository.get_language(ChatId(1)).await.unwrap(), None);
+        assert!(
+            repository
+                .set_language(ChatId(1), Language::Venetian)
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            repository.get_language(ChatId(1)).await.unwrap(),
+            Some(Language::Venetian)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_handle_album_size() {
+        let (repository, _database) = setup_repository().await;
+        assert_eq!(repository.get_album_size(ChatId(1)).await.unwrap(), None);
+        assert!(repository.set_album_size(ChatId(1), 3).await.is_ok());
+        assert_eq!(repository.get_album_size(ChatId(1)).await.unwrap(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn should_handle_captions() {
+        let (repository, _database) = setup_repository().await;
+        let template = Template::parse("Buon {giorno} {nome}!").unwrap();
+        assert_eq!(
+            repository
+                .get_caption(ChatId(1), Greeting::BuonaNotte)
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(
+            repository
+                .set_caption(ChatId(1), Greeting::BuonaNotte, &template)
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            repository
+                .get_caption(ChatId(1), Greeting::BuonaNotte)
+                .await
+                .unwrap(),
+            Some(template)
+        );
+        assert_eq!(
+            repository
+                .get_caption(ChatId(1), Greeting::BuonPranzo)
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(
+            repository
+                .delete_caption(ChatId(1), Greeting::BuonaNotte)
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            repository
+                .get_caption(ChatId(1), Greeting::BuonaNotte)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn should_handle_watermark() {
+        let (repository, _database) = setup_repository().await;
+        assert_eq!(repository.get_watermark(ChatId(1)).await.unwrap(), None);
+        // position and opacity alone don't make a watermark
+        assert!(
+            repository
+                .set_watermark_position(ChatId(1), Position::TopLeft)
+                .await
+                .is_ok()
+        );
+        assert_eq!(repository.get_watermark(ChatId(1)).await.unwrap(), None);
+        let text = Mark::Text("Caffè Club".to_string());
+        assert!(
+            repository
+                .set_watermark_mark(ChatId(1), &text)
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            repository.get_watermark(ChatId(1)).await.unwrap(),
+            Some(Watermark {
+                mark: text,
+                position: Position::TopLeft,
+                opacity: watermark::DEFAULT_OPACITY,
+            })
+        );
+        let logo = Mark::Logo(Url::parse("https://example.com/logo.png").unwrap());
+        assert!(
+            repository
+                .set_watermark_mark(ChatId(1), &logo)
+                .await
+                .is_ok()
+        );
+        assert!(
+            repository
+                .set_watermark_opacity(ChatId(1), 150)
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            repository.get_watermark(ChatId(1)).await.unwrap(),
+            Some(Watermark {
+                mark: logo,
+                position: Position::TopLeft,
+                opacity: 100,
+            })
+        );
+        assert!(repository.delete_watermark(ChatId(1)).await.is_ok());
+        assert_eq!(repository.get_watermark(ChatId(1)).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn should_handle_submissions() {
+        let (repository, _database) = setup_repository().await;
+        let approved = repository
+            .insert_submission(ChatId(1), UserId(42), Greeting::BuonaNotte, "AgACAgQAAx")
+            .await
+            .unwrap();
+        let rejected = repository
+            .insert_submission(ChatId(1), UserId(42), Greeting::BuonaNotte, "AgACAgQAAy")
+            .await
+            .unwrap();
+        let submission = repository
+            .review_submission(approved, true)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(submission.chat(), ChatId(1));
+        assert_eq!(submission.greeting(), "notte");
+        assert!(
+            repository
+                .review_submission(rejected, false)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            repository
+                .review_submission(approved, false)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(
+            repository
+                .get_community_images(Greeting::BuonaNotte)
+                .await
+                .unwrap(),
+            vec!["AgACAgQAAx".to_string()]
+        );
+        assert!(repository.forget_user(UserId(42)).await.is_ok());
+        assert!(
+            repository
+                .get_community_images(Greeting::BuonaNotte)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_handle_favourites() {
+        let (repository, _database) = setup_repository().await;
+        for index in 0..7 {
+            assert!(
+                repository
+                    .insert_favourite(
+                        UserId(42),
+                        Greeting::BuonaNotte,
+                        &format!("AgACAgQAA{index}"),
+                        MediaKind::Image
+                    )
+                    .await
+                    .unwrap()
+            );
+        }
+        assert!(
+            !repository
+                .insert_favourite(
+                    UserId(42),
+                    Greeting::BuonaNotte,
+                    "AgACAgQAA0",
+                    MediaKind::Image
+                )
+                .await
+                .unwrap()
+        );
+        let (favourites, pages) = repository.get_favourites(UserId(42), 0).await.unwrap();
+        assert_eq!(favourites.len(), 5);
+        assert_eq!(pages, 2);
+        assert_eq!(favourites[0].file_id(), "AgACAgQAA6");
+        let (favourites, _) = repository.get_favourites(UserId(42), 1).await.unwrap();
+        assert_eq!(favourites.len(), 2);
+        let favourite = repository
+            .get_favourite(UserId(42), favourites[0].id())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(favourite.greeting(), "notte");
+        assert!(repository.forget_user(UserId(42)).await.is_ok());
+        assert_eq!(
+            repository.get_favourites(UserId(42), 0).await.unwrap(),
+            (Vec::new(), 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_handle_votes() {
+        let (repository, _database) = setup_repository().await;
+        assert!(
+            repository
+                .vote_image(ChatId(1), UserId(42), 7, true)
+                .await
+                .is_ok()
+        );
+        assert!(
+            repository
+                .vote_image(ChatId(2), UserId(42), 7, true)
+                .await
+                .is_ok()
+        );
+        assert!(
+            repository
+                .vote_image(ChatId(1), UserId(43), 8, false)
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            repository.get_image_scores().await.unwrap(),
+            HashMap::from([(7, 2), (8, -1)])
+        );
+        assert!(repository.forget_user(UserId(42)).await.is_ok());
+        assert_eq!(
+            repository.get_image_scores().await.unwrap(),
+            HashMap::from([(8, -1)])
+        );
+    }
+
+    #[tokio::test]
+    async fn should_handle_greeting_of_the_day() {
+        let (repository, _database) = setup_repository().await;
+        let greeting = repository.get_greeting_of_the_day().await.unwrap();
+        for _ in 0..10 {
+            assert_eq!(
+                repository.get_greeting_of_the_day().await.unwrap(),
+                greeting
+            );
+        }
+        assert!(repository.get_image_of_the_day().await.unwrap().is_none());
+        // generated images are not stored
+        let generated = GreetingImage::generated(Url::parse("generator:buongiorno").unwrap());
+        assert!(
+            repository
+                .set_image_of_the_day(&generated)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let image = GreetingImage {
+            provider: Providers::Augurando,
+            url: Url::parse("https://example.com/caffe.jpg").unwrap(),
+            kind: MediaKind::Image,
+            hash: Some(0xcafe),
+        };
+        assert_eq!(
+            repository.set_image_of_the_day(&image).await.unwrap(),
+            Some(image.clone())
+        );
+        // the first image chosen wins
+        let other = GreetingImage {
+            url: Url::parse("https://example.com/notte.jpg").unwrap(),
+            ..image.clone()
+        };
+        assert_eq!(
+            repository.set_image_of_the_day(&other).await.unwrap(),
+            Some(image.clone())
+        );
+        assert_eq!(
+            repository.get_image_of_the_day().await.unwrap(),
+            Some(image)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_handle_known_users() {
+        let (repository, _database) = setup_repository().await;
+        let mut user = User {
+            id: UserId(42),
+            is_bot: false,
+            first_name: "Mario".to_string(),
+            last_name: Some("Rossi".to_string()),
+            username: None,
+            language_code: None,
+            is_premium: false,
+            added_to_attachment_menu: false,
+        };
+        // users without username can't be mentioned by username
+        assert!(repository.remember_user(&user).await.is_ok());
+        assert_eq!(
+            repository.get_user_by_username("mario").await.unwrap(),
+            None
+        );
+        user.username = Some("Mario".to_string());
+        assert!(repository.remember_user(&user).await.is_ok());
+        assert_eq!(
+            repository.get_user_by_username("@mario").await.unwrap(),
+            Some((UserId(42), "Mario Rossi".to_string()))
+        );
+        assert!(repository.forget_user(UserId(42)).await.is_ok());
+        assert_eq!(
+            repository.get_user_by_username("mario").await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn should_forget_user() {
+        let (repository, _database) = setup_repository().await;
+        assert!(repository.insert_chat(ChatId(42)).await.is_ok());
+        assert!(repository.insert_chat(ChatId(-1)).await.is_ok());
+        assert!(
+            repository
+                .set_user_birthday(
+                    ChatId(-1),
+                    UserId(42),
+                    "Chiara".to_string(),
+                    PartialDate::new(24, 6, None).unwrap()
+                )
+                .await
+                .is_ok()
+        );
+        assert!(
+            repository
+                .insert_birthday(
+                    ChatId(-1),
+                    "Christian".to_string(),
+                    PartialDate::new(30, 5, None).unwrap(),
+                    None
+                )
+                .await
+                .is_ok()
+        );
+        assert!(
+            repository
+                .set_language(ChatId(42), Language::English)
+                .await
+                .is_ok()
+        );
+        assert!(repository.forget_user(UserId(42)).await.is_ok());
+        assert_eq!(repository.get_language(ChatId(42)).await.unwrap(), None);
+        assert_eq!(
+            repository.get_subscribed_chats().await.unwrap(),
+            vec![ChatId(-1)]
+        );
+        assert_eq!(repository.get_birthdays().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_collect_delivery_stats() {
+        let (repository, _database) = setup_repository().await;
+        let stats = repository.get_delivery_stats().await.unwrap();
+        assert_eq!(stats.per_day.len(), STATS_DAYS as usize);
+        assert!(stats.per_day.iter().all(|(_, count)| *count == 0));
+        assert!(stats.per_provider.is_empty());
+        assert!(stats.most_active_chats.is_empty());
+        assert!(
+            repository
+                .insert_delivery(Delivery::new(
+                    ChatId(1),
+                    "good_morning",
+                    "BuonGiorno",
+                    Some("augurando".to_string()),
+                    None
+                ))
+                .await
+                .is_ok()
+        );
+        assert!(
+            repository
+                .insert_delivery(
+                    Delivery::new(
+                        ChatId(2),
+                        "good_morning",
+                        "BuonGiorno",
+                        Some("augurando".to_string()),
+                        None
+                    )
+                    .failed("bot was blocked by the user")
+                )
+                .await
+                .is_ok()
+        );
+        let stats = repository.get_delivery_stats().await.unwrap();
+        assert_eq!(stats.per_day.len(), STATS_DAYS as usize);
+        assert_eq!(stats.per_day[0].1, 1);
+        // the chat which blocked the bot is not the provider's fault
+        assert_eq!(stats.per_provider, vec![("augurando".to_string(), 1, 0)]);
+        assert_eq!(stats.most_active_chats, vec![(ChatId(1), 1)]);
+    }
+
+    #[tokio::test]
+    async fn should_handle_image_hashes() {
+        let (repository, _database) = setup_repository().await;
+        let url = "https://example.com/caffe.jpg";
+        assert!(
+            repository
+                .insert_image_hash(url, "augurando", 0xcafe)
+                .await
+                .is_ok()
+        );
+        assert!(
+            repository
+                .get_recent_image_hashes()
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            repository
+                .insert_delivery(Delivery::new(
+                    ChatId(1),
+                    "good_morning",
+                    "BuonGiorno",
+                    Some("augurando".to_string()),
+                    Some(url.to_string())
+                ))
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            repository.get_recent_image_hashes().await.unwrap(),
+            vec![0xcafe]
+        );
+        // images sent on request
+        let other = "https://example.com/notte.jpg";
+        assert!(
+            repository
+                .insert_image_hash(other, "augurando", 0xbeef)
+                .await
+                .is_ok()
+        );
+        assert!(repository.mark_image_sent(other).await.is_ok());
+        let mut hashes = repository.get_recent_image_hashes().await.unwrap();
+        hashes.sort();
+        assert_eq!(hashes, vec![0xbeef, 0xcafe]);
+    }
+
+    #[test]
+    fn should_render_delivery_stats() {
+        let stats = DeliveryStats {
+            per_day: Vec::new(),
+            per_provider: vec![("<augurando>".to_string(), 2, 1)],
+            most_active_chats: vec![(ChatId(1), 1)],
+        };
+        let report = stats.render(Language::English, Markup::Html);
+        assert!(report.starts_with("<b>📊 Delivery statistics</b>\n"));
+        assert!(report.contains("  &lt;augurando&gt;: 1/2 (50.0%)\n"));
+        let report = stats.to_string();
+        assert!(report.starts_with("📊 Statistiche consegne\n"));
+        assert!(report.contains("  <augurando>: 1/2 (50.0%)\n"));
+    }
 }
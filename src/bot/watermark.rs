@@ -0,0 +1,478 @@
+//! # Watermark
+//!
+//! This module stamps the watermark chosen by a chat, a text or a PNG logo, on the greeting images
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Cursor;
+use std::net::IpAddr;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use ab_glyph::{FontRef, PxScale};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImageView, ImageFormat, Rgb, RgbImage, Rgba, RgbaImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+use url::{Host, Url};
+
+use super::i18n::Language;
+use super::media;
+
+/// The font the text watermarks are written with
+const FONT: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans-Bold.ttf");
+/// The text height, as a fraction of the image height
+const TEXT_SCALE: f32 = 1.0 / 20.0;
+/// Minimum text height, in pixels
+const MIN_TEXT_SIZE: f32 = 12.0;
+/// Maximum logo size, as a fraction of the image size
+const LOGO_SCALE: u32 = 4;
+/// Margin between the watermark and the image border, as a percentage of the shortest image side
+const MARGIN_PERCENT: u32 = 2;
+/// Quality of the stamped images, re-encoded as JPEG
+const JPEG_QUALITY: u8 = 90;
+/// Default watermark opacity, in percent
+pub const DEFAULT_OPACITY: u8 = 80;
+/// For how long a downloaded logo is kept, before downloading it again in case it changed
+const LOGO_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Logos downloaded and decoded, shared by all the chats
+static LOGOS: LazyLock<LogoCache> = LazyLock::new(LogoCache::default);
+
+/// Cache of the logos downloaded and decoded, by url, with the time they were downloaded at
+#[derive(Default)]
+struct LogoCache {
+    entries: Mutex<HashMap<Url, (Instant, Arc<DynamicImage>)>>,
+}
+
+impl LogoCache {
+    /// Get the logo at `url`, if not expired
+    fn get(&self, url: &Url) -> Option<Arc<DynamicImage>> {
+        self.entries
+            .lock()
+            .expect("logo cache lock poisoned")
+            .get(url)
+            .filter(|(cached_at, _)| cached_at.elapsed() < LOGO_CACHE_TTL)
+            .map(|(_, logo)| logo.clone())
+    }
+
+    /// Put the `logo` at `url` in the cache, dropping the expired logos
+    fn insert(&self, url: &Url, logo: Arc<DynamicImage>) {
+        let mut entries = self.entries.lock().expect("logo cache lock poisoned");
+        entries.retain(|_, (cached_at, _)| cached_at.elapsed() < LOGO_CACHE_TTL);
+        entries.insert(url.clone(), (Instant::now(), logo));
+    }
+}
+
+/// What is stamped on the images
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mark {
+    Text(String),
+    /// The url of a PNG logo
+    Logo(Url),
+}
+
+impl fmt::Display for Mark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(text) => write!(f, "«{text}»"),
+            Self::Logo(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+/// Where the watermark is placed on the images
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+impl Position {
+    /// Get the position from its name, such as `basso-destra` or `top left`; both the italian and the english names are accepted
+    pub fn parse(name: &str) -> Option<Self> {
+        let name: String = name
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect();
+        match name.as_str() {
+            "altosinistra" | "topleft" => Some(Self::TopLeft),
+            "altodestra" | "topright" => Some(Self::TopRight),
+            "bassosinistra" | "bottomleft" => Some(Self::BottomLeft),
+            "bassodestra" | "bottomright" => Some(Self::BottomRight),
+            "centro" | "center" | "centre" => Some(Self::Center),
+            _ => None,
+        }
+    }
+
+    /// Get the name of the position in `lang`
+    pub fn name(self, lang: Language) -> &'static str {
+        match (self, lang) {
+            (Self::TopLeft, Language::English) => "top-left",
+            (Self::TopRight, Language::English) => "top-right",
+            (Self::BottomLeft, Language::English) => "bottom-left",
+            (Self::BottomRight, Language::English) => "bottom-right",
+            (Self::Center, Language::English) => "center",
+            (Self::TopLeft, _) => "alto-sinistra",
+            (Self::TopRight, _) => "alto-destra",
+            (Self::BottomLeft, _) => "basso-sinistra",
+            (Self::BottomRight, _) => "basso-destra",
+            (Self::Center, _) => "centro",
+        }
+    }
+
+    /// Get the top left corner of an overlay of `size` placed in a `canvas`, `margin` pixels away from the borders
+    fn origin(self, canvas: (u32, u32), size: (u32, u32), margin: u32) -> (i64, i64) {
+        let (canvas_width, canvas_height) = canvas;
+        let (width, height) = size;
+        let left = margin;
+        let right = canvas_width.saturating_sub(width + margin);
+        let top = margin;
+        let bottom = canvas_height.saturating_sub(height + margin);
+        let (x, y) = match self {
+            Self::TopLeft => (left, top),
+            Self::TopRight => (right, top),
+            Self::BottomLeft => (left, bottom),
+            Self::BottomRight => (right, bottom),
+            Self::Center => (
+                canvas_width.saturating_sub(width) / 2,
+                canvas_height.saturating_sub(height) / 2,
+            ),
+        };
+        (x as i64, y as i64)
+    }
+}
+
+/// The watermark chosen by a chat
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watermark {
+    pub mark: Mark,
+    pub position: Position,
+    /// Opacity in percent
+    pub opacity: u8,
+}
+
+/// The overlay drawn on an image, as resolved from a [`Mark`]
+#[derive(Debug, Clone)]
+pub enum Overlay {
+    Text(String),
+    Logo(Arc<DynamicImage>),
+}
+
+/// Stamp `watermark` on the image `bytes` and encode it as JPEG
+pub async fn stamp(bytes: Vec<u8>, watermark: &Watermark) -> anyhow::Result<Vec<u8>> {
+    let overlay = match &watermark.mark {
+        Mark::Text(text) => Overlay::Text(text.clone()),
+        Mark::Logo(url) => Overlay::Logo(logo(url).await?),
+    };
+    let (position, opacity) = (watermark.position, watermark.opacity);
+    // decoding and encoding images is expensive, so it mustn't block the runtime
+    tokio::task::spawn_blocking(move || {
+        let image = image::load_from_memory(&bytes)?;
+        encode_jpeg(&apply(&image, &overlay, position, opacity))
+    })
+    .await?
+}
+
+/// Get the PNG logo at `url`, downloading it only if not cached yet
+async fn logo(url: &Url) -> anyhow::Result<Arc<DynamicImage>> {
+    match LOGOS.get(url) {
+        Some(logo) => Ok(logo),
+        None => download_logo(url).await,
+    }
+}
+
+/// Download the PNG logo at `url` and put it in the cache
+pub async fn download_logo(url: &Url) -> anyhow::Result<Arc<DynamicImage>> {
+    check_logo_url(url).await?;
+    let (bytes, _) = media::download(url).await?;
+    if image::guess_format(&bytes)? != ImageFormat::Png {
+        anyhow::bail!("logo at {url} is not a PNG image");
+    }
+    let logo = Arc::new(image::load_from_memory_with_format(
+        &bytes,
+        ImageFormat::Png,
+    )?);
+    LOGOS.insert(url, logo.clone());
+    Ok(logo)
+}
+
+/// Check the logo at `url` can be downloaded: the urls are chosen by the users,
+/// so only https urls of public hosts are accepted, not to reach the network the bot runs in
+async fn check_logo_url(url: &Url) -> anyhow::Result<()> {
+    if url.scheme() != "https" {
+        anyhow::bail!("logo url {url} is not https");
+    }
+    let addresses: Vec<IpAddr> = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![IpAddr::V4(ip)],
+        Some(Host::Ipv6(ip)) => vec![IpAddr::V6(ip)],
+        Some(Host::Domain(domain)) => {
+            let port = url.port_or_known_default().unwrap_or(443);
+            tokio::net::lookup_host((domain, port))
+                .await?
+                .map(|address| address.ip())
+                .collect()
+        }
+        None => anyhow::bail!("logo url {url} has no host"),
+    };
+    if addresses.is_empty() {
+        anyhow::bail!("logo host of {url} not found");
+    }
+    if let Some(ip) = addresses.into_iter().find(|ip| !is_public(*ip)) {
+        anyhow::bail!("logo host of {url} is not public ({ip})");
+    }
+    Ok(())
+}
+
+/// Whether `ip` is reachable on the internet, rather than a loopback, private or link-local address
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                // shared address space, used by the carrier-grade NATs
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let segment = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    // unique local addresses
+                    || (segment & 0xfe00) == 0xfc00
+                    // link-local addresses
+                    || (segment & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Draw `overlay` on `image` at `position`, blended with the image according to `opacity` (in percent)
+pub fn apply(image: &DynamicImage, overlay: &Overlay, position: Position, opacity: u8) -> RgbImage {
+    let base = image.to_rgba8();
+    let mut stamped = base.clone();
+    let canvas = stamped.dimensions();
+    let margin = canvas.0.min(canvas.1) * MARGIN_PERCENT / 100;
+    match overlay {
+        Overlay::Text(text) => {
+            let font = font();
+            let scale = PxScale::from((canvas.1 as f32 * TEXT_SCALE).max(MIN_TEXT_SIZE));
+            let size = text_size(scale, &font, text);
+            let (x, y) = position.origin(canvas, size, margin);
+            // the shadow keeps the text readable on bright images
+            let shadow = (scale.y / 16.0).max(1.0) as i64;
+            draw_text_mut(
+                &mut stamped,
+                Rgba([0, 0, 0, 255]),
+                (x + shadow) as i32,
+                (y + shadow) as i32,
+                scale,
+                &font,
+                text,
+            );
+            draw_text_mut(
+                &mut stamped,
+                Rgba([255, 255, 255, 255]),
+                x as i32,
+                y as i32,
+                scale,
+                &font,
+                text,
+            );
+        }
+        Overlay::Logo(logo) => {
+            let (max_width, max_height) = (canvas.0 / LOGO_SCALE, canvas.1 / LOGO_SCALE);
+            let logo = if logo.width() > max_width || logo.height() > max_height {
+                Cow::Owned(logo.resize(max_width, max_height, FilterType::Triangle))
+            } else {
+                Cow::Borrowed(logo.as_ref())
+            };
+            let (x, y) = position.origin(canvas, logo.dimensions(), margin);
+            imageops::overlay(&mut stamped, &logo.to_rgba8(), x, y);
+        }
+    }
+    blend(&base, &stamped, opacity)
+}
+
+/// Get the bundled font
+pub fn font() -> FontRef<'static> {
+    FontRef::try_from_slice(FONT).expect("invalid bundled font")
+}
+
+/// Blend `stamped` over `base`, with `opacity` in percent
+fn blend(base: &RgbaImage, stamped: &RgbaImage, opacity: u8) -> RgbImage {
+    let opacity = u16::from(opacity.min(100));
+    RgbImage::from_fn(base.width(), base.height(), |x, y| {
+        let base = base.get_pixel(x, y);
+        let stamped = stamped.get_pixel(x, y);
+        Rgb([0, 1, 2].map(|channel| {
+            ((u16::from(base[channel]) * (100 - opacity) + u16::from(stamped[channel]) * opacity)
+                / 100) as u8
+        }))
+    })
+}
+
+/// Encode `image` as JPEG
+pub fn encode_jpeg(image: &RgbImage) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    image.write_with_encoder(JpegEncoder::new_with_quality(
+        Cursor::new(&mut bytes),
+        JPEG_QUALITY,
+    ))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const BACKGROUND: Rgb<u8> = Rgb([120, 72, 40]);
+
+    fn fixture(bytes: &[u8]) -> DynamicImage {
+        image::load_from_memory_with_format(bytes, ImageFormat::Png).unwrap()
+    }
+
+    fn caffe() -> DynamicImage {
+        fixture(include_bytes!("../../tests/fixtures/caffe.png"))
+    }
+
+    fn logo() -> Arc<DynamicImage> {
+        Arc::new(fixture(include_bytes!("../../tests/fixtures/logo.png")))
+    }
+
+    /// Whether any pixel in the rectangle from `(x, y)` of `width` x `height` differs from the background
+    fn is_stamped(image: &RgbImage, (x, y): (u32, u32), (width, height): (u32, u32)) -> bool {
+        (x..x + width)
+            .flat_map(|x| (y..y + height).map(move |y| (x, y)))
+            .any(|(x, y)| *image.get_pixel(x, y) != BACKGROUND)
+    }
+
+    #[test]
+    fn should_parse_position() {
+        assert_eq!(Position::parse("basso-destra"), Some(Position::BottomRight));
+        assert_eq!(Position::parse("Alto Sinistra"), Some(Position::TopLeft));
+        assert_eq!(Position::parse("top_right"), Some(Position::TopRight));
+        assert_eq!(Position::parse("centro"), Some(Position::Center));
+        assert_eq!(Position::parse("sotto"), None);
+        for language in Language::ALL {
+            for position in [
+                Position::TopLeft,
+                Position::TopRight,
+                Position::BottomLeft,
+                Position::BottomRight,
+                Position::Center,
+            ] {
+                assert_eq!(Position::parse(position.name(language)), Some(position));
+            }
+        }
+    }
+
+    #[test]
+    fn should_stamp_text() {
+        let text = Overlay::Text("Caffè Club".to_string());
+        let stamped = apply(&caffe(), &text, Position::BottomRight, 100);
+        assert_eq!(stamped.dimensions(), (320, 240));
+        assert!(is_stamped(&stamped, (160, 120), (160, 120)));
+        assert!(!is_stamped(&stamped, (0, 0), (160, 120)));
+        let stamped = apply(&caffe(), &text, Position::TopLeft, 100);
+        assert!(is_stamped(&stamped, (0, 0), (160, 120)));
+        assert!(!is_stamped(&stamped, (0, 120), (320, 120)));
+    }
+
+    #[test]
+    fn should_stamp_logo() {
+        let logo = Overlay::Logo(logo());
+        let stamped = apply(&caffe(), &logo, Position::TopLeft, 100);
+        // the logo is a red square with a transparent border, 4 pixels away from the image border
+        assert_eq!(*stamped.get_pixel(20, 20), Rgb([255, 0, 0]));
+        assert_eq!(*stamped.get_pixel(5, 5), BACKGROUND);
+        assert!(!is_stamped(&stamped, (40, 40), (280, 200)));
+        let stamped = apply(&caffe(), &logo, Position::Center, 100);
+        assert_eq!(*stamped.get_pixel(160, 120), Rgb([255, 0, 0]));
+        assert_eq!(*stamped.get_pixel(20, 20), BACKGROUND);
+    }
+
+    #[test]
+    fn should_cache_logo() {
+        let cache = LogoCache::default();
+        let url = Url::parse("https://example.com/logo.png").unwrap();
+        assert!(cache.get(&url).is_none());
+        cache.insert(&url, logo());
+        assert_eq!(cache.get(&url).unwrap().dimensions(), logo().dimensions());
+        assert!(
+            cache
+                .get(&Url::parse("https://example.com/other.png").unwrap())
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_reject_private_logo_urls() {
+        for url in [
+            "http://example.com/logo.png",
+            "https://127.0.0.1/logo.png",
+            "https://192.168.1.10/logo.png",
+            "https://169.254.169.254/latest/meta-data",
+            "https://[::1]/logo.png",
+            "https://[fd00::1]/logo.png",
+            "https://[::ffff:10.0.0.1]/logo.png",
+            "https://localhost/logo.png",
+        ] {
+            assert!(
+                check_logo_url(&Url::parse(url).unwrap()).await.is_err(),
+                "{url}"
+            );
+        }
+        assert!(
+            check_logo_url(&Url::parse("https://93.184.215.14/logo.png").unwrap())
+                .await
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn should_tell_public_addresses() {
+        assert!(is_public("93.184.215.14".parse().unwrap()));
+        assert!(is_public("2606:4700::1111".parse().unwrap()));
+        assert!(!is_public("10.1.2.3".parse().unwrap()));
+        assert!(!is_public("100.64.0.1".parse().unwrap()));
+        assert!(!is_public("0.0.0.0".parse().unwrap()));
+        assert!(!is_public("fe80::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn should_apply_opacity() {
+        let stamped = apply(&caffe(), &Overlay::Logo(logo()), Position::TopLeft, 50);
+        assert_eq!(*stamped.get_pixel(20, 20), Rgb([187, 36, 20]));
+        let text = Overlay::Text("Caffè Club".to_string());
+        let stamped = apply(&caffe(), &text, Position::Center, 0);
+        assert_eq!(stamped, caffe().to_rgb8());
+    }
+
+    #[test]
+    fn should_encode_jpeg() {
+        let text = Overlay::Text("Caffè Club".to_string());
+        let stamped = apply(&caffe(), &text, Position::Center, 80);
+        let jpeg = encode_jpeg(&stamped).unwrap();
+        assert_eq!(image::guess_format(&jpeg).unwrap(), ImageFormat::Jpeg);
+        assert_eq!(
+            image::load_from_memory(&jpeg).unwrap().dimensions(),
+            (320, 240)
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! # Weather
+//!
+//! This module fetches the weather forecast of the `{meteo}` caption placeholder from [wttr.in](https://wttr.in).
+//! Forecasts are cached, so that the service is queried at most once in a while for each language
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+use url::Url;
+
+use super::i18n::Language;
+
+/// Timeout of the requests made to get the forecast; the caption goes without it rather than waiting
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a forecast is kept before asking for a new one
+const CACHE_TTL: Duration = Duration::from_secs(30 * 60);
+/// Maximum length of a forecast; longer answers are error pages
+const MAX_FORECAST_LEN: usize = 64;
+
+/// The place the forecast is for; the forecast is disabled if unset
+static LOCATION: OnceLock<String> = OnceLock::new();
+
+/// Forecasts fetched in each language, with the time they were fetched at
+static CACHE: LazyLock<Mutex<HashMap<&'static str, (Instant, String)>>> =
+    LazyLock::new(Mutex::default);
+
+/// HTTP client used to get the forecast
+static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("failed to build http client")
+});
+
+/// Set the place the forecast is for
+pub fn set_location(location: &str) {
+    if LOCATION.set(location.trim().to_string()).is_err() {
+        warn!("weather location already set");
+    }
+}
+
+/// Get the forecast in `lang` for today, such as `☀️ Soleggiato +18°C`.
+///
+/// Returns `None` if the location is not set or the forecast is not available
+pub async fn forecast(lang: Language) -> Option<String> {
+    let location = LOCATION.get().filter(|location| !location.is_empty())?;
+    let code = code(lang);
+    if let Some(forecast) = cached(code) {
+        return Some(forecast);
+    }
+    match fetch(location, code).await {
+        Ok(forecast) => {
+            debug!("got weather forecast for {location}: {forecast}");
+            CACHE
+                .lock()
+                .unwrap()
+                .insert(code, (Instant::now(), forecast.clone()));
+            Some(forecast)
+        }
+        Err(err) => {
+            warn!("failed to get weather forecast for {location}: {err}");
+            None
+        }
+    }
+}
+
+/// Get the forecast in `code` from the cache, if not expired yet
+fn cached(code: &str) -> Option<String> {
+    CACHE
+        .lock()
+        .unwrap()
+        .get(code)
+        .filter(|(fetched_at, _)| fetched_at.elapsed() < CACHE_TTL)
+        .map(|(_, forecast)| forecast.clone())
+}
+
+async fn fetch(location: &str, code: &str) -> anyhow::Result<String> {
+    let text = HTTP_CLIENT
+        .get(url(location, code)?)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse(&text).ok_or_else(|| anyhow::anyhow!("unexpected forecast {text:?}"))
+}
+
+/// Build the url of the one-line forecast for `location` in `code`
+fn url(location: &str, code: &str) -> anyhow::Result<Url> {
+    let mut url = Url::parse("https://wttr.in/")?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow::anyhow!("bad weather url"))?
+        .push(location);
+    url.query_pairs_mut()
+        .append_pair("format", "%c%C %t")
+        .append_pair("lang", code);
+    Ok(url)
+}
+
+/// Check the one-line forecast returned by the service
+fn parse(text: &str) -> Option<String> {
+    let forecast = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if forecast.is_empty()
+        || forecast.chars().count() > MAX_FORECAST_LEN
+        || forecast.contains('<')
+        || forecast.to_lowercase().starts_with("unknown location")
+    {
+        return None;
+    }
+    Some(forecast)
+}
+
+/// The language code understood by the service; dialects get the forecast in italian
+fn code(lang: Language) -> &'static str {
+    match lang {
+        Language::English => "en",
+        Language::Italian | Language::Neapolitan | Language::Venetian => "it",
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_build_forecast_url() {
+        assert_eq!(
+            url("Roma", "it").unwrap().as_str(),
+            "https://wttr.in/Roma?format=%25c%25C+%25t&lang=it"
+        );
+        assert_eq!(
+            url("San Donà di Piave", "en").unwrap().as_str(),
+            "https://wttr.in/San%20Don%C3%A0%20di%20Piave?format=%25c%25C+%25t&lang=en"
+        );
+    }
+
+    #[test]
+    fn should_parse_forecast() {
+        assert_eq!(
+            parse("☀️   Soleggiato +18°C\n").as_deref(),
+            Some("☀️ Soleggiato +18°C")
+        );
+        assert!(parse("").is_none());
+        assert!(parse("Unknown location; please try ~45.4,12.3").is_none());
+        assert!(parse("<html><body>Service unavailable</body></html>").is_none());
+    }
+
+    #[test]
+    fn should_get_language_code() {
+        assert_eq!(code(Language::English), "en");
+        assert_eq!(code(Language::Venetian), "it");
+    }
+}
@@ -0,0 +1,102 @@
+//! # Health
+//!
+//! Health and readiness endpoints
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use serde::Serialize;
+
+use crate::bot::Automatizer;
+use crate::repository::SqliteDb;
+
+/// A job running for longer than this is considered stuck
+const JOB_STUCK_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Shared state of the health endpoints
+#[derive(Clone)]
+pub struct HealthState {
+    automatizer: Arc<Automatizer>,
+    db: SqliteDb,
+}
+
+impl HealthState {
+    pub fn new(db: SqliteDb, automatizer: Arc<Automatizer>) -> Self {
+        Self { automatizer, db }
+    }
+}
+
+/// Readiness report
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    ready: bool,
+    database: bool,
+    scheduler: bool,
+}
+
+/// Liveness report
+#[derive(Debug, Serialize)]
+pub struct Health {
+    healthy: bool,
+    running_jobs: Vec<RunningJob>,
+}
+
+/// A scheduled job currently running
+#[derive(Debug, Serialize)]
+pub struct RunningJob {
+    name: String,
+    running_for_secs: u64,
+    stuck: bool,
+}
+
+/// Report whether the bot is ready: database is reachable and the automatizer scheduler is running
+pub async fn readyz(State(state): State<HealthState>) -> (StatusCode, Json<Readiness>) {
+    let database = state.db.ping().await.is_ok();
+    let scheduler = state.automatizer.is_scheduler_running().await;
+    let ready = database && scheduler;
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(Readiness {
+            ready,
+            database,
+            scheduler,
+        }),
+    )
+}
+
+/// Report whether the bot is healthy: no scheduled job must be stuck
+pub async fn healthz(State(state): State<HealthState>) -> (StatusCode, Json<Health>) {
+    let running_jobs: Vec<RunningJob> = state
+        .automatizer
+        .running_jobs()
+        .into_iter()
+        .map(|(name, running_for)| RunningJob {
+            name,
+            running_for_secs: running_for.as_secs(),
+            stuck: running_for > JOB_STUCK_TIMEOUT,
+        })
+        .collect();
+    let healthy = running_jobs.iter().all(|job| !job.stuck);
+    let status = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(Health {
+            healthy,
+            running_jobs,
+        }),
+    )
+}
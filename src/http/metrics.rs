@@ -0,0 +1,21 @@
+//! # Metrics
+//!
+//! Prometheus metrics endpoint
+
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use prometheus::TEXT_FORMAT;
+use tracing::error;
+
+use crate::metrics::METRICS;
+
+/// Export metrics with the prometheus text format
+pub async fn metrics() -> Response {
+    match METRICS.encode() {
+        Ok(body) => ([(header::CONTENT_TYPE, TEXT_FORMAT)], body).into_response(),
+        Err(err) => {
+            error!("failed to export metrics: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+    }
+}
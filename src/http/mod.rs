@@ -0,0 +1,54 @@
+//! # Http
+//!
+//! This module exposes the optional HTTP endpoints of the bot
+
+mod health;
+mod metrics;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::Router;
+use axum::routing::get;
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+use self::health::HealthState;
+use crate::bot::Automatizer;
+use crate::repository::SqliteDb;
+
+/// Start the metrics server at `address` in a background task
+pub async fn serve_metrics(address: SocketAddr) -> anyhow::Result<()> {
+    serve(
+        address,
+        Router::new().route("/metrics", get(metrics::metrics)),
+    )
+    .await
+}
+
+/// Start the health and readiness server at `address` in a background task
+pub async fn serve_health(
+    address: SocketAddr,
+    db: SqliteDb,
+    automatizer: Arc<Automatizer>,
+) -> anyhow::Result<()> {
+    let router = Router::new()
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .with_state(HealthState::new(db, automatizer));
+    serve(address, router).await
+}
+
+/// Bind `address` and serve `router` in a background task
+async fn serve(address: SocketAddr, router: Router) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(address)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to bind http server to {}: {}", address, e))?;
+    info!("http server listening on {}", address);
+    tokio::spawn(async move {
+        if let Err(err) = axum::serve(listener, router).await {
+            error!("http server at {} stopped: {}", address, err);
+        }
+    });
+    Ok(())
+}
@@ -2,6 +2,8 @@ const APP_AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
 const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 mod bot;
+mod http;
+mod metrics;
 mod repository;
 mod utils;
 
@@ -15,6 +17,9 @@ async fn main() -> anyhow::Result<()> {
         "buongiorno-caffe-bot v{} - developed by {}",
         APP_VERSION, APP_AUTHORS
     );
+    if std::env::args().nth(1).as_deref() == Some("stats") {
+        return Buongiornissimo::print_stats().await;
+    }
     let app = Buongiornissimo::init().await?;
     info!("application ready!");
     app.run().await
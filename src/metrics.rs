@@ -0,0 +1,196 @@
+//! # Metrics
+//!
+//! Prometheus metrics collected by the bot
+
+use std::sync::LazyLock;
+
+use prometheus::{
+    Encoder as _, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+/// Application metrics
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Collection of the application metrics, registered on a dedicated registry
+pub struct Metrics {
+    registry: Registry,
+    commands_received: IntCounterVec,
+    scrape_attempts: IntCounterVec,
+    scrape_failures: IntCounterVec,
+    discarded_images: IntCounterVec,
+    job_runs: IntCounterVec,
+    job_duration: HistogramVec,
+    send_failures: IntCounterVec,
+    subscribers: IntGauge,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("buongiornissimo".to_string()), None)
+            .expect("invalid registry prefix");
+        let commands_received = IntCounterVec::new(
+            Opts::new("commands_received_total", "Commands received per command"),
+            &["command"],
+        )
+        .expect("invalid metric");
+        let scrape_attempts = IntCounterVec::new(
+            Opts::new("scrape_attempts_total", "Scrape attempts per provider"),
+            &["provider"],
+        )
+        .expect("invalid metric");
+        let scrape_failures = IntCounterVec::new(
+            Opts::new("scrape_failures_total", "Scrape failures per provider"),
+            &["provider"],
+        )
+        .expect("invalid metric");
+        let discarded_images = IntCounterVec::new(
+            Opts::new(
+                "discarded_images_total",
+                "Scraped images discarded per provider and reason",
+            ),
+            &["provider", "reason"],
+        )
+        .expect("invalid metric");
+        let job_runs = IntCounterVec::new(
+            Opts::new("job_runs_total", "Scheduled job runs per job"),
+            &["job"],
+        )
+        .expect("invalid metric");
+        let job_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "job_duration_seconds",
+                "Scheduled job run duration in seconds",
+            )
+            .buckets(vec![0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0]),
+            &["job"],
+        )
+        .expect("invalid metric");
+        let send_failures = IntCounterVec::new(
+            Opts::new(
+                "send_failures_total",
+                "Telegram send failures per error kind",
+            ),
+            &["kind"],
+        )
+        .expect("invalid metric");
+        let subscribers =
+            IntGauge::new("subscribers", "Current subscribed chats").expect("invalid metric");
+
+        registry
+            .register(Box::new(commands_received.clone()))
+            .expect("failed to register metric");
+        registry
+            .register(Box::new(scrape_attempts.clone()))
+            .expect("failed to register metric");
+        registry
+            .register(Box::new(scrape_failures.clone()))
+            .expect("failed to register metric");
+        registry
+            .register(Box::new(discarded_images.clone()))
+            .expect("failed to register metric");
+        registry
+            .register(Box::new(job_runs.clone()))
+            .expect("failed to register metric");
+        registry
+            .register(Box::new(job_duration.clone()))
+            .expect("failed to register metric");
+        registry
+            .register(Box::new(send_failures.clone()))
+            .expect("failed to register metric");
+        registry
+            .register(Box::new(subscribers.clone()))
+            .expect("failed to register metric");
+
+        Self {
+            registry,
+            commands_received,
+            scrape_attempts,
+            scrape_failures,
+            discarded_images,
+            job_runs,
+            job_duration,
+            send_failures,
+            subscribers,
+        }
+    }
+
+    /// Count a received command
+    pub fn command_received(&self, command: &str) {
+        self.commands_received.with_label_values(&[command]).inc();
+    }
+
+    /// Count a scrape attempt for `provider`
+    pub fn scrape_attempt(&self, provider: &str) {
+        self.scrape_attempts.with_label_values(&[provider]).inc();
+    }
+
+    /// Count a scrape failure for `provider`
+    pub fn scrape_failure(&self, provider: &str) {
+        self.scrape_failures.with_label_values(&[provider]).inc();
+    }
+
+    /// Count an image scraped from `provider` and discarded for `reason`
+    pub fn discarded_image(&self, provider: &str, reason: &str) {
+        self.discarded_images
+            .with_label_values(&[provider, reason])
+            .inc();
+    }
+
+    /// Count a scheduled job run, which took `seconds` to complete
+    pub fn job_run(&self, job: &str, seconds: f64) {
+        self.job_runs.with_label_values(&[job]).inc();
+        self.job_duration.with_label_values(&[job]).observe(seconds);
+    }
+
+    /// Count a failure while sending a message to telegram
+    pub fn send_failure(&self, kind: &str) {
+        self.send_failures.with_label_values(&[kind]).inc();
+    }
+
+    /// Set the current amount of subscribed chats
+    pub fn set_subscribers(&self, subscribers: usize) {
+        self.subscribers.set(subscribers as i64);
+    }
+
+    /// Encode metrics with the prometheus text format
+    pub fn encode(&self) -> anyhow::Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| anyhow::anyhow!("failed to encode metrics: {}", e))?;
+        String::from_utf8(buffer).map_err(|e| anyhow::anyhow!("metrics are not utf-8: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn should_encode_metrics() {
+        let metrics = Metrics::new();
+        metrics.command_received("buongiornissimo");
+        metrics.scrape_attempt("augurando");
+        metrics.scrape_failure("augurando");
+        metrics.discarded_image("augurando", "too_small");
+        metrics.job_run("good_morning", 1.5);
+        metrics.send_failure("network");
+        metrics.set_subscribers(3);
+        let encoded = metrics.encode().unwrap();
+        assert!(
+            encoded.contains(
+                r#"buongiornissimo_commands_received_total{command="buongiornissimo"} 1"#
+            )
+        );
+        assert!(
+            encoded.contains(r#"buongiornissimo_scrape_failures_total{provider="augurando"} 1"#)
+        );
+        assert!(encoded.contains(
+            r#"buongiornissimo_discarded_images_total{provider="augurando",reason="too_small"} 1"#
+        ));
+        assert!(encoded.contains(r#"buongiornissimo_job_runs_total{job="good_morning"} 1"#));
+        assert!(encoded.contains(r#"buongiornissimo_send_failures_total{kind="network"} 1"#));
+        assert!(encoded.contains("buongiornissimo_subscribers 3"));
+    }
+}
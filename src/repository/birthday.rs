@@ -4,12 +4,13 @@
 
 use std::str::FromStr;
 
-use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use chrono::{DateTime, FixedOffset, Utc};
 use sqlx::{Pool, Sqlite};
-use teloxide::types::ChatId;
+use teloxide::types::{ChatId, UserId};
 use tracing::debug;
 
 use super::{RepositoryError, RepositoryResult};
+use crate::utils::date::PartialDate;
 
 #[derive(sqlx::FromRow, Debug, Clone, Eq, PartialEq)]
 pub struct Birthday {
@@ -17,18 +18,26 @@ pub struct Birthday {
     name: String,
     date: String,
     created_at: String,
+    user_id: Option<i64>,
 }
 
 impl Birthday {
-    pub fn new(chat_id: ChatId, name: String, date: NaiveDate) -> Self {
+    pub fn new(chat_id: ChatId, name: String, date: PartialDate) -> Self {
         Self {
             chat: chat_id.0,
             name,
-            date: date.to_string(),
+            date: date.to_iso_string(),
             created_at: Utc::now().to_rfc3339(),
+            user_id: None,
         }
     }
 
+    /// Associate the birthday to the telegram user celebrating it
+    pub fn with_user(mut self, user: UserId) -> Self {
+        self.user_id = Some(user.0 as i64);
+        self
+    }
+
     /// Return inner `ChatId`
     pub fn chat(&self) -> ChatId {
         ChatId(self.chat)
@@ -38,9 +47,14 @@ impl Birthday {
         &self.name
     }
 
-    /// Return birthdate
-    pub fn date(&self) -> RepositoryResult<NaiveDate> {
-        NaiveDate::from_str(&self.date).map_err(|_| RepositoryError::BadDateTimeSyntax)
+    /// Return the telegram user celebrating the birthday, if known
+    pub fn user(&self) -> Option<UserId> {
+        self.user_id.map(|id| UserId(id as u64))
+    }
+
+    /// Return birthdate; the year is unknown if the date was stored as `--MM-DD`
+    pub fn date(&self) -> RepositoryResult<PartialDate> {
+        PartialDate::from_str(&self.date).map_err(|_| RepositoryError::BadDateTimeSyntax)
     }
 
     /// Return created_at as a `DateTime`
@@ -53,7 +67,7 @@ impl Birthday {
     pub async fn get_all(db: &Pool<Sqlite>) -> RepositoryResult<Vec<Birthday>> {
         sqlx::query_as(
             r#"
-            SELECT chat, name, date, created_at
+            SELECT chat, name, date, created_at, user_id
             FROM birthday"#,
         )
         .fetch_all(db)
@@ -84,12 +98,13 @@ impl Birthday {
     pub async fn insert(&self, db: &Pool<Sqlite>) -> RepositoryResult<()> {
         debug!("inserting a new chat {} to repository", self.chat);
         let rows = sqlx::query(
-            "INSERT INTO birthday (chat, name, date, created_at) VALUES ($1, $2, $3, $4)",
+            "INSERT INTO birthday (chat, name, date, created_at, user_id) VALUES ($1, $2, $3, $4, $5)",
         )
         .bind(self.chat)
         .bind(&self.name)
         .bind(&self.date)
         .bind(&self.created_at)
+        .bind(self.user_id)
         .execute(db)
         .await
         .map_err(RepositoryError::from)?
@@ -101,6 +116,47 @@ impl Birthday {
         Ok(())
     }
 
+    /// Insert `Birthday` to database, replacing the birthday of the same user in the same chat if any.
+    ///
+    /// Birthdays without a user are always inserted
+    pub async fn upsert(&self, db: &Pool<Sqlite>) -> RepositoryResult<()> {
+        let Some(user_id) = self.user_id else {
+            return self.insert(db).await;
+        };
+        debug!(
+            "updating birthday of user {} in chat {} in repository",
+            user_id, self.chat
+        );
+        let rows = sqlx::query(
+            "UPDATE birthday SET name = $1, date = $2 WHERE chat = $3 AND user_id = $4",
+        )
+        .bind(&self.name)
+        .bind(&self.date)
+        .bind(self.chat)
+        .bind(user_id)
+        .execute(db)
+        .await
+        .map_err(RepositoryError::from)?
+        .rows_affected();
+        if rows == 0 {
+            return self.insert(db).await;
+        }
+
+        Ok(())
+    }
+
+    /// Delete the birthdays of `user` in every chat from database
+    pub async fn delete_by_user(db: &Pool<Sqlite>, user: UserId) -> RepositoryResult<()> {
+        debug!("deleting birthdays of user {} from repository", user);
+        sqlx::query("DELETE FROM birthday WHERE user_id = $1")
+            .bind(user.0 as i64)
+            .execute(db)
+            .await
+            .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
     /// Delete this chat from database
     pub async fn delete_by_chat(db: &Pool<Sqlite>, chat: ChatId) -> RepositoryResult<()> {
         debug!("deleting birthday for chat {} from repository", chat);
@@ -117,6 +173,9 @@ impl Birthday {
 #[cfg(test)]
 mod test {
 
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+
     use super::*;
     use crate::repository::test::init_database;
 
@@ -126,7 +185,7 @@ mod test {
         let chat = Birthday::new(
             ChatId(32),
             "pippo".to_string(),
-            NaiveDate::from_ymd_opt(1997, 5, 30).unwrap(),
+            NaiveDate::from_ymd_opt(1997, 5, 30).unwrap().into(),
         );
         assert!(chat.insert(db.pool()).await.is_ok());
         drop(temp)
@@ -138,7 +197,7 @@ mod test {
         let birthday = Birthday::new(
             ChatId(1),
             "pippo".to_string(),
-            NaiveDate::from_ymd_opt(1997, 5, 30).unwrap(),
+            NaiveDate::from_ymd_opt(1997, 5, 30).unwrap().into(),
         );
         assert!(birthday.insert(db.pool()).await.is_ok());
         assert!(
@@ -156,17 +215,17 @@ mod test {
             Birthday::new(
                 ChatId(1),
                 "pippo".to_string(),
-                NaiveDate::from_ymd_opt(1997, 5, 30).unwrap(),
+                NaiveDate::from_ymd_opt(1997, 5, 30).unwrap().into(),
             ),
             Birthday::new(
                 ChatId(2),
                 "pippo".to_string(),
-                NaiveDate::from_ymd_opt(1997, 5, 30).unwrap(),
+                NaiveDate::from_ymd_opt(1997, 5, 30).unwrap().into(),
             ),
             Birthday::new(
                 ChatId(3),
                 "pippo".to_string(),
-                NaiveDate::from_ymd_opt(1997, 5, 30).unwrap(),
+                NaiveDate::from_ymd_opt(1997, 5, 30).unwrap().into(),
             ),
         ];
         for chat in chats.iter() {
@@ -176,4 +235,108 @@ mod test {
         assert_eq!(Birthday::get_all(db.pool()).await.unwrap(), chats);
         drop(temp)
     }
+
+    #[tokio::test]
+    async fn should_retrieve_birthday_without_year() {
+        let (db, temp) = init_database().await;
+        let birthday = Birthday::new(
+            ChatId(1),
+            "pippo".to_string(),
+            PartialDate::new(30, 5, None).unwrap(),
+        );
+        assert!(birthday.insert(db.pool()).await.is_ok());
+        let birthdays = Birthday::get_all(db.pool()).await.unwrap();
+        assert_eq!(
+            birthdays[0].date().unwrap(),
+            PartialDate::new(30, 5, None).unwrap()
+        );
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_retrieve_birthday_with_user() {
+        let (db, temp) = init_database().await;
+        let birthday = Birthday::new(
+            ChatId(1),
+            "pippo".to_string(),
+            PartialDate::new(30, 5, None).unwrap(),
+        )
+        .with_user(UserId(42));
+        assert!(birthday.insert(db.pool()).await.is_ok());
+        let birthdays = Birthday::get_all(db.pool()).await.unwrap();
+        assert_eq!(birthdays[0].user(), Some(UserId(42)));
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_upsert_birthday_by_user() {
+        let (db, temp) = init_database().await;
+        let birthday = Birthday::new(
+            ChatId(1),
+            "pippo".to_string(),
+            PartialDate::new(30, 5, None).unwrap(),
+        )
+        .with_user(UserId(42));
+        assert!(birthday.upsert(db.pool()).await.is_ok());
+        let updated = Birthday::new(
+            ChatId(1),
+            "Pippo Baudo".to_string(),
+            PartialDate::new(7, 6, Some(1936)).unwrap(),
+        )
+        .with_user(UserId(42));
+        assert!(updated.upsert(db.pool()).await.is_ok());
+        // same user in another chat
+        let other_chat = Birthday::new(
+            ChatId(2),
+            "pippo".to_string(),
+            PartialDate::new(7, 6, None).unwrap(),
+        )
+        .with_user(UserId(42));
+        assert!(other_chat.upsert(db.pool()).await.is_ok());
+        let birthdays = Birthday::get_all(db.pool()).await.unwrap();
+        assert_eq!(birthdays.len(), 2);
+        assert_eq!(birthdays[0].name(), "Pippo Baudo");
+        assert_eq!(
+            birthdays[0].date().unwrap(),
+            PartialDate::new(7, 6, Some(1936)).unwrap()
+        );
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_delete_birthdays_by_user() {
+        let (db, temp) = init_database().await;
+        let birthdays = [
+            Birthday::new(
+                ChatId(1),
+                "pippo".to_string(),
+                PartialDate::new(30, 5, None).unwrap(),
+            )
+            .with_user(UserId(42)),
+            Birthday::new(
+                ChatId(2),
+                "pippo".to_string(),
+                PartialDate::new(30, 5, None).unwrap(),
+            )
+            .with_user(UserId(42)),
+            Birthday::new(
+                ChatId(2),
+                "pluto".to_string(),
+                PartialDate::new(30, 5, None).unwrap(),
+            ),
+        ];
+        for birthday in birthdays.iter() {
+            assert!(birthday.insert(db.pool()).await.is_ok());
+        }
+        assert!(
+            Birthday::delete_by_user(db.pool(), UserId(42))
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            Birthday::get_all(db.pool()).await.unwrap(),
+            vec![birthdays[2].clone()]
+        );
+        drop(temp)
+    }
 }
@@ -0,0 +1,179 @@
+//! # Daily greeting
+//!
+//! this module contains the daily greeting entity repository, which stores the greeting of the day and its image,
+//! so that they are the same for every chat and command during the day
+
+use chrono::{NaiveDate, Utc};
+use sqlx::{Pool, Sqlite};
+use tracing::debug;
+
+use super::{RepositoryError, RepositoryResult};
+
+#[derive(sqlx::FromRow, Debug, Clone, Eq, PartialEq)]
+pub struct DailyGreeting {
+    date: String,
+    /// Whether the good morning greeting is based on the weekday
+    use_weekday: bool,
+    provider: Option<String>,
+    image_url: Option<String>,
+    kind: Option<String>,
+    /// The perceptual hash of the image, stored with the same bits of the `u64`
+    hash: Option<i64>,
+    created_at: String,
+}
+
+impl DailyGreeting {
+    /// Return whether the good morning greeting of the day is based on the weekday
+    pub fn use_weekday(&self) -> bool {
+        self.use_weekday
+    }
+
+    /// Return the provider, the url and the kind of media of the image of the day, if it has been chosen
+    pub fn image(&self) -> Option<(&str, &str, &str)> {
+        match (&self.provider, &self.image_url, &self.kind) {
+            (Some(provider), Some(url), Some(kind)) => Some((provider, url, kind)),
+            _ => None,
+        }
+    }
+
+    /// Return the perceptual hash of the image of the day, if any
+    pub fn hash(&self) -> Option<u64> {
+        self.hash.map(|hash| hash as u64)
+    }
+
+    /// Get the greeting of `date`; if it hasn't been decided yet, it's stored with `use_weekday`.
+    /// If the greeting is decided concurrently, the first one stored wins
+    pub async fn get_or_insert(
+        db: &Pool<Sqlite>,
+        date: NaiveDate,
+        use_weekday: bool,
+    ) -> RepositoryResult<DailyGreeting> {
+        let date = date.format("%Y-%m-%d").to_string();
+        let inserted = sqlx::query(
+            r#"INSERT INTO daily_greeting (date, use_weekday, created_at) VALUES ($1, $2, $3)
+            ON CONFLICT (date) DO NOTHING"#,
+        )
+        .bind(&date)
+        .bind(use_weekday)
+        .bind(Utc::now().to_rfc3339())
+        .execute(db)
+        .await
+        .map_err(RepositoryError::from)?
+        .rows_affected();
+        if inserted == 1 {
+            debug!("decided greeting of {} (weekday: {})", date, use_weekday);
+        }
+        sqlx::query_as(
+            r#"
+            SELECT date, use_weekday, provider, image_url, kind, hash, created_at
+            FROM daily_greeting
+            WHERE date = $1"#,
+        )
+        .bind(&date)
+        .fetch_one(db)
+        .await
+        .map_err(RepositoryError::from)
+    }
+
+    /// Set the image of the greeting of `date`, unless it has already been chosen; returns whether it has been set
+    pub async fn set_image(
+        db: &Pool<Sqlite>,
+        date: NaiveDate,
+        provider: &str,
+        url: &str,
+        kind: &str,
+        hash: Option<u64>,
+    ) -> RepositoryResult<bool> {
+        debug!("setting image {} as the image of {}", url, date);
+        let rows = sqlx::query(
+            r#"UPDATE daily_greeting SET provider = $1, image_url = $2, kind = $3, hash = $4
+            WHERE date = $5 AND image_url IS NULL"#,
+        )
+        .bind(provider)
+        .bind(url)
+        .bind(kind)
+        .bind(hash.map(|hash| hash as i64))
+        .bind(date.format("%Y-%m-%d").to_string())
+        .execute(db)
+        .await
+        .map_err(RepositoryError::from)?
+        .rows_affected();
+
+        Ok(rows == 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repository::test::init_database;
+
+    #[tokio::test]
+    async fn should_decide_greeting_once() {
+        let (db, temp) = init_database().await;
+        let today = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        let tomorrow = today.succ_opt().unwrap();
+        let greeting = DailyGreeting::get_or_insert(db.pool(), today, true)
+            .await
+            .unwrap();
+        assert!(greeting.use_weekday());
+        assert_eq!(greeting.image(), None);
+        // already decided
+        let greeting = DailyGreeting::get_or_insert(db.pool(), today, false)
+            .await
+            .unwrap();
+        assert!(greeting.use_weekday());
+        assert!(
+            !DailyGreeting::get_or_insert(db.pool(), tomorrow, false)
+                .await
+                .unwrap()
+                .use_weekday()
+        );
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_set_image_once() {
+        let (db, temp) = init_database().await;
+        let today = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        DailyGreeting::get_or_insert(db.pool(), today, false)
+            .await
+            .unwrap();
+        assert!(
+            DailyGreeting::set_image(
+                db.pool(),
+                today,
+                "augurando",
+                "https://example.com/caffe.jpg",
+                "image",
+                Some(u64::MAX)
+            )
+            .await
+            .unwrap()
+        );
+        assert!(
+            !DailyGreeting::set_image(
+                db.pool(),
+                today,
+                "ticondivido",
+                "https://example.com/notte.jpg",
+                "image",
+                None
+            )
+            .await
+            .unwrap()
+        );
+        let greeting = DailyGreeting::get_or_insert(db.pool(), today, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            greeting.image(),
+            Some(("augurando", "https://example.com/caffe.jpg", "image"))
+        );
+        assert_eq!(greeting.hash(), Some(u64::MAX));
+        drop(temp)
+    }
+}
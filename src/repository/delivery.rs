@@ -0,0 +1,373 @@
+//! # Delivery
+//!
+//! this module contains the delivery log entity repository
+
+use std::collections::HashMap;
+
+use chrono::{Days, NaiveDate, Utc};
+use sqlx::{Pool, Sqlite};
+use teloxide::types::ChatId;
+use tracing::debug;
+
+use super::{RepositoryError, RepositoryResult};
+
+/// Delivery status
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(super) enum DeliveryStatus {
+    Sent,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sent => "sent",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// A greeting message delivered to a chat, or the failure to get the images of a job.
+///
+/// Each message is recorded once, with the provider and the url of its first image
+#[derive(sqlx::FromRow, Debug, Clone, Eq, PartialEq)]
+pub struct Delivery {
+    /// The chat the message is sent to; scrape failures are recorded once per job, without chat
+    chat: Option<i64>,
+    job: String,
+    greeting: String,
+    provider: Option<String>,
+    image_url: Option<String>,
+    status: String,
+    error: Option<String>,
+    created_at: String,
+}
+
+impl Delivery {
+    pub fn new(
+        chat_id: ChatId,
+        job: impl ToString,
+        greeting: impl ToString,
+        provider: Option<String>,
+        image_url: Option<String>,
+    ) -> Self {
+        Self {
+            chat: Some(chat_id.0),
+            job: job.to_string(),
+            greeting: greeting.to_string(),
+            provider,
+            image_url,
+            status: DeliveryStatus::Sent.as_str().to_string(),
+            error: None,
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Create the failure of `provider` to provide the images of `greeting` for `job`
+    pub fn scrape_failed(
+        job: impl ToString,
+        greeting: impl ToString,
+        provider: Option<String>,
+        error: impl ToString,
+    ) -> Self {
+        Self {
+            chat: None,
+            job: job.to_string(),
+            greeting: greeting.to_string(),
+            provider,
+            image_url: None,
+            status: DeliveryStatus::Failed.as_str().to_string(),
+            error: Some(error.to_string()),
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Mark delivery as failed to be sent with the provided error.
+    /// Sending fails regardless of the provider (e.g. the bot has been blocked), so the failure is not counted against it
+    pub fn failed(mut self, error: impl ToString) -> Self {
+        self.status = DeliveryStatus::Failed.as_str().to_string();
+        self.error = Some(error.to_string());
+        self.provider = None;
+        self
+    }
+
+    /// Whether the delivery has failed
+    pub fn is_failed(&self) -> bool {
+        self.status == DeliveryStatus::Failed.as_str()
+    }
+
+    /// Insert `Delivery` to database
+    pub async fn insert(&self, db: &Pool<Sqlite>) -> RepositoryResult<()> {
+        debug!(
+            "inserting a new delivery for chat {:?} ({}) to repository",
+            self.chat, self.status
+        );
+        let rows = sqlx::query(
+            r#"INSERT INTO delivery (chat, job, greeting, provider, image_url, status, error, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#,
+        )
+        .bind(self.chat)
+        .bind(&self.job)
+        .bind(&self.greeting)
+        .bind(&self.provider)
+        .bind(&self.image_url)
+        .bind(&self.status)
+        .bind(&self.error)
+        .bind(&self.created_at)
+        .execute(db)
+        .await
+        .map_err(RepositoryError::from)?
+        .rows_affected();
+        if rows != 1 {
+            return Err(RepositoryError::TooManyInserts);
+        }
+
+        Ok(())
+    }
+
+    /// Count the messages delivered per day in the `days` calendar days up to `today`, most recent first.
+    /// The days without deliveries are counted as zero
+    pub async fn count_per_day(
+        db: &Pool<Sqlite>,
+        today: NaiveDate,
+        days: u64,
+    ) -> RepositoryResult<Vec<(String, i64)>> {
+        let days: Vec<NaiveDate> = (0..days)
+            .filter_map(|ago| today.checked_sub_days(Days::new(ago)))
+            .collect();
+        let Some(first_day) = days.last() else {
+            return Ok(Vec::new());
+        };
+        let counts: HashMap<String, i64> = sqlx::query_as(
+            r#"
+            SELECT substr(created_at, 1, 10) AS day, COUNT(*)
+            FROM delivery
+            WHERE status = $1 AND substr(created_at, 1, 10) BETWEEN $2 AND $3
+            GROUP BY day"#,
+        )
+        .bind(DeliveryStatus::Sent.as_str())
+        .bind(first_day.to_string())
+        .bind(today.to_string())
+        .fetch_all(db)
+        .await
+        .map_err(RepositoryError::from)?
+        .into_iter()
+        .collect();
+        Ok(days
+            .into_iter()
+            .map(|day| {
+                let day = day.to_string();
+                let count = counts.get(&day).copied().unwrap_or_default();
+                (day, count)
+            })
+            .collect())
+    }
+
+    /// Count total and failed deliveries for each provider
+    pub async fn count_per_provider(
+        db: &Pool<Sqlite>,
+    ) -> RepositoryResult<Vec<(String, i64, i64)>> {
+        sqlx::query_as(
+            r#"
+            SELECT provider, COUNT(*), SUM(CASE WHEN status = $1 THEN 1 ELSE 0 END)
+            FROM delivery
+            WHERE provider IS NOT NULL
+            GROUP BY provider
+            ORDER BY provider"#,
+        )
+        .bind(DeliveryStatus::Failed.as_str())
+        .fetch_all(db)
+        .await
+        .map_err(RepositoryError::from)
+    }
+
+    /// Get the `limit` chats with the most successful deliveries
+    pub async fn most_active_chats(
+        db: &Pool<Sqlite>,
+        limit: i64,
+    ) -> RepositoryResult<Vec<(i64, i64)>> {
+        sqlx::query_as(
+            r#"
+            SELECT chat, COUNT(*) AS deliveries
+            FROM delivery
+            WHERE status = $1
+            GROUP BY chat
+            ORDER BY deliveries DESC, chat
+            LIMIT $2"#,
+        )
+        .bind(DeliveryStatus::Sent.as_str())
+        .bind(limit)
+        .fetch_all(db)
+        .await
+        .map_err(RepositoryError::from)
+    }
+
+    /// Delete the deliveries to `chat` from database
+    pub async fn delete_by_chat(db: &Pool<Sqlite>, chat: ChatId) -> RepositoryResult<()> {
+        debug!("deleting deliveries for chat {} from repository", chat);
+        sqlx::query("DELETE FROM delivery WHERE chat = $1")
+            .bind(chat.0)
+            .execute(db)
+            .await
+            .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repository::test::init_database;
+
+    #[tokio::test]
+    async fn should_insert_delivery() {
+        let (db, temp) = init_database().await;
+        let delivery = Delivery::new(
+            ChatId(32),
+            "good_morning",
+            "BuonGiorno",
+            Some("augurando".to_string()),
+            Some("https://example.com/caffe.jpg".to_string()),
+        );
+        assert!(delivery.insert(db.pool()).await.is_ok());
+        let failed =
+            Delivery::new(ChatId(32), "good_night", "BuonaNotte", None, None).failed("blocked");
+        assert!(failed.is_failed());
+        assert!(failed.insert(db.pool()).await.is_ok());
+        let failed = Delivery::scrape_failed(
+            "good_night",
+            "BuonaNotte",
+            Some("augurando".to_string()),
+            "no images",
+        );
+        assert!(failed.is_failed());
+        assert!(failed.insert(db.pool()).await.is_ok());
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_count_scrape_failures_once_per_job() {
+        let (db, temp) = init_database().await;
+        let failed = Delivery::scrape_failed(
+            "good_morning",
+            "BuonGiorno",
+            Some("augurando".to_string()),
+            "no images",
+        );
+        assert!(failed.insert(db.pool()).await.is_ok());
+        assert_eq!(
+            Delivery::count_per_provider(db.pool()).await.unwrap(),
+            vec![("augurando".to_string(), 1, 1)]
+        );
+        let today = Utc::now().date_naive();
+        assert!(
+            Delivery::count_per_day(db.pool(), today, 7)
+                .await
+                .unwrap()
+                .iter()
+                .all(|(_, count)| *count == 0)
+        );
+        assert!(
+            Delivery::most_active_chats(db.pool(), 5)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_count_days_without_deliveries() {
+        let (db, temp) = init_database().await;
+        let today = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        assert_eq!(
+            Delivery::count_per_day(db.pool(), today, 3).await.unwrap(),
+            vec![
+                ("2026-10-19".to_string(), 0),
+                ("2026-10-18".to_string(), 0),
+                ("2026-10-17".to_string(), 0)
+            ]
+        );
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_aggregate_deliveries() {
+        let (db, temp) = init_database().await;
+        let deliveries = [
+            Delivery::new(
+                ChatId(1),
+                "good_morning",
+                "BuonGiorno",
+                Some("augurando".to_string()),
+                None,
+            ),
+            Delivery::new(
+                ChatId(1),
+                "good_night",
+                "BuonaNotte",
+                Some("augurando".to_string()),
+                None,
+            )
+            .failed("telegram error"),
+            Delivery::new(
+                ChatId(1),
+                "good_lunch",
+                "BuonPranzo",
+                Some("ticondivido".to_string()),
+                None,
+            ),
+            Delivery::new(
+                ChatId(2),
+                "good_lunch",
+                "BuonPranzo",
+                Some("ticondivido".to_string()),
+                None,
+            ),
+        ];
+        for delivery in deliveries.iter() {
+            assert!(delivery.insert(db.pool()).await.is_ok());
+        }
+        let today = Utc::now().date_naive();
+        let per_day = Delivery::count_per_day(db.pool(), today, 7).await.unwrap();
+        assert_eq!(per_day.len(), 7);
+        assert_eq!(per_day[0], (today.to_string(), 3));
+        assert!(per_day[1..].iter().all(|(_, count)| *count == 0));
+        // send failures are not counted against the provider
+        assert_eq!(
+            Delivery::count_per_provider(db.pool()).await.unwrap(),
+            vec![
+                ("augurando".to_string(), 1, 0),
+                ("ticondivido".to_string(), 2, 0)
+            ]
+        );
+        assert_eq!(
+            Delivery::most_active_chats(db.pool(), 10).await.unwrap(),
+            vec![(1, 2), (2, 1)]
+        );
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_delete_deliveries_by_chat() {
+        let (db, temp) = init_database().await;
+        for chat in [ChatId(1), ChatId(2)] {
+            assert!(
+                Delivery::new(chat, "good_morning", "BuonGiorno", None, None)
+                    .insert(db.pool())
+                    .await
+                    .is_ok()
+            );
+        }
+        assert!(Delivery::delete_by_chat(db.pool(), ChatId(1)).await.is_ok());
+        assert_eq!(
+            Delivery::most_active_chats(db.pool(), 5).await.unwrap(),
+            vec![(2, 1)]
+        );
+        drop(temp)
+    }
+}
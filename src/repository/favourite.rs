@@ -0,0 +1,233 @@
+//! # Favourite
+//!
+//! this module contains the favourite entity repository, which stores the greeting images saved by the users
+
+use chrono::Utc;
+use sqlx::{Pool, Sqlite};
+use teloxide::types::UserId;
+use tracing::debug;
+
+use super::{RepositoryError, RepositoryResult};
+
+#[derive(sqlx::FromRow, Debug, Clone, Eq, PartialEq)]
+pub struct Favourite {
+    id: i64,
+    user_id: i64,
+    greeting: String,
+    file_id: String,
+    kind: String,
+    created_at: String,
+}
+
+impl Favourite {
+    /// Create the favourite of `user` for the telegram file `file_id` of `greeting`, which is a media of `kind`
+    pub fn new(
+        user: UserId,
+        greeting: impl ToString,
+        file_id: impl ToString,
+        kind: impl ToString,
+    ) -> Self {
+        Self {
+            id: 0,
+            user_id: user.0 as i64,
+            greeting: greeting.to_string(),
+            file_id: file_id.to_string(),
+            kind: kind.to_string(),
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Return the keyword of the greeting of the image
+    pub fn greeting(&self) -> &str {
+        &self.greeting
+    }
+
+    pub fn file_id(&self) -> &str {
+        &self.file_id
+    }
+
+    /// Return the kind of media of the file
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Insert `Favourite` to database; returns whether it has been inserted,
+    /// since a file can be saved only once by the same user
+    pub async fn insert(&self, db: &Pool<Sqlite>) -> RepositoryResult<bool> {
+        debug!(
+            "inserting a new favourite of user {} for {} to repository",
+            self.user_id, self.greeting
+        );
+        let rows = sqlx::query(
+            r#"INSERT INTO favourite (user_id, greeting, file_id, kind, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, file_id) DO NOTHING"#,
+        )
+        .bind(self.user_id)
+        .bind(&self.greeting)
+        .bind(&self.file_id)
+        .bind(&self.kind)
+        .bind(&self.created_at)
+        .execute(db)
+        .await
+        .map_err(RepositoryError::from)?
+        .rows_affected();
+
+        Ok(rows == 1)
+    }
+
+    /// Get the favourite `id` of `user`, if any
+    pub async fn get(
+        db: &Pool<Sqlite>,
+        user: UserId,
+        id: i64,
+    ) -> RepositoryResult<Option<Favourite>> {
+        sqlx::query_as(
+            r#"
+            SELECT id, user_id, greeting, file_id, kind, created_at
+            FROM favourite
+            WHERE id = $1 AND user_id = $2"#,
+        )
+        .bind(id)
+        .bind(user.0 as i64)
+        .fetch_optional(db)
+        .await
+        .map_err(RepositoryError::from)
+    }
+
+    /// Get up to `limit` favourites of `user`, skipping the first `offset`, most recent first
+    pub async fn get_by_user(
+        db: &Pool<Sqlite>,
+        user: UserId,
+        limit: i64,
+        offset: i64,
+    ) -> RepositoryResult<Vec<Favourite>> {
+        sqlx::query_as(
+            r#"
+            SELECT id, user_id, greeting, file_id, kind, created_at
+            FROM favourite
+            WHERE user_id = $1
+            ORDER BY id DESC
+            LIMIT $2 OFFSET $3"#,
+        )
+        .bind(user.0 as i64)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(db)
+        .await
+        .map_err(RepositoryError::from)
+    }
+
+    /// Count the favourites of `user`
+    pub async fn count_by_user(db: &Pool<Sqlite>, user: UserId) -> RepositoryResult<i64> {
+        let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM favourite WHERE user_id = $1")
+            .bind(user.0 as i64)
+            .fetch_one(db)
+            .await
+            .map_err(RepositoryError::from)?;
+        Ok(row.0)
+    }
+
+    /// Delete the favourites of `user` from database
+    pub async fn delete_by_user(db: &Pool<Sqlite>, user: UserId) -> RepositoryResult<()> {
+        debug!("deleting favourites of user {} from repository", user);
+        sqlx::query("DELETE FROM favourite WHERE user_id = $1")
+            .bind(user.0 as i64)
+            .execute(db)
+            .await
+            .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repository::test::init_database;
+
+    #[tokio::test]
+    async fn should_insert_and_page_favourites() {
+        let (db, temp) = init_database().await;
+        for file_id in ["AgACAgQAAx", "AgACAgQAAy", "AgACAgQAAz"] {
+            assert!(
+                Favourite::new(UserId(42), "notte", file_id, "image")
+                    .insert(db.pool())
+                    .await
+                    .unwrap()
+            );
+        }
+        // already saved
+        assert!(
+            !Favourite::new(UserId(42), "notte", "AgACAgQAAx", "image")
+                .insert(db.pool())
+                .await
+                .unwrap()
+        );
+        assert!(
+            Favourite::new(UserId(7), "notte", "AgACAgQAAx", "image")
+                .insert(db.pool())
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            Favourite::count_by_user(db.pool(), UserId(42))
+                .await
+                .unwrap(),
+            3
+        );
+        let page = Favourite::get_by_user(db.pool(), UserId(42), 2, 0)
+            .await
+            .unwrap();
+        let file_ids: Vec<&str> = page.iter().map(Favourite::file_id).collect();
+        assert_eq!(file_ids, vec!["AgACAgQAAz", "AgACAgQAAy"]);
+        let page = Favourite::get_by_user(db.pool(), UserId(42), 2, 2)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        let favourite = Favourite::get(db.pool(), UserId(42), page[0].id())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(favourite.greeting(), "notte");
+        assert_eq!(favourite.kind(), "image");
+        // favourites of other users can't be got
+        assert!(
+            Favourite::get(db.pool(), UserId(7), page[0].id())
+                .await
+                .unwrap()
+                .is_none()
+        );
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_delete_favourites_by_user() {
+        let (db, temp) = init_database().await;
+        assert!(
+            Favourite::new(UserId(42), "notte", "AgACAgQAAx", "image")
+                .insert(db.pool())
+                .await
+                .unwrap()
+        );
+        assert!(
+            Favourite::delete_by_user(db.pool(), UserId(42))
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            Favourite::count_by_user(db.pool(), UserId(42))
+                .await
+                .unwrap(),
+            0
+        );
+        drop(temp)
+    }
+}
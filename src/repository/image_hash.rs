@@ -0,0 +1,236 @@
+//! # Image hash
+//!
+//! this module contains the image hash entity repository, which stores the perceptual hash of the scraped images
+//! and when they have been sent the last time
+
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Sqlite};
+use tracing::debug;
+
+use super::delivery::DeliveryStatus;
+use super::{RepositoryError, RepositoryResult};
+
+#[derive(sqlx::FromRow, Debug, Clone, Eq, PartialEq)]
+pub struct ImageHash {
+    url: String,
+    provider: String,
+    /// The perceptual hash, stored with the same bits of the `u64`
+    hash: i64,
+    created_at: String,
+}
+
+impl ImageHash {
+    pub fn new(url: impl ToString, provider: impl ToString, hash: u64) -> Self {
+        Self {
+            url: url.to_string(),
+            provider: provider.to_string(),
+            hash: hash as i64,
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Insert `ImageHash` to database, replacing the hash previously computed for the same url
+    pub async fn upsert(&self, db: &Pool<Sqlite>) -> RepositoryResult<()> {
+        debug!("setting hash {:016x} for image {}", self.hash, self.url);
+        sqlx::query(
+            r#"INSERT INTO image_hash (url, provider, hash, created_at) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (url) DO UPDATE SET hash = excluded.hash, created_at = excluded.created_at"#,
+        )
+        .bind(&self.url)
+        .bind(&self.provider)
+        .bind(self.hash)
+        .bind(&self.created_at)
+        .execute(db)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    /// Record that the image at `url` has been sent at `sent_at`; images without hash are ignored
+    pub async fn mark_sent(
+        db: &Pool<Sqlite>,
+        url: &str,
+        sent_at: DateTime<Utc>,
+    ) -> RepositoryResult<()> {
+        debug!("marking image {} as sent", url);
+        sqlx::query("UPDATE image_hash SET sent_at = $1 WHERE url = $2")
+            .bind(sent_at.to_rfc3339())
+            .bind(url)
+            .execute(db)
+            .await
+            .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    /// Get the hashes of the images sent since `since`, either on request or by the scheduled deliveries
+    pub async fn get_sent_since(
+        db: &Pool<Sqlite>,
+        since: DateTime<Utc>,
+    ) -> RepositoryResult<Vec<u64>> {
+        let rows: Vec<(i64,)> = sqlx::query_as(
+            r#"
+            SELECT hash FROM image_hash WHERE sent_at >= $2
+            UNION
+            SELECT image_hash.hash
+            FROM image_hash
+            JOIN delivery ON delivery.image_url = image_hash.url
+            WHERE delivery.status = $1 AND delivery.created_at >= $2"#,
+        )
+        .bind(DeliveryStatus::Sent.as_str())
+        .bind(since.to_rfc3339())
+        .fetch_all(db)
+        .await
+        .map_err(RepositoryError::from)?;
+        Ok(rows.into_iter().map(|(hash,)| hash as u64).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use chrono::Duration;
+    use pretty_assertions::assert_eq;
+    use teloxide::types::ChatId;
+
+    use super::*;
+    use crate::repository::delivery::Delivery;
+    use crate::repository::test::init_database;
+
+    const URL: &str = "https://example.com/caffe.jpg";
+
+    #[tokio::test]
+    async fn should_upsert_image_hash() {
+        let (db, temp) = init_database().await;
+        assert!(
+            ImageHash::new(URL, "augurando", 42)
+                .upsert(db.pool())
+                .await
+                .is_ok()
+        );
+        assert!(
+            ImageHash::new(URL, "augurando", u64::MAX)
+                .upsert(db.pool())
+                .await
+                .is_ok()
+        );
+        assert!(
+            Delivery::new(
+                ChatId(1),
+                "good_morning",
+                "BuonGiorno",
+                None,
+                Some(URL.to_string())
+            )
+            .insert(db.pool())
+            .await
+            .is_ok()
+        );
+        assert_eq!(
+            ImageHash::get_sent_since(db.pool(), Utc::now() - Duration::days(1))
+                .await
+                .unwrap(),
+            vec![u64::MAX]
+        );
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_get_delivered_hashes() {
+        let (db, temp) = init_database().await;
+        let other = "https://example.com/notte.jpg";
+        for (url, hash) in [(URL, 42), (other, 7)] {
+            assert!(
+                ImageHash::new(url, "augurando", hash)
+                    .upsert(db.pool())
+                    .await
+                    .is_ok()
+            );
+        }
+        let deliveries = [
+            Delivery::new(
+                ChatId(1),
+                "good_morning",
+                "BuonGiorno",
+                Some("augurando".to_string()),
+                Some(URL.to_string()),
+            ),
+            Delivery::new(
+                ChatId(2),
+                "good_morning",
+                "BuonGiorno",
+                Some("augurando".to_string()),
+                Some(URL.to_string()),
+            ),
+            Delivery::new(
+                ChatId(1),
+                "good_night",
+                "BuonaNotte",
+                Some("augurando".to_string()),
+                Some(other.to_string()),
+            )
+            .failed("telegram error"),
+        ];
+        for delivery in deliveries.iter() {
+            assert!(delivery.insert(db.pool()).await.is_ok());
+        }
+        let yesterday = Utc::now() - Duration::days(1);
+        assert_eq!(
+            ImageHash::get_sent_since(db.pool(), yesterday)
+                .await
+                .unwrap(),
+            vec![42]
+        );
+        let tomorrow = Utc::now() + Duration::days(1);
+        assert!(
+            ImageHash::get_sent_since(db.pool(), tomorrow)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_get_hashes_sent_on_request() {
+        let (db, temp) = init_database().await;
+        assert!(
+            ImageHash::new(URL, "augurando", 42)
+                .upsert(db.pool())
+                .await
+                .is_ok()
+        );
+        let yesterday = Utc::now() - Duration::days(1);
+        assert!(
+            ImageHash::get_sent_since(db.pool(), yesterday)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            ImageHash::mark_sent(db.pool(), URL, Utc::now())
+                .await
+                .is_ok()
+        );
+        // unknown images are ignored
+        assert!(
+            ImageHash::mark_sent(db.pool(), "https://example.com/notte.jpg", Utc::now())
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            ImageHash::get_sent_since(db.pool(), yesterday)
+                .await
+                .unwrap(),
+            vec![42]
+        );
+        assert!(
+            ImageHash::get_sent_since(db.pool(), Utc::now() + Duration::days(1))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        drop(temp)
+    }
+}
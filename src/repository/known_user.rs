@@ -0,0 +1,171 @@
+//! # Known user
+//!
+//! this module contains the known user entity repository, which stores the usernames of the users who wrote to the bot,
+//! so that the users mentioned with their `@username` can be resolved
+
+use chrono::Utc;
+use sqlx::{Pool, Sqlite};
+use teloxide::types::UserId;
+use tracing::debug;
+
+use super::{RepositoryError, RepositoryResult};
+
+#[derive(sqlx::FromRow, Debug, Clone, Eq, PartialEq)]
+pub struct KnownUser {
+    user_id: i64,
+    /// The username, lowercase and without the leading `@`
+    username: String,
+    name: String,
+    updated_at: String,
+}
+
+impl KnownUser {
+    /// Create the known user `user`, with `username` and full `name`
+    pub fn new(user: UserId, username: &str, name: impl ToString) -> Self {
+        Self {
+            user_id: user.0 as i64,
+            username: Self::normalize(username),
+            name: name.to_string(),
+            updated_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    pub fn user_id(&self) -> UserId {
+        UserId(self.user_id as u64)
+    }
+
+    /// Return the full name of the user
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Insert `KnownUser` to database, replacing the username and the name previously known.
+    /// Usernames can be given away, so the username is taken from the user who had it before
+    pub async fn upsert(&self, db: &Pool<Sqlite>) -> RepositoryResult<()> {
+        debug!(
+            "setting username {} for user {}",
+            self.username, self.user_id
+        );
+        sqlx::query("DELETE FROM known_user WHERE username = $1 AND user_id <> $2")
+            .bind(&self.username)
+            .bind(self.user_id)
+            .execute(db)
+            .await
+            .map_err(RepositoryError::from)?;
+        sqlx::query(
+            r#"INSERT INTO known_user (user_id, username, name, updated_at) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id) DO UPDATE SET username = excluded.username, name = excluded.name, updated_at = excluded.updated_at"#,
+        )
+        .bind(self.user_id)
+        .bind(&self.username)
+        .bind(&self.name)
+        .bind(&self.updated_at)
+        .execute(db)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    /// Get the user with `username`, with or without the leading `@`, if known
+    pub async fn get_by_username(
+        db: &Pool<Sqlite>,
+        username: &str,
+    ) -> RepositoryResult<Option<KnownUser>> {
+        sqlx::query_as(
+            r#"
+            SELECT user_id, username, name, updated_at
+            FROM known_user
+            WHERE username = $1"#,
+        )
+        .bind(Self::normalize(username))
+        .fetch_optional(db)
+        .await
+        .map_err(RepositoryError::from)
+    }
+
+    /// Delete `user` from database
+    pub async fn delete_by_user(db: &Pool<Sqlite>, user: UserId) -> RepositoryResult<()> {
+        debug!("deleting known user {} from repository", user);
+        sqlx::query("DELETE FROM known_user WHERE user_id = $1")
+            .bind(user.0 as i64)
+            .execute(db)
+            .await
+            .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    /// Usernames are case insensitive
+    fn normalize(username: &str) -> String {
+        username.trim_start_matches('@').to_lowercase()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repository::test::init_database;
+
+    #[tokio::test]
+    async fn should_resolve_usernames() {
+        let (db, temp) = init_database().await;
+        assert!(
+            KnownUser::new(UserId(42), "MarioRossi", "Mario Rossi")
+                .upsert(db.pool())
+                .await
+                .is_ok()
+        );
+        let user = KnownUser::get_by_username(db.pool(), "@mariorossi")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(user.user_id(), UserId(42));
+        assert_eq!(user.name(), "Mario Rossi");
+        assert!(
+            KnownUser::get_by_username(db.pool(), "luigi")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        // the username changes owner
+        assert!(
+            KnownUser::new(UserId(7), "mariorossi", "Mario Bianchi")
+                .upsert(db.pool())
+                .await
+                .is_ok()
+        );
+        let user = KnownUser::get_by_username(db.pool(), "mariorossi")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(user.user_id(), UserId(7));
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_delete_known_user() {
+        let (db, temp) = init_database().await;
+        assert!(
+            KnownUser::new(UserId(42), "mariorossi", "Mario Rossi")
+                .upsert(db.pool())
+                .await
+                .is_ok()
+        );
+        assert!(
+            KnownUser::delete_by_user(db.pool(), UserId(42))
+                .await
+                .is_ok()
+        );
+        assert!(
+            KnownUser::get_by_username(db.pool(), "mariorossi")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        drop(temp)
+    }
+}
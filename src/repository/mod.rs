@@ -4,6 +4,14 @@
 
 pub mod birthday;
 pub mod chat;
+pub mod daily_greeting;
+pub mod delivery;
+pub mod favourite;
+pub mod image_hash;
+pub mod known_user;
+pub mod setting;
+pub mod submission;
+pub mod vote;
 
 use sqlx::sqlite::SqlitePool;
 use thiserror::Error;
@@ -49,11 +57,34 @@ impl SqliteDb {
         &self.pool
     }
 
+    /// Check whether the database is reachable
+    pub async fn ping(&self) -> RepositoryResult<()> {
+        sqlx::query("SELECT 1")
+            .execute(self.pool())
+            .await
+            .map_err(RepositoryError::from)
+            .map(|_| ())
+    }
+
+    /// Close the connection pool, waiting for the connections to be released
+    pub async fn close(&self) {
+        info!("closing database");
+        self.pool.close().await;
+    }
+
     /// Init db tables
     async fn init_tables(&self) -> RepositoryResult<()> {
         debug!("initializing tables");
         self.init_chat_table().await?;
-        self.init_birthday_table().await
+        self.init_birthday_table().await?;
+        self.init_delivery_table().await?;
+        self.init_image_hash_table().await?;
+        self.init_submission_table().await?;
+        self.init_favourite_table().await?;
+        self.init_vote_table().await?;
+        self.init_daily_greeting_table().await?;
+        self.init_known_user_table().await?;
+        self.init_setting_table().await
     }
 
     async fn init_birthday_table(&self) -> RepositoryResult<()> {
@@ -64,6 +95,167 @@ impl SqliteDb {
             chat INTEGER NOT NULL,
             name TEXT NOT NULL,
             date TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            user_id INTEGER
+          );"#,
+        )
+        .execute(self.pool())
+        .await
+        .map_err(RepositoryError::from)?;
+        // added after the first release
+        self.add_column_if_missing("birthday", "user_id", "INTEGER")
+            .await
+    }
+
+    /// Add a column to a table created by a previous release, if the column doesn't exist yet
+    async fn add_column_if_missing(
+        &self,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> RepositoryResult<()> {
+        let row: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM pragma_table_info($1) WHERE name = $2")
+                .bind(table)
+                .bind(column)
+                .fetch_one(self.pool())
+                .await
+                .map_err(RepositoryError::from)?;
+        if row.0 > 0 {
+            return Ok(());
+        }
+        debug!("adding column {column} to table {table}");
+        sqlx::query(&format!(
+            "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+        ))
+        .execute(self.pool())
+        .await
+        .map_err(RepositoryError::from)
+        .map(|_| ())
+    }
+
+    async fn init_delivery_table(&self) -> RepositoryResult<()> {
+        debug!("creating delivery table");
+        sqlx::query(
+            r#"CREATE TABLE IF NOT EXISTS delivery (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            chat INTEGER,
+            job TEXT NOT NULL,
+            greeting TEXT NOT NULL,
+            provider TEXT,
+            image_url TEXT,
+            status TEXT NOT NULL,
+            error TEXT,
+            created_at TEXT NOT NULL
+          );"#,
+        )
+        .execute(self.pool())
+        .await
+        .map_err(RepositoryError::from)
+        .map(|_| ())
+    }
+
+    async fn init_image_hash_table(&self) -> RepositoryResult<()> {
+        debug!("creating image hash table");
+        sqlx::query(
+            r#"CREATE TABLE IF NOT EXISTS image_hash (
+            url TEXT PRIMARY KEY,
+            provider TEXT NOT NULL,
+            hash INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            sent_at TEXT
+          );"#,
+        )
+        .execute(self.pool())
+        .await
+        .map_err(RepositoryError::from)?;
+        // added after the first release
+        self.add_column_if_missing("image_hash", "sent_at", "TEXT")
+            .await
+    }
+
+    async fn init_submission_table(&self) -> RepositoryResult<()> {
+        debug!("creating submission table");
+        sqlx::query(
+            r#"CREATE TABLE IF NOT EXISTS submission (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            chat INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            greeting TEXT NOT NULL,
+            file_id TEXT NOT NULL,
+            status TEXT NOT NULL,
+            created_at TEXT NOT NULL
+          );"#,
+        )
+        .execute(self.pool())
+        .await
+        .map_err(RepositoryError::from)
+        .map(|_| ())
+    }
+
+    async fn init_favourite_table(&self) -> RepositoryResult<()> {
+        debug!("creating favourite table");
+        sqlx::query(
+            r#"CREATE TABLE IF NOT EXISTS favourite (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            greeting TEXT NOT NULL,
+            file_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            UNIQUE (user_id, file_id)
+          );"#,
+        )
+        .execute(self.pool())
+        .await
+        .map_err(RepositoryError::from)
+        .map(|_| ())
+    }
+
+    async fn init_vote_table(&self) -> RepositoryResult<()> {
+        debug!("creating vote table");
+        sqlx::query(
+            r#"CREATE TABLE IF NOT EXISTS vote (
+            chat INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            image INTEGER NOT NULL,
+            value INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (chat, user_id, image)
+          );"#,
+        )
+        .execute(self.pool())
+        .await
+        .map_err(RepositoryError::from)
+        .map(|_| ())
+    }
+
+    async fn init_known_user_table(&self) -> RepositoryResult<()> {
+        debug!("creating known user table");
+        sqlx::query(
+            r#"CREATE TABLE IF NOT EXISTS known_user (
+            user_id INTEGER PRIMARY KEY,
+            username TEXT NOT NULL UNIQUE,
+            name TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+          );"#,
+        )
+        .execute(self.pool())
+        .await
+        .map_err(RepositoryError::from)
+        .map(|_| ())
+    }
+
+    async fn init_daily_greeting_table(&self) -> RepositoryResult<()> {
+        debug!("creating daily greeting table");
+        sqlx::query(
+            r#"CREATE TABLE IF NOT EXISTS daily_greeting (
+            date TEXT PRIMARY KEY,
+            use_weekday INTEGER NOT NULL,
+            provider TEXT,
+            image_url TEXT,
+            kind TEXT,
+            hash INTEGER,
             created_at TEXT NOT NULL
           );"#,
         )
@@ -73,6 +265,22 @@ impl SqliteDb {
         .map(|_| ())
     }
 
+    async fn init_setting_table(&self) -> RepositoryResult<()> {
+        debug!("creating chat setting table");
+        sqlx::query(
+            r#"CREATE TABLE IF NOT EXISTS chat_setting (
+            chat INTEGER NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            PRIMARY KEY (chat, key)
+          );"#,
+        )
+        .execute(self.pool())
+        .await
+        .map_err(RepositoryError::from)
+        .map(|_| ())
+    }
+
     async fn init_chat_table(&self) -> RepositoryResult<()> {
         debug!("creating chat table");
         sqlx::query(
@@ -100,6 +308,39 @@ mod test {
         let _ = init_database().await;
     }
 
+    #[tokio::test]
+    async fn should_add_missing_column() {
+        let (db, temp) = init_database().await;
+        sqlx::query("CREATE TABLE legacy (id INTEGER PRIMARY KEY)")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        assert!(
+            db.add_column_if_missing("legacy", "name", "TEXT")
+                .await
+                .is_ok()
+        );
+        // idempotent
+        assert!(
+            db.add_column_if_missing("legacy", "name", "TEXT")
+                .await
+                .is_ok()
+        );
+        assert!(
+            sqlx::query("SELECT name FROM legacy")
+                .execute(db.pool())
+                .await
+                .is_ok()
+        );
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_ping_database() {
+        let (db, _temp) = init_database().await;
+        assert!(db.ping().await.is_ok());
+    }
+
     pub async fn init_database() -> (SqliteDb, NamedTempFile) {
         let temp = NamedTempFile::new().expect("failed to create tempfile");
         let pool = SqliteDb::connect(&temp.path().to_string_lossy())
@@ -0,0 +1,183 @@
+//! # Setting
+//!
+//! this module contains the chat setting entity repository
+
+use sqlx::{Pool, Sqlite};
+use teloxide::types::ChatId;
+use tracing::debug;
+
+use super::{RepositoryError, RepositoryResult};
+
+/// A key/value setting of a chat
+#[derive(sqlx::FromRow, Debug, Clone, Eq, PartialEq)]
+pub struct Setting {
+    chat: i64,
+    key: String,
+    value: String,
+}
+
+impl Setting {
+    pub fn new(chat_id: ChatId, key: &str, value: impl ToString) -> Self {
+        Self {
+            chat: chat_id.0,
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    /// Get the value of the setting `key` of `chat`, if set
+    pub async fn get(
+        db: &Pool<Sqlite>,
+        chat: ChatId,
+        key: &str,
+    ) -> RepositoryResult<Option<String>> {
+        let row: Option<(String,)> =
+            sqlx::query_as("SELECT value FROM chat_setting WHERE chat = $1 AND key = $2")
+                .bind(chat.0)
+                .bind(key)
+                .fetch_optional(db)
+                .await
+                .map_err(RepositoryError::from)?;
+        Ok(row.map(|(value,)| value))
+    }
+
+    /// Insert `Setting` to database, replacing the previous value
+    pub async fn upsert(&self, db: &Pool<Sqlite>) -> RepositoryResult<()> {
+        debug!(
+            "setting {} = {} for chat {} in repository",
+            self.key, self.value, self.chat
+        );
+        sqlx::query(
+            r#"INSERT INTO chat_setting (chat, key, value) VALUES ($1, $2, $3)
+            ON CONFLICT (chat, key) DO UPDATE SET value = excluded.value"#,
+        )
+        .bind(self.chat)
+        .bind(&self.key)
+        .bind(&self.value)
+        .execute(db)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    /// Delete the setting `key` of `chat` from database
+    pub async fn delete(db: &Pool<Sqlite>, chat: ChatId, key: &str) -> RepositoryResult<()> {
+        debug!("deleting setting {} for chat {} from repository", key, chat);
+        sqlx::query("DELETE FROM chat_setting WHERE chat = $1 AND key = $2")
+            .bind(chat.0)
+            .bind(key)
+            .execute(db)
+            .await
+            .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    /// Delete the settings of `chat` from database
+    pub async fn delete_by_chat(db: &Pool<Sqlite>, chat: ChatId) -> RepositoryResult<()> {
+        debug!("deleting settings for chat {} from repository", chat);
+        sqlx::query("DELETE FROM chat_setting WHERE chat = $1")
+            .bind(chat.0)
+            .execute(db)
+            .await
+            .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repository::test::init_database;
+
+    #[tokio::test]
+    async fn should_set_and_get_setting() {
+        let (db, temp) = init_database().await;
+        assert_eq!(
+            Setting::get(db.pool(), ChatId(1), "language")
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(
+            Setting::new(ChatId(1), "language", "en")
+                .upsert(db.pool())
+                .await
+                .is_ok()
+        );
+        assert!(
+            Setting::new(ChatId(1), "language", "nap")
+                .upsert(db.pool())
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            Setting::get(db.pool(), ChatId(1), "language")
+                .await
+                .unwrap(),
+            Some("nap".to_string())
+        );
+        assert_eq!(
+            Setting::get(db.pool(), ChatId(2), "language")
+                .await
+                .unwrap(),
+            None
+        );
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_delete_setting() {
+        let (db, temp) = init_database().await;
+        for key in ["language", "caption.notte"] {
+            assert!(
+                Setting::new(ChatId(1), key, "en")
+                    .upsert(db.pool())
+                    .await
+                    .is_ok()
+            );
+        }
+        assert!(
+            Setting::delete(db.pool(), ChatId(1), "caption.notte")
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            Setting::get(db.pool(), ChatId(1), "caption.notte")
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            Setting::get(db.pool(), ChatId(1), "language")
+                .await
+                .unwrap(),
+            Some("en".to_string())
+        );
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_delete_settings_by_chat() {
+        let (db, temp) = init_database().await;
+        assert!(
+            Setting::new(ChatId(1), "language", "en")
+                .upsert(db.pool())
+                .await
+                .is_ok()
+        );
+        assert!(Setting::delete_by_chat(db.pool(), ChatId(1)).await.is_ok());
+        assert_eq!(
+            Setting::get(db.pool(), ChatId(1), "language")
+                .await
+                .unwrap(),
+            None
+        );
+        drop(temp)
+    }
+}
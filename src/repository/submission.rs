@@ -0,0 +1,223 @@
+//! # Submission
+//!
+//! this module contains the submission entity repository, which stores the greeting images proposed by the users
+
+use chrono::Utc;
+use sqlx::{Pool, Sqlite};
+use teloxide::types::{ChatId, UserId};
+use tracing::debug;
+
+use super::{RepositoryError, RepositoryResult};
+
+/// Review status of a submission
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SubmissionStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl SubmissionStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Rejected => "rejected",
+        }
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, Eq, PartialEq)]
+pub struct Submission {
+    id: i64,
+    chat: i64,
+    user_id: i64,
+    greeting: String,
+    file_id: String,
+    status: String,
+    created_at: String,
+}
+
+impl Submission {
+    /// Create a pending submission of the telegram file `file_id` for `greeting`, proposed by `user` in `chat`
+    pub fn new(
+        chat_id: ChatId,
+        user: UserId,
+        greeting: impl ToString,
+        file_id: impl ToString,
+    ) -> Self {
+        Self {
+            id: 0,
+            chat: chat_id.0,
+            user_id: user.0 as i64,
+            greeting: greeting.to_string(),
+            file_id: file_id.to_string(),
+            status: SubmissionStatus::Pending.as_str().to_string(),
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Return the chat the submission was proposed in
+    pub fn chat(&self) -> ChatId {
+        ChatId(self.chat)
+    }
+
+    /// Return the keyword of the greeting of the submitted image
+    pub fn greeting(&self) -> &str {
+        &self.greeting
+    }
+
+    /// Insert `Submission` to database, returning its id
+    pub async fn insert(&self, db: &Pool<Sqlite>) -> RepositoryResult<i64> {
+        debug!(
+            "inserting a new submission of user {} for {} to repository",
+            self.user_id, self.greeting
+        );
+        let result = sqlx::query(
+            r#"INSERT INTO submission (chat, user_id, greeting, file_id, status, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)"#,
+        )
+        .bind(self.chat)
+        .bind(self.user_id)
+        .bind(&self.greeting)
+        .bind(&self.file_id)
+        .bind(&self.status)
+        .bind(&self.created_at)
+        .execute(db)
+        .await
+        .map_err(RepositoryError::from)?;
+        if result.rows_affected() != 1 {
+            return Err(RepositoryError::TooManyInserts);
+        }
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Get the submission with `id`, if any
+    pub async fn get(db: &Pool<Sqlite>, id: i64) -> RepositoryResult<Option<Submission>> {
+        sqlx::query_as(
+            r#"
+            SELECT id, chat, user_id, greeting, file_id, status, created_at
+            FROM submission
+            WHERE id = $1"#,
+        )
+        .bind(id)
+        .fetch_optional(db)
+        .await
+        .map_err(RepositoryError::from)
+    }
+
+    /// Set the status of the pending submission `id`; returns whether the submission was pending
+    pub async fn review(
+        db: &Pool<Sqlite>,
+        id: i64,
+        status: SubmissionStatus,
+    ) -> RepositoryResult<bool> {
+        debug!("setting submission {} as {}", id, status.as_str());
+        let rows = sqlx::query("UPDATE submission SET status = $1 WHERE id = $2 AND status = $3")
+            .bind(status.as_str())
+            .bind(id)
+            .bind(SubmissionStatus::Pending.as_str())
+            .execute(db)
+            .await
+            .map_err(RepositoryError::from)?
+            .rows_affected();
+
+        Ok(rows == 1)
+    }
+
+    /// Get the file ids of the approved submissions for the greeting `keyword`
+    pub async fn get_approved(db: &Pool<Sqlite>, keyword: &str) -> RepositoryResult<Vec<String>> {
+        let rows: Vec<(String,)> =
+            sqlx::query_as("SELECT file_id FROM submission WHERE greeting = $1 AND status = $2")
+                .bind(keyword)
+                .bind(SubmissionStatus::Approved.as_str())
+                .fetch_all(db)
+                .await
+                .map_err(RepositoryError::from)?;
+        Ok(rows.into_iter().map(|(file_id,)| file_id).collect())
+    }
+
+    /// Delete the submissions of `user` from database
+    pub async fn delete_by_user(db: &Pool<Sqlite>, user: UserId) -> RepositoryResult<()> {
+        debug!("deleting submissions of user {} from repository", user);
+        sqlx::query("DELETE FROM submission WHERE user_id = $1")
+            .bind(user.0 as i64)
+            .execute(db)
+            .await
+            .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repository::test::init_database;
+
+    #[tokio::test]
+    async fn should_review_submission() {
+        let (db, temp) = init_database().await;
+        let id = Submission::new(ChatId(1), UserId(42), "notte", "AgACAgQAAx")
+            .insert(db.pool())
+            .await
+            .unwrap();
+        let submission = Submission::get(db.pool(), id).await.unwrap().unwrap();
+        assert_eq!(submission.id(), id);
+        assert_eq!(submission.chat(), ChatId(1));
+        assert_eq!(submission.greeting(), "notte");
+        assert!(
+            Submission::get_approved(db.pool(), "notte")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            Submission::review(db.pool(), id, SubmissionStatus::Approved)
+                .await
+                .unwrap()
+        );
+        // already reviewed
+        assert!(
+            !Submission::review(db.pool(), id, SubmissionStatus::Rejected)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            Submission::get_approved(db.pool(), "notte").await.unwrap(),
+            vec!["AgACAgQAAx".to_string()]
+        );
+        assert!(
+            Submission::get_approved(db.pool(), "pranzo")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(Submission::get(db.pool(), id + 1).await.unwrap().is_none());
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_delete_submissions_by_user() {
+        let (db, temp) = init_database().await;
+        let id = Submission::new(ChatId(1), UserId(42), "notte", "AgACAgQAAx")
+            .insert(db.pool())
+            .await
+            .unwrap();
+        assert!(
+            Submission::delete_by_user(db.pool(), UserId(42))
+                .await
+                .is_ok()
+        );
+        assert!(Submission::get(db.pool(), id).await.unwrap().is_none());
+        drop(temp)
+    }
+}
@@ -0,0 +1,124 @@
+//! # Vote
+//!
+//! this module contains the vote entity repository, which stores the votes given to the greeting images in the chats
+
+use chrono::Utc;
+use sqlx::{Pool, Sqlite};
+use teloxide::types::{ChatId, UserId};
+use tracing::debug;
+
+use super::{RepositoryError, RepositoryResult};
+
+#[derive(sqlx::FromRow, Debug, Clone, Eq, PartialEq)]
+pub struct Vote {
+    chat: i64,
+    user_id: i64,
+    /// The key of the image, stored with the same bits of the `u64`
+    image: i64,
+    /// 1 for an upvote, -1 for a downvote
+    value: i64,
+    created_at: String,
+}
+
+impl Vote {
+    /// Create the vote of `user` in `chat` for the image with key `image`
+    pub fn new(chat_id: ChatId, user: UserId, image: u64, up: bool) -> Self {
+        Self {
+            chat: chat_id.0,
+            user_id: user.0 as i64,
+            image: image as i64,
+            value: if up { 1 } else { -1 },
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Insert `Vote` to database, replacing the vote previously given by the user to the image in the chat
+    pub async fn upsert(&self, db: &Pool<Sqlite>) -> RepositoryResult<()> {
+        debug!(
+            "setting vote {} of user {} for image {:016x} in {}",
+            self.value, self.user_id, self.image, self.chat
+        );
+        sqlx::query(
+            r#"INSERT INTO vote (chat, user_id, image, value, created_at) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (chat, user_id, image) DO UPDATE SET value = excluded.value, created_at = excluded.created_at"#,
+        )
+        .bind(self.chat)
+        .bind(self.user_id)
+        .bind(self.image)
+        .bind(self.value)
+        .bind(&self.created_at)
+        .execute(db)
+        .await
+        .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    /// Get the score of every voted image, as the sum of its votes in all the chats
+    pub async fn get_scores(db: &Pool<Sqlite>) -> RepositoryResult<Vec<(u64, i64)>> {
+        let rows: Vec<(i64, i64)> =
+            sqlx::query_as("SELECT image, SUM(value) FROM vote GROUP BY image")
+                .fetch_all(db)
+                .await
+                .map_err(RepositoryError::from)?;
+        Ok(rows
+            .into_iter()
+            .map(|(image, score)| (image as u64, score))
+            .collect())
+    }
+
+    /// Delete the votes of `user` from database
+    pub async fn delete_by_user(db: &Pool<Sqlite>, user: UserId) -> RepositoryResult<()> {
+        debug!("deleting votes of user {} from repository", user);
+        sqlx::query("DELETE FROM vote WHERE user_id = $1")
+            .bind(user.0 as i64)
+            .execute(db)
+            .await
+            .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repository::test::init_database;
+
+    #[tokio::test]
+    async fn should_aggregate_votes() {
+        let (db, temp) = init_database().await;
+        for vote in [
+            Vote::new(ChatId(1), UserId(1), u64::MAX, true),
+            Vote::new(ChatId(1), UserId(2), u64::MAX, true),
+            Vote::new(ChatId(2), UserId(1), u64::MAX, true),
+            Vote::new(ChatId(1), UserId(1), 42, true),
+            // the user changes their mind
+            Vote::new(ChatId(1), UserId(1), 42, false),
+            Vote::new(ChatId(1), UserId(2), 42, false),
+        ] {
+            assert!(vote.upsert(db.pool()).await.is_ok());
+        }
+        let mut scores = Vote::get_scores(db.pool()).await.unwrap();
+        scores.sort();
+        assert_eq!(scores, vec![(42, -2), (u64::MAX, 3)]);
+        drop(temp)
+    }
+
+    #[tokio::test]
+    async fn should_delete_votes_by_user() {
+        let (db, temp) = init_database().await;
+        assert!(
+            Vote::new(ChatId(1), UserId(42), 7, true)
+                .upsert(db.pool())
+                .await
+                .is_ok()
+        );
+        assert!(Vote::delete_by_user(db.pool(), UserId(42)).await.is_ok());
+        assert!(Vote::get_scores(db.pool()).await.unwrap().is_empty());
+        drop(temp)
+    }
+}
@@ -0,0 +1,377 @@
+//! # Date
+//!
+//! Date parsing utils, for dates written in italian or in english
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Italian month names
+pub const MONTHS: [&str; 12] = [
+    "gennaio",
+    "febbraio",
+    "marzo",
+    "aprile",
+    "maggio",
+    "giugno",
+    "luglio",
+    "agosto",
+    "settembre",
+    "ottobre",
+    "novembre",
+    "dicembre",
+];
+
+/// English month names, accepted when parsing dates too
+pub const ENGLISH_MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Italian weekday names, starting from monday
+pub const WEEKDAYS: [&str; 7] = [
+    "lunedì",
+    "martedì",
+    "mercoledì",
+    "giovedì",
+    "venerdì",
+    "sabato",
+    "domenica",
+];
+
+/// Minimum length of an abbreviated month name (e.g. `mar`)
+const MONTH_ABBREVIATION_LEN: usize = 3;
+
+/// A date which may lack the year
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialDate {
+    pub day: u32,
+    pub month: u32,
+    pub year: Option<i32>,
+}
+
+impl PartialDate {
+    /// Create a new partial date, if `day` and `month` are a valid day of the year (and of `year` if known)
+    pub fn new(day: u32, month: u32, year: Option<i32>) -> Option<Self> {
+        // 2000 is a leap year, so the 29th of february is accepted if the year is unknown
+        NaiveDate::from_ymd_opt(year.unwrap_or(2000), month, day)?;
+        Some(Self { day, month, year })
+    }
+
+    /// Check whether the date falls on the same day of the year of `date`.
+    /// In the years without the 29th of february, its anniversary falls on the 28th
+    pub fn is_anniversary_of(&self, date: NaiveDate) -> bool {
+        if self.day == 29 && self.month == 2 && !date.leap_year() {
+            return date.day() == 28 && date.month() == 2;
+        }
+        self.day == date.day() && self.month == date.month()
+    }
+
+    /// Format the date as ISO 8601; the year is omitted if unknown (e.g. `--03-12`)
+    pub fn to_iso_string(self) -> String {
+        match self.year {
+            Some(year) => format!("{year:04}-{:02}-{:02}", self.month, self.day),
+            None => format!("--{:02}-{:02}", self.month, self.day),
+        }
+    }
+}
+
+impl From<NaiveDate> for PartialDate {
+    fn from(date: NaiveDate) -> Self {
+        Self {
+            day: date.day(),
+            month: date.month(),
+            year: Some(date.year()),
+        }
+    }
+}
+
+impl fmt::Display for PartialDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.day, MONTHS[self.month as usize - 1])?;
+        if let Some(year) = self.year {
+            write!(f, " {year}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse an ISO 8601 date, as formatted by [`PartialDate::to_iso_string`]
+impl FromStr for PartialDate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("invalid date {s}");
+        if let Some(month_day) = s.strip_prefix("--") {
+            let (month, day) = month_day.split_once('-').ok_or_else(error)?;
+            let month = month.parse().map_err(|_| error())?;
+            let day = day.parse().map_err(|_| error())?;
+            return Self::new(day, month, None).ok_or_else(error);
+        }
+        NaiveDate::from_str(s).map(Self::from).map_err(|_| error())
+    }
+}
+
+impl Serialize for PartialDate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_iso_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for PartialDate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Self::from_str(&s).map_err(D::Error::custom)
+    }
+}
+
+/// Parse a date, with the month name either in italian or in english.
+///
+/// Supported formats are:
+///
+/// - `12/03/1950`, `12-03-1950` or `12.03.1950`
+/// - `12/03` or `12-03`
+/// - `1950-03-12`
+/// - `12 marzo 1950`, `12 marzo`, `12 March 1950` or `12th March`
+/// - `12 mar 1950` or `12 mar`
+pub fn parse_date(text: &str) -> Option<PartialDate> {
+    let text = text.trim().to_lowercase();
+    if text.contains(char::is_whitespace) {
+        parse_textual_date(&text)
+    } else {
+        parse_numeric_date(&text)
+    }
+}
+
+/// Parse a date made of numbers only, such as `12/03/1950` or `12-03`
+fn parse_numeric_date(text: &str) -> Option<PartialDate> {
+    let parts: Vec<&str> = text.split(['/', '-', '.']).collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        return None;
+    }
+    match parts.as_slice() {
+        [year, month, day] if year.len() == 4 => PartialDate::new(
+            day.parse().ok()?,
+            month.parse().ok()?,
+            Some(parse_year(year)?),
+        ),
+        [day, month, year] => PartialDate::new(
+            day.parse().ok()?,
+            month.parse().ok()?,
+            Some(parse_year(year)?),
+        ),
+        [day, month] => PartialDate::new(day.parse().ok()?, month.parse().ok()?, None),
+        _ => None,
+    }
+}
+
+/// Parse a date with the month name, such as `12 marzo 1950`, `12 mar` or `1st March`
+fn parse_textual_date(text: &str) -> Option<PartialDate> {
+    let mut tokens = text.split_whitespace();
+    let day = parse_day(tokens.next()?)?;
+    let month = parse_month(tokens.next()?)?;
+    let year = match tokens.next() {
+        Some(year) => Some(parse_year(year)?),
+        None => None,
+    };
+    if tokens.next().is_some() {
+        return None;
+    }
+
+    PartialDate::new(day, month, year)
+}
+
+/// Parse the day of the month, which may be written as an ordinal (e.g. `1°` or `1st`)
+fn parse_day(text: &str) -> Option<u32> {
+    let text = text.trim_end_matches(['°', 'º']);
+    ["st", "nd", "rd", "th"]
+        .iter()
+        .find_map(|suffix| text.strip_suffix(suffix))
+        .unwrap_or(text)
+        .parse()
+        .ok()
+}
+
+/// Parse a four digits year
+fn parse_year(text: &str) -> Option<i32> {
+    if text.len() != 4 {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// Parse an italian or english month name, either full or abbreviated (e.g. `mar`, `sett.` or `oct`)
+fn parse_month(text: &str) -> Option<u32> {
+    let text = text.trim_end_matches('.');
+    if text.chars().count() < MONTH_ABBREVIATION_LEN {
+        return None;
+    }
+    MONTHS
+        .iter()
+        .position(|month| month.starts_with(text))
+        .or_else(|| {
+            ENGLISH_MONTHS
+                .iter()
+                .position(|month| month.to_lowercase().starts_with(text))
+        })
+        .map(|index| index as u32 + 1)
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_parse_numeric_dates() {
+        assert_eq!(
+            parse_date("12/03/1950"),
+            PartialDate::new(12, 3, Some(1950))
+        );
+        assert_eq!(
+            parse_date("12-03-1950"),
+            PartialDate::new(12, 3, Some(1950))
+        );
+        assert_eq!(
+            parse_date("12.03.1950"),
+            PartialDate::new(12, 3, Some(1950))
+        );
+        assert_eq!(parse_date("1/3/1950"), PartialDate::new(1, 3, Some(1950)));
+        assert_eq!(
+            parse_date("1950-03-12"),
+            PartialDate::new(12, 3, Some(1950))
+        );
+    }
+
+    #[test]
+    fn should_parse_numeric_dates_without_year() {
+        assert_eq!(parse_date("12-03"), PartialDate::new(12, 3, None));
+        assert_eq!(parse_date("12/03"), PartialDate::new(12, 3, None));
+        assert_eq!(parse_date("29/02"), PartialDate::new(29, 2, None));
+    }
+
+    #[test]
+    fn should_parse_dates_with_month_name() {
+        assert_eq!(
+            parse_date("12 marzo 1950"),
+            PartialDate::new(12, 3, Some(1950))
+        );
+        assert_eq!(parse_date(" 1 Gennaio "), PartialDate::new(1, 1, None));
+        assert_eq!(parse_date("1° maggio"), PartialDate::new(1, 5, None));
+        assert_eq!(parse_date("29 febbraio"), PartialDate::new(29, 2, None));
+    }
+
+    #[test]
+    fn should_parse_dates_with_abbreviated_month_name() {
+        assert_eq!(parse_date("12 mar"), PartialDate::new(12, 3, None));
+        assert_eq!(
+            parse_date("12 mar 1950"),
+            PartialDate::new(12, 3, Some(1950))
+        );
+        assert_eq!(parse_date("3 sett."), PartialDate::new(3, 9, None));
+        for (index, month) in MONTHS.iter().enumerate() {
+            assert_eq!(
+                parse_date(&format!("10 {}", &month[..MONTH_ABBREVIATION_LEN])),
+                PartialDate::new(10, index as u32 + 1, None)
+            );
+        }
+    }
+
+    #[test]
+    fn should_parse_dates_with_english_month_name() {
+        assert_eq!(
+            parse_date("12 March 1950"),
+            PartialDate::new(12, 3, Some(1950))
+        );
+        assert_eq!(parse_date("1st May"), PartialDate::new(1, 5, None));
+        assert_eq!(parse_date("22nd oct"), PartialDate::new(22, 10, None));
+        assert_eq!(parse_date("3rd Aug."), PartialDate::new(3, 8, None));
+        for (index, month) in ENGLISH_MONTHS.iter().enumerate() {
+            assert_eq!(
+                parse_date(&format!("10th {month}")),
+                PartialDate::new(10, index as u32 + 1, None)
+            );
+            assert_eq!(
+                parse_date(&format!("10 {}", &month[..MONTH_ABBREVIATION_LEN])),
+                PartialDate::new(10, index as u32 + 1, None)
+            );
+        }
+    }
+
+    #[test]
+    fn should_not_parse_invalid_dates() {
+        assert!(parse_date("").is_none());
+        assert!(parse_date("31/02/1950").is_none());
+        assert!(parse_date("32/01").is_none());
+        assert!(parse_date("12/13").is_none());
+        assert!(parse_date("12/03/50").is_none());
+        assert!(parse_date("12//1950").is_none());
+        assert!(parse_date("12/03/1950/1").is_none());
+        assert!(parse_date("30 febbraio").is_none());
+        assert!(parse_date("29 febbraio 1950").is_none());
+        assert!(parse_date("12 ma").is_none());
+        assert!(parse_date("12 brumaio").is_none());
+        assert!(parse_date("12xx marzo").is_none());
+        assert!(parse_date("12 marzo 1950 pippo").is_none());
+        assert!(parse_date("marzo 12").is_none());
+        assert!(parse_date("domani").is_none());
+    }
+
+    #[test]
+    fn should_check_anniversary() {
+        let date = PartialDate::new(12, 3, None).unwrap();
+        assert!(date.is_anniversary_of(NaiveDate::from_ymd_opt(2024, 3, 12).unwrap()));
+        assert!(!date.is_anniversary_of(NaiveDate::from_ymd_opt(2024, 3, 13).unwrap()));
+    }
+
+    #[test]
+    fn should_check_leap_day_anniversary() {
+        let date = PartialDate::new(29, 2, None).unwrap();
+        assert!(date.is_anniversary_of(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()));
+        assert!(!date.is_anniversary_of(NaiveDate::from_ymd_opt(2024, 2, 28).unwrap()));
+        assert!(date.is_anniversary_of(NaiveDate::from_ymd_opt(2025, 2, 28).unwrap()));
+        assert!(!date.is_anniversary_of(NaiveDate::from_ymd_opt(2025, 3, 1).unwrap()));
+        // the 28th of february is still its own anniversary
+        let date = PartialDate::new(28, 2, None).unwrap();
+        assert!(date.is_anniversary_of(NaiveDate::from_ymd_opt(2025, 2, 28).unwrap()));
+    }
+
+    #[test]
+    fn should_convert_to_and_from_iso_string() {
+        let date = PartialDate::new(12, 3, Some(1950)).unwrap();
+        assert_eq!(date.to_iso_string(), "1950-03-12");
+        assert_eq!(PartialDate::from_str("1950-03-12"), Ok(date));
+        let date = PartialDate::new(2, 11, None).unwrap();
+        assert_eq!(date.to_iso_string(), "--11-02");
+        assert_eq!(PartialDate::from_str("--11-02"), Ok(date));
+        assert!(PartialDate::from_str("--13-02").is_err());
+        assert!(PartialDate::from_str("12/03/1950").is_err());
+    }
+
+    #[test]
+    fn should_display_date() {
+        assert_eq!(
+            PartialDate::new(12, 3, Some(1950)).unwrap().to_string(),
+            "12 marzo 1950"
+        );
+        assert_eq!(
+            PartialDate::new(1, 12, None).unwrap().to_string(),
+            "1 dicembre"
+        );
+    }
+}
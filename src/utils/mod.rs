@@ -2,4 +2,7 @@
 //!
 //! Utilities module
 
+pub mod date;
 pub mod random;
+pub mod saints;
+pub mod shutdown;
@@ -0,0 +1,438 @@
+//! # Saints
+//!
+//! The saint of the day, according to the italian calendar
+
+use chrono::{Datelike, NaiveDate};
+
+/// The saints celebrated in each day of the year, by month
+const SAINTS: [&[&str]; 12] = [
+    &[
+        "Maria Santissima Madre di Dio",
+        "Santi Basilio e Gregorio",
+        "Santissimo Nome di Gesù",
+        "Sant'Angela da Foligno",
+        "San Giovanni Nepomuceno Neumann",
+        "Epifania del Signore",
+        "San Raimondo di Peñafort",
+        "San Severino",
+        "Sant'Adriano di Canterbury",
+        "Sant'Aldo",
+        "Sant'Igino",
+        "San Bernardo da Corleone",
+        "Sant'Ilario di Poitiers",
+        "San Felice da Nola",
+        "San Mauro abate",
+        "San Marcello I papa",
+        "Sant'Antonio abate",
+        "Santa Margherita d'Ungheria",
+        "San Mario",
+        "San Sebastiano",
+        "Sant'Agnese",
+        "San Vincenzo",
+        "Sant'Emerenziana",
+        "San Francesco di Sales",
+        "Conversione di San Paolo",
+        "Santi Timoteo e Tito",
+        "Sant'Angela Merici",
+        "San Tommaso d'Aquino",
+        "San Valerio",
+        "Santa Martina",
+        "San Giovanni Bosco",
+    ],
+    &[
+        "Santa Verdiana",
+        "Presentazione del Signore",
+        "San Biagio",
+        "Sant'Andrea Corsini",
+        "Sant'Agata",
+        "San Paolo Miki e compagni",
+        "San Riccardo",
+        "San Girolamo Emiliani",
+        "Santa Apollonia",
+        "Santa Scolastica",
+        "Beata Vergine Maria di Lourdes",
+        "Sant'Eulalia",
+        "Santa Fosca",
+        "San Valentino",
+        "Santi Faustino e Giovita",
+        "Santa Giuliana",
+        "Santi Sette Fondatori dei Servi di Maria",
+        "San Simone",
+        "San Corrado Confalonieri",
+        "Santi Francesco e Giacinta Marto",
+        "San Pier Damiani",
+        "Cattedra di San Pietro",
+        "San Policarpo",
+        "San Modesto",
+        "San Cesario di Nazianzo",
+        "San Porfirio",
+        "San Gabriele dell'Addolorata",
+        "San Romano abate",
+        "Sant'Osvaldo",
+    ],
+    &[
+        "Sant'Albino",
+        "Sant'Agnese di Boemia",
+        "Santa Cunegonda",
+        "San Casimiro",
+        "Sant'Olivia",
+        "Santa Coletta",
+        "Sante Perpetua e Felicita",
+        "San Giovanni di Dio",
+        "Santa Francesca Romana",
+        "San Simplicio",
+        "San Costantino",
+        "San Massimiliano",
+        "San Leandro",
+        "Santa Matilde",
+        "Santa Luisa de Marillac",
+        "Sant'Eriberto",
+        "San Patrizio",
+        "San Cirillo di Gerusalemme",
+        "San Giuseppe",
+        "Sant'Alessandra",
+        "San Benedetto",
+        "Santa Lea",
+        "San Turibio de Mogrovejo",
+        "Santa Caterina di Svezia",
+        "Annunciazione del Signore",
+        "Sant'Emanuele",
+        "Sant'Augusta",
+        "San Sisto III papa",
+        "San Secondo",
+        "San Leonardo Murialdo",
+        "San Beniamino",
+    ],
+    &[
+        "Sant'Ugo",
+        "San Francesco di Paola",
+        "San Riccardo di Chichester",
+        "Sant'Isidoro",
+        "San Vincenzo Ferrer",
+        "San Celestino",
+        "San Giovanni Battista de La Salle",
+        "San Dionigi",
+        "Santa Maria di Cleofa",
+        "Sant'Ezechiele",
+        "San Stanislao",
+        "San Giulio I papa",
+        "San Martino I papa",
+        "Santa Liduina",
+        "San Damiano de Veuster",
+        "Santa Bernadette",
+        "Sant'Aniceto",
+        "San Galdino",
+        "San Leone IX papa",
+        "Sant'Agnese di Montepulciano",
+        "Sant'Anselmo",
+        "San Leonida",
+        "San Giorgio",
+        "San Fedele da Sigmaringen",
+        "San Marco evangelista",
+        "Santi Cleto e Marcellino",
+        "Santa Zita",
+        "Santa Gianna Beretta Molla",
+        "Santa Caterina da Siena",
+        "San Pio V papa",
+    ],
+    &[
+        "San Giuseppe lavoratore",
+        "Sant'Atanasio",
+        "Santi Filippo e Giacomo",
+        "San Floriano",
+        "San Gottardo",
+        "San Domenico Savio",
+        "Santa Flavia Domitilla",
+        "San Vittore",
+        "San Pacomio",
+        "San Giovanni d'Avila",
+        "San Fabio",
+        "Santi Nereo e Achilleo",
+        "Beata Vergine Maria di Fatima",
+        "San Mattia",
+        "Sant'Isidoro agricoltore",
+        "Sant'Ubaldo",
+        "San Pasquale Baylon",
+        "San Giovanni I papa",
+        "San Celestino V papa",
+        "San Bernardino da Siena",
+        "San Vittorio",
+        "Santa Rita da Cascia",
+        "San Desiderio",
+        "Beata Vergine Maria Ausiliatrice",
+        "San Beda il Venerabile",
+        "San Filippo Neri",
+        "Sant'Agostino di Canterbury",
+        "Sant'Emilio",
+        "San Paolo VI papa",
+        "Santa Giovanna d'Arco",
+        "Visitazione della Beata Vergine Maria",
+    ],
+    &[
+        "San Giustino",
+        "Santi Marcellino e Pietro",
+        "San Carlo Lwanga e compagni",
+        "San Quirino",
+        "San Bonifacio",
+        "San Norberto",
+        "Sant'Antonio Maria Gianelli",
+        "San Medardo",
+        "Sant'Efrem",
+        "Beata Diana d'Andalò",
+        "San Barnaba",
+        "Sant'Onofrio",
+        "Sant'Antonio di Padova",
+        "Sant'Eliseo",
+        "San Vito",
+        "Santi Quirico e Giulitta",
+        "San Ranieri",
+        "San Gregorio Barbarigo",
+        "San Romualdo",
+        "San Silverio",
+        "San Luigi Gonzaga",
+        "San Paolino da Nola",
+        "San Giuseppe Cafasso",
+        "Natività di San Giovanni Battista",
+        "San Guglielmo da Vercelli",
+        "San Josemaría Escrivá",
+        "San Cirillo d'Alessandria",
+        "Sant'Ireneo",
+        "Santi Pietro e Paolo",
+        "Santi Primi Martiri della Chiesa di Roma",
+    ],
+    &[
+        "Sant'Aronne",
+        "San Bernardino Realino",
+        "San Tommaso apostolo",
+        "Santa Elisabetta di Portogallo",
+        "Sant'Antonio Maria Zaccaria",
+        "Santa Maria Goretti",
+        "San Claudio",
+        "Santi Aquila e Priscilla",
+        "Santa Veronica Giuliani",
+        "Santa Rufina",
+        "San Benedetto da Norcia",
+        "San Giovanni Gualberto",
+        "Sant'Enrico",
+        "San Camillo de Lellis",
+        "San Bonaventura",
+        "Beata Vergine Maria del Monte Carmelo",
+        "Sant'Alessio",
+        "San Federico",
+        "Santa Macrina",
+        "Sant'Apollinare",
+        "San Lorenzo da Brindisi",
+        "Santa Maria Maddalena",
+        "Santa Brigida",
+        "Santa Cristina",
+        "San Giacomo apostolo",
+        "Santi Gioacchino e Anna",
+        "San Pantaleone",
+        "Santi Nazario e Celso",
+        "Santa Marta",
+        "San Pietro Crisologo",
+        "Sant'Ignazio di Loyola",
+    ],
+    &[
+        "Sant'Alfonso Maria de' Liguori",
+        "Sant'Eusebio di Vercelli",
+        "Santa Lidia",
+        "San Giovanni Maria Vianney",
+        "Dedicazione della Basilica di Santa Maria Maggiore",
+        "Trasfigurazione del Signore",
+        "San Gaetano da Thiene",
+        "San Domenico",
+        "Santa Teresa Benedetta della Croce",
+        "San Lorenzo",
+        "Santa Chiara",
+        "Santa Giovanna Francesca de Chantal",
+        "Santi Ponziano e Ippolito",
+        "San Massimiliano Maria Kolbe",
+        "Assunzione della Beata Vergine Maria",
+        "San Rocco",
+        "San Giacinto",
+        "Sant'Elena",
+        "San Giovanni Eudes",
+        "San Bernardo di Chiaravalle",
+        "San Pio X papa",
+        "Beata Vergine Maria Regina",
+        "Santa Rosa da Lima",
+        "San Bartolomeo apostolo",
+        "San Ludovico",
+        "Sant'Alessandro di Bergamo",
+        "Santa Monica",
+        "Sant'Agostino",
+        "Martirio di San Giovanni Battista",
+        "San Felice martire",
+        "San Raimondo Nonnato",
+    ],
+    &[
+        "Sant'Egidio",
+        "Sant'Elpidio",
+        "San Gregorio Magno",
+        "Santa Rosalia",
+        "Santa Teresa di Calcutta",
+        "San Petronio",
+        "Santa Regina",
+        "Natività della Beata Vergine Maria",
+        "San Pietro Claver",
+        "San Nicola da Tolentino",
+        "Santi Proto e Giacinto",
+        "Santissimo Nome di Maria",
+        "San Giovanni Crisostomo",
+        "Esaltazione della Santa Croce",
+        "Beata Vergine Maria Addolorata",
+        "Santi Cornelio e Cipriano",
+        "San Roberto Bellarmino",
+        "San Giuseppe da Copertino",
+        "San Gennaro",
+        "Santi Andrea Kim Taegon e compagni",
+        "San Matteo apostolo",
+        "San Maurizio",
+        "San Pio da Pietrelcina",
+        "San Pacifico da San Severino",
+        "San Cleofa",
+        "Santi Cosma e Damiano",
+        "San Vincenzo de' Paoli",
+        "San Venceslao",
+        "Santi Michele, Gabriele e Raffaele arcangeli",
+        "San Girolamo",
+    ],
+    &[
+        "Santa Teresa di Gesù Bambino",
+        "Santi Angeli custodi",
+        "San Gerardo",
+        "San Francesco d'Assisi",
+        "Santa Faustina Kowalska",
+        "San Bruno",
+        "Beata Vergine Maria del Rosario",
+        "San Giovanni Calabria",
+        "San Dionigi",
+        "San Daniele Comboni",
+        "San Giovanni XXIII papa",
+        "San Serafino da Montegranaro",
+        "Sant'Edoardo",
+        "San Callisto I papa",
+        "Santa Teresa d'Avila",
+        "Santa Margherita Maria Alacoque",
+        "Sant'Ignazio di Antiochia",
+        "San Luca evangelista",
+        "San Paolo della Croce",
+        "Santa Maria Bertilla Boscardin",
+        "Sant'Orsola",
+        "San Giovanni Paolo II papa",
+        "San Giovanni da Capestrano",
+        "Sant'Antonio Maria Claret",
+        "Santi Crisante e Daria",
+        "Sant'Evaristo",
+        "San Frumenzio",
+        "Santi Simone e Giuda apostoli",
+        "San Narciso",
+        "San Germano",
+        "Sant'Alfonso Rodriguez",
+    ],
+    &[
+        "Tutti i Santi",
+        "Commemorazione dei defunti",
+        "San Martino de Porres",
+        "San Carlo Borromeo",
+        "Santi Zaccaria ed Elisabetta",
+        "San Leonardo",
+        "Sant'Ernesto",
+        "San Goffredo",
+        "Dedicazione della Basilica Lateranense",
+        "San Leone Magno",
+        "San Martino di Tours",
+        "San Giosafat",
+        "San Diego",
+        "San Giocondo",
+        "Sant'Alberto Magno",
+        "Santa Margherita di Scozia",
+        "Santa Elisabetta d'Ungheria",
+        "Dedicazione delle Basiliche dei Santi Pietro e Paolo",
+        "San Fausto",
+        "Sant'Ottavio",
+        "Presentazione della Beata Vergine Maria",
+        "Santa Cecilia",
+        "San Clemente I papa",
+        "Santi Andrea Dung-Lac e compagni",
+        "Santa Caterina d'Alessandria",
+        "San Leonardo da Porto Maurizio",
+        "San Virgilio",
+        "San Giacomo della Marca",
+        "San Saturnino",
+        "Sant'Andrea apostolo",
+    ],
+    &[
+        "Sant'Eligio",
+        "Santa Bibiana",
+        "San Francesco Saverio",
+        "Santa Barbara",
+        "San Saba",
+        "San Nicola",
+        "Sant'Ambrogio",
+        "Immacolata Concezione",
+        "San Juan Diego",
+        "Beata Vergine Maria di Loreto",
+        "San Damaso I papa",
+        "Beata Vergine Maria di Guadalupe",
+        "Santa Lucia",
+        "San Giovanni della Croce",
+        "Santa Virginia Centurione Bracelli",
+        "Sant'Adelaide",
+        "San Lazzaro",
+        "San Malachia",
+        "Sant'Anastasio I papa",
+        "San Domenico di Silos",
+        "San Pietro Canisio",
+        "Santa Francesca Saverio Cabrini",
+        "San Giovanni da Kęty",
+        "Sant'Adele",
+        "Natale del Signore",
+        "Santo Stefano",
+        "San Giovanni apostolo",
+        "Santi Innocenti martiri",
+        "San Tommaso Becket",
+        "Sant'Eugenio",
+        "San Silvestro I papa",
+    ],
+];
+
+/// Get the saint celebrated on `date`
+pub fn saint_of_the_day(date: NaiveDate) -> &'static str {
+    SAINTS[date.month0() as usize][date.day0() as usize]
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_have_a_saint_for_every_day() {
+        for (month, saints) in SAINTS.iter().enumerate() {
+            let days = NaiveDate::from_ymd_opt(2024, month as u32 + 1, 1)
+                .unwrap()
+                .num_days_in_month();
+            assert_eq!(saints.len(), days as usize, "month {}", month + 1);
+        }
+    }
+
+    #[test]
+    fn should_get_saint_of_the_day() {
+        assert_eq!(
+            saint_of_the_day(NaiveDate::from_ymd_opt(2026, 10, 4).unwrap()),
+            "San Francesco d'Assisi"
+        );
+        assert_eq!(
+            saint_of_the_day(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()),
+            "Sant'Osvaldo"
+        );
+        assert_eq!(
+            saint_of_the_day(NaiveDate::from_ymd_opt(2025, 12, 31).unwrap()),
+            "San Silvestro I papa"
+        );
+    }
+}